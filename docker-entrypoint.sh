@@ -0,0 +1,16 @@
+#!/bin/sh
+set -e
+
+# The server only starts once the universe is imported from the SDE. Put
+# `sde.zip` and `checksum` from CCP into the data volume, they are imported
+# on every start, which only writes what changed.
+if [ "$#" -eq 0 ] || [ "$1" = "serve" ]; then
+    if [ -f /app/data/sde.zip ]; then
+        /app/noice2 import-sde /app/data/sde.zip
+    else
+        echo "No SDE at /app/data/sde.zip, skipping the import." >&2
+        echo "The server does not start until the SDE is imported: download sde.zip and checksum into the data volume and restart." >&2
+    fi
+fi
+
+exec /app/noice2 "$@"
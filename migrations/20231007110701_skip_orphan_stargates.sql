@@ -0,0 +1,12 @@
+-- Add migration script here
+-- The next migration inserts stargates from an old SDE. A new database has no
+-- systems yet, those stargates would fail their foreign keys, so they are
+-- skipped and come from import-sde instead. The trigger only lives as long as
+-- the connection running the migrations.
+CREATE TEMP TRIGGER IF NOT EXISTS skip_orphan_stargates
+BEFORE INSERT ON main.eve_stargates
+WHEN NOT EXISTS (SELECT 1 FROM main.eve_system WHERE id = NEW.source_system_id)
+    OR NOT EXISTS (SELECT 1 FROM main.eve_system WHERE id = NEW.target_system_id)
+BEGIN
+    SELECT RAISE(IGNORE);
+END;
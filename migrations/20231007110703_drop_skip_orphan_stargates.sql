@@ -0,0 +1,2 @@
+-- Add migration script here
+DROP TRIGGER IF EXISTS temp.skip_orphan_stargates;
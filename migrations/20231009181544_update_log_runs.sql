@@ -0,0 +1,17 @@
+-- Add migration script here
+DROP TABLE IF EXISTS update_log;
+
+CREATE TABLE IF NOT EXISTS update_log (
+    id INTEGER PRIMARY KEY,
+    region_id INTEGER NOT NULL REFERENCES eve_region(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'running',
+    started DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished DATETIME,
+    pages_fetched INTEGER NOT NULL DEFAULT 0,
+    rows_inserted INTEGER NOT NULL DEFAULT 0,
+    rows_deactivated INTEGER NOT NULL DEFAULT 0,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS update_log_region_kind ON update_log (region_id, kind, started);
@@ -0,0 +1,7 @@
+-- Add migration script here
+ALTER TABLE market_orders ADD COLUMN location_id INTEGER;
+ALTER TABLE market_orders ADD COLUMN range TEXT;
+ALTER TABLE market_orders ADD COLUMN min_volume INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE market_orders ADD COLUMN duration INTEGER;
+
+CREATE INDEX IF NOT EXISTS market_orders_location ON market_orders (location_id, active);
@@ -0,0 +1,3 @@
+-- Add migration script here
+CREATE INDEX IF NOT EXISTS market_history_item_region_date ON market_history (item_id, region_id, date);
+CREATE INDEX IF NOT EXISTS market_history_region_date ON market_history (region_id, date);
@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE eve_items ADD COLUMN volume REAL;
+ALTER TABLE eve_items ADD COLUMN packaged_volume REAL;
+ALTER TABLE eve_items ADD COLUMN portion_size INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE eve_items ADD COLUMN base_price REAL;
+ALTER TABLE eve_items ADD COLUMN mass REAL;
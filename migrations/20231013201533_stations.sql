@@ -0,0 +1,18 @@
+-- Add migration script here
+CREATE TABLE eve_station (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    system_id INTEGER NOT NULL REFERENCES eve_system(id) ON DELETE CASCADE,
+    corporation_id INTEGER NOT NULL,
+    type_id INTEGER NOT NULL,
+    reprocessing_efficiency REAL NOT NULL,
+    reprocessing_stations_take REAL NOT NULL
+);
+
+CREATE INDEX eve_station_system ON eve_station(system_id);
+
+CREATE TABLE eve_station_services (
+    station_id INTEGER NOT NULL REFERENCES eve_station(id) ON DELETE CASCADE,
+    service TEXT NOT NULL,
+    PRIMARY KEY (station_id, service)
+);
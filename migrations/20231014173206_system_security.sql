@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE eve_constellation (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    region_id INTEGER NOT NULL REFERENCES eve_region(id) ON DELETE CASCADE
+);
+
+ALTER TABLE eve_system ADD COLUMN constellation_id INTEGER REFERENCES eve_constellation(id) ON DELETE SET NULL;
+ALTER TABLE eve_system ADD COLUMN security REAL;
+ALTER TABLE eve_system ADD COLUMN security_rounded REAL;
+ALTER TABLE eve_system ADD COLUMN security_class TEXT;
+ALTER TABLE eve_system ADD COLUMN x REAL;
+ALTER TABLE eve_system ADD COLUMN y REAL;
+ALTER TABLE eve_system ADD COLUMN z REAL;
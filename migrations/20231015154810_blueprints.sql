@@ -0,0 +1,39 @@
+-- Add migration script here
+CREATE TABLE eve_blueprint (
+    id INTEGER PRIMARY KEY,
+    max_production_limit INTEGER NOT NULL
+);
+
+CREATE TABLE eve_blueprint_activity (
+    blueprint_id INTEGER NOT NULL REFERENCES eve_blueprint(id) ON DELETE CASCADE,
+    activity TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    PRIMARY KEY (blueprint_id, activity)
+);
+
+CREATE TABLE eve_blueprint_material (
+    blueprint_id INTEGER NOT NULL REFERENCES eve_blueprint(id) ON DELETE CASCADE,
+    activity TEXT NOT NULL,
+    type_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    PRIMARY KEY (blueprint_id, activity, type_id)
+);
+
+CREATE TABLE eve_blueprint_product (
+    blueprint_id INTEGER NOT NULL REFERENCES eve_blueprint(id) ON DELETE CASCADE,
+    activity TEXT NOT NULL,
+    type_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    probability REAL,
+    PRIMARY KEY (blueprint_id, activity, type_id)
+);
+
+CREATE INDEX eve_blueprint_product_type ON eve_blueprint_product(type_id, activity);
+
+CREATE TABLE eve_blueprint_skill (
+    blueprint_id INTEGER NOT NULL REFERENCES eve_blueprint(id) ON DELETE CASCADE,
+    activity TEXT NOT NULL,
+    skill_id INTEGER NOT NULL,
+    level INTEGER NOT NULL,
+    PRIMARY KEY (blueprint_id, activity, skill_id)
+);
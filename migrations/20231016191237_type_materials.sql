@@ -0,0 +1,7 @@
+-- Add migration script here
+CREATE TABLE type_materials (
+    type_id INTEGER NOT NULL,
+    material_type_id INTEGER NOT NULL,
+    quantity INTEGER NOT NULL,
+    PRIMARY KEY (type_id, material_type_id)
+);
@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE TABLE eve_categories (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    published BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+ALTER TABLE eve_groups ADD COLUMN category_id INTEGER REFERENCES eve_categories(id) ON DELETE SET NULL;
+ALTER TABLE eve_groups ADD COLUMN published BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE eve_market_groups ADD COLUMN parent_id INTEGER REFERENCES eve_market_groups(id) ON DELETE SET NULL;
+ALTER TABLE eve_market_groups ADD COLUMN description TEXT;
+ALTER TABLE eve_market_groups ADD COLUMN has_types BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE eve_market_groups ADD COLUMN icon_id INTEGER;
+
+CREATE INDEX eve_market_groups_parent ON eve_market_groups(parent_id);
+CREATE INDEX eve_groups_category ON eve_groups(category_id);
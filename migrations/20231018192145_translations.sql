@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE eve_translations (
+    kind TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    language TEXT NOT NULL,
+    name TEXT NOT NULL,
+    -- Lowercased in Rust, SQLite only folds the case of ASCII characters.
+    name_lower TEXT NOT NULL,
+    PRIMARY KEY (kind, id, language)
+);
+
+CREATE INDEX eve_translations_name ON eve_translations(kind, name_lower);
@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE eve_meta_groups (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+);
+
+ALTER TABLE eve_items ADD COLUMN meta_group_id INTEGER REFERENCES eve_meta_groups(id) ON DELETE SET NULL;
+ALTER TABLE eve_items ADD COLUMN meta_level INTEGER;
+ALTER TABLE eve_items ADD COLUMN tech_level INTEGER;
+
+CREATE INDEX eve_items_meta_group ON eve_items(meta_group_id);
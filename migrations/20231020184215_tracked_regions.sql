@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS tracked_regions (
+    region_id INTEGER NOT NULL PRIMARY KEY REFERENCES eve_region(id) ON DELETE CASCADE,
+    history BOOLEAN NOT NULL DEFAULT TRUE,
+    orders BOOLEAN NOT NULL DEFAULT TRUE,
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    added DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
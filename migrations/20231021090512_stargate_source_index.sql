@@ -0,0 +1,2 @@
+-- Add migration script here
+CREATE INDEX IF NOT EXISTS eve_stargates_source ON eve_stargates(source_system_id);
@@ -0,0 +1,8 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS tracked_structures (
+    structure_id INTEGER NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    system_id INTEGER NOT NULL REFERENCES eve_system(id) ON DELETE CASCADE,
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    added DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
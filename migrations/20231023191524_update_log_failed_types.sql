@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE update_log ADD COLUMN failed_types INTEGER NOT NULL DEFAULT 0;
@@ -1,15 +1,51 @@
-use crate::esi::errors::EsiError;
+use crate::{esi::errors::EsiError, ids::RegionId, repository::RunStats};
+use std::fmt::Display;
 
-mod update_orders;
 mod update_history;
+mod update_orders;
 
-pub use update_orders::update_order_for_region;
 pub use update_history::update_history_for_region;
+pub use update_orders::update_order_for_region;
 
+/// Why a run failed, with what it fetched and saved until then.
 #[derive(Debug)]
 pub enum UpdateError {
-    MarketHistorySql(sqlx::Error, usize),
-    MarketHistoryEsi(EsiError, usize),
-    UpdateOrderSql(sqlx::Error, usize),
-    UpdateOrderEsi(EsiError, usize),
+    MarketHistorySql(sqlx::Error, RegionId, RunStats),
+    MarketHistoryEsi(EsiError, RegionId, RunStats),
+    UpdateOrderSql(sqlx::Error, RegionId, RunStats),
+    UpdateOrderEsi(EsiError, RegionId, RunStats),
+    /// Stopped at a safe point because the collector is shutting down.
+    Interrupted(RunStats),
+}
+
+impl UpdateError {
+    pub fn stats(&self) -> RunStats {
+        match self {
+            UpdateError::MarketHistorySql(_, _, stats)
+            | UpdateError::MarketHistoryEsi(_, _, stats)
+            | UpdateError::UpdateOrderSql(_, _, stats)
+            | UpdateError::UpdateOrderEsi(_, _, stats)
+            | UpdateError::Interrupted(stats) => *stats,
+        }
+    }
+}
+
+impl Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::MarketHistorySql(e, region_id, _) => {
+                write!(f, "Could not save history of region {}: {}", region_id, e)
+            }
+            UpdateError::MarketHistoryEsi(e, region_id, _) => {
+                write!(f, "Could not fetch history of region {}: {}", region_id, e)
+            }
+            UpdateError::UpdateOrderSql(e, region_id, _) => {
+                write!(f, "Could not save orders of region {}: {}", region_id, e)
+            }
+            UpdateError::UpdateOrderEsi(e, region_id, _) => {
+                write!(f, "Could not fetch orders of region {}: {}", region_id, e)
+            }
+            UpdateError::Interrupted(_) => write!(f, "Interrupted"),
+        }
+    }
 }
@@ -1,42 +1,56 @@
-use std::time::Duration;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use chrono::{DateTime, Datelike, NaiveTime, TimeZone, Timelike, Utc};
-use futures::future::try_join_all;
+use futures::future::join_all;
 
 use crate::{
     esi::{errors::EsiError, get_market_history, get_market_region_types, EsiClient},
-    repository::{ItemRepository, MarketHistoryRepository},
+    ids::{RegionId, TypeId},
+    repository::{ItemRepository, MarketHistoryRepository, RunStats},
 };
 
 use super::UpdateError;
 
+/// Imports the missing history of the region, or only of `types`.
 pub async fn update_history_for_region(
-    region_id: usize,
+    region_id: RegionId,
+    types: Option<Vec<TypeId>>,
     mut market_history_repository: MarketHistoryRepository,
     mut item_repository: ItemRepository,
-) -> Result<(), UpdateError> {
+    progress: impl Fn(String),
+    stop: Arc<AtomicBool>,
+) -> Result<RunStats, UpdateError> {
     let client = EsiClient::current();
 
     log::debug!("Starting history for region: {}", region_id);
 
     let today = current_market_date();
 
+    progress("reading latest histories".to_string());
+
     let latest_histories = market_history_repository
         .latest_histories(region_id)
         .await
-        .map_err(|e| UpdateError::MarketHistorySql(e, region_id))?;
+        .map_err(|e| UpdateError::MarketHistorySql(e, region_id, RunStats::default()))?;
 
     let all_items = item_repository
         .tradeable_item_ids()
         .await
-        .map_err(|e| UpdateError::MarketHistorySql(e, region_id))?;
+        .map_err(|e| UpdateError::MarketHistorySql(e, region_id, RunStats::default()))?;
 
+    progress("fetching region types".to_string());
     let region_types = get_market_region_types(client.clone(), region_id)
         .await
-        .map_err(|e| UpdateError::MarketHistoryEsi(e, region_id))?
+        .map_err(|e| UpdateError::MarketHistoryEsi(e, region_id, RunStats::default()))?
         .into_iter()
-        .map(|i| i as usize)
         .filter(|i| all_items.contains(i)) // needs to be published
+        .filter(|i| types.as_ref().is_none_or(|types| types.contains(i)))
         .filter(|i| latest_histories.get(i).map(|s| *s < today).unwrap_or(true))
         .collect::<Vec<_>>();
 
@@ -46,67 +60,112 @@ pub async fn update_history_for_region(
         region_types.len()
     );
 
+    let mut stats = RunStats::default();
+
     let chunk_size = 300;
-    let chunk_len = region_types.len() / chunk_size;
+    let chunk_len = region_types.len().div_ceil(chunk_size);
 
     for (chunk, types) in region_types.chunks(chunk_size).enumerate() {
-        let a = try_join_all(types.iter().map(|type_id| async {
-            get_market_history(client.clone(), region_id, *type_id)
-                .await
-                .map(|history| (*type_id, history))
+        // Every chunk is saved on its own, so the run can stop in between.
+        if stop.load(Ordering::Relaxed) {
+            return Err(UpdateError::Interrupted(stats));
+        }
+
+        let chunk = chunk + 1;
+        progress(format!("chunk {}/{}", chunk, chunk_len));
+
+        let results = join_all(types.iter().map(|type_id| async {
+            (
+                *type_id,
+                get_market_history(client.clone(), region_id, *type_id).await,
+            )
         }))
         .await;
+        // One history request per type.
+        stats.pages_fetched += results.len();
+
+        let mut added = Vec::new();
+        // The error limit or an unreachable ESI fail the run once the chunk is
+        // saved. A type that failed on its own is counted and left for the
+        // next run, which only fetches the missing history.
+        let mut failure = None;
+        let mut chunk_failures = 0;
+        for (type_id, result) in results {
+            match result {
+                Ok(history) => added.push((type_id, history)),
+                // Unpublished since the SDE was imported, there is no history.
+                Err(EsiError::NotPublished(type_id)) => {
+                    log::debug!("Skipping unpublished type: {}", type_id)
+                }
+                Err(e @ EsiError::ErrorLimited) => failure = Some(e),
+                Err(e @ EsiError::ConnectionError(_)) => {
+                    failure.get_or_insert(e);
+                }
+                Err(e) => {
+                    log::debug!("Failed collecting history of type: {}, {}", type_id, e);
+                    chunk_failures += 1;
+                }
+            }
+        }
+        stats.failed_types += chunk_failures;
 
-        match a {
-            Ok(added) => {
-                let added = added
+        let added = added
+            .into_iter()
+            .flat_map(|(id, history)| {
+                history
                     .into_iter()
-                    .flat_map(|(id, history)| {
-                        history
-                            .into_iter()
-                            .filter(|item| {
-                                if let Some(latest) = latest_histories.get(&id) {
-                                    return Utc.from_utc_datetime(
-                                        &item
-                                            .date
-                                            .and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap()),
-                                    ) > *latest;
-                                }
-                                true
-                            })
-                            .map(|item| (id, item))
-                            .collect::<Vec<_>>()
+                    .filter(|item| {
+                        if let Some(latest) = latest_histories.get(&id) {
+                            return Utc.from_utc_datetime(
+                                &item
+                                    .date
+                                    .and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap()),
+                            ) > *latest;
+                        }
+                        true
                     })
-                    .collect::<Vec<_>>();
-
-                let _ = market_history_repository
-                    .insert_items(added, region_id)
-                    .await
-                    .map_err(|e| UpdateError::MarketHistorySql(e, region_id));
-                log::info!(
-                    "Collected history for region: {}  chunk({}/{})",
-                    region_id,
-                    chunk,
-                    chunk_len
-                );
-            }
-            Err(e) => {
-                log::info!(
-                    "Failed collecting history for region: {}, chunk({}/{}), {:?}",
-                    region_id,
-                    chunk,
-                    chunk_len,
-                    e
-                );
-                tokio::time::sleep(Duration::from_millis(400)).await;
-                if let EsiError::ErrorLimited = e {
-                    return Err(UpdateError::MarketHistoryEsi(e, 0));
-                }
-            }
+                    .map(|item| (id, item))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        stats.rows_inserted += market_history_repository
+            .insert_items(added, region_id)
+            .await
+            .map_err(|e| UpdateError::MarketHistorySql(e, region_id, stats))?;
+
+        if let Some(e) = failure {
+            return Err(UpdateError::MarketHistoryEsi(e, region_id, stats));
         }
+
+        if chunk_failures == 0 {
+            log::info!(
+                "Collected history for region: {}  chunk({}/{})",
+                region_id,
+                chunk,
+                chunk_len
+            );
+        } else {
+            log::warn!(
+                "Failed collecting history of {} types for region: {}, chunk({}/{})",
+                chunk_failures,
+                region_id,
+                chunk,
+                chunk_len
+            );
+            tokio::time::sleep(Duration::from_millis(400)).await;
+        }
+    }
+
+    if stats.failed_types > 0 {
+        log::warn!(
+            "Failed collecting history of {} types for region: {}",
+            stats.failed_types,
+            region_id
+        );
     }
 
-    Ok(())
+    Ok(stats)
 }
 
 fn current_market_date() -> DateTime<Utc> {
@@ -1,14 +1,36 @@
 use super::UpdateError;
 use crate::{
-    esi::{get_market_orders, EsiClient},
-    repository::{ItemRepository, MarketOrderRepository},
+    esi::{
+        errors::EsiError, get_market_orders, get_market_orders_for_type, get_structure_orders,
+        EsiClient,
+    },
+    events::{EventBus, MarketEvent, Side},
+    ids::{OrderId, RegionId, TypeId},
+    repository::{BestOrder, ItemRepository, MarketOrderRepository, RunStats},
 };
+use futures::future::try_join_all;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+type BestOrders = (HashMap<TypeId, BestOrder>, HashMap<TypeId, BestOrder>);
 
+/// Replaces the orders of the region, or only those of `types`, and publishes
+/// the best prices that changed. A whole region also replaces the orders of
+/// its tracked structures when SSO credentials are configured.
 pub async fn update_order_for_region(
-    region_id: usize,
+    region_id: RegionId,
+    types: Option<Vec<TypeId>>,
     mut market_order_repository: MarketOrderRepository,
     mut item_repository: ItemRepository,
-) -> Result<(), UpdateError> {
+    events: EventBus,
+    progress: impl Fn(String),
+    stop: Arc<AtomicBool>,
+) -> Result<RunStats, UpdateError> {
     let client = EsiClient::current();
 
     log::debug!("Starting orders for region: {}", region_id);
@@ -16,23 +38,249 @@ pub async fn update_order_for_region(
     let all_items = item_repository
         .tradeable_item_ids()
         .await
-        .map_err(|e| UpdateError::UpdateOrderSql(e, region_id))?;
+        .map_err(|e| UpdateError::UpdateOrderSql(e, region_id, RunStats::default()))?;
 
-    let orders = get_market_orders(client.clone(), region_id)
+    progress("fetching orders".to_string());
+    let (orders, pages_fetched) = match &types {
+        Some(types) => try_join_all(
+            types
+                .iter()
+                .map(|type_id| get_market_orders_for_type(client.clone(), region_id, *type_id)),
+        )
         .await
-        .map_err(|e| UpdateError::UpdateOrderEsi(e, region_id))?
+        .map(|fetched| {
+            fetched.into_iter().fold(
+                (Vec::new(), 0),
+                |(mut orders, pages), (type_orders, type_pages)| {
+                    orders.extend(type_orders);
+                    (orders, pages + type_pages)
+                },
+            )
+        }),
+        None => get_market_orders(client.clone(), region_id).await,
+    }
+    .map_err(|e| UpdateError::UpdateOrderEsi(e, region_id, RunStats::default()))?;
+
+    let orders = orders
         .into_iter()
-        .filter(|x| all_items.contains(&(x.type_id as usize)))
+        .filter(|x| all_items.contains(&x.type_id))
         .collect::<Vec<_>>();
 
     log::debug!("Region: {}, orders: {}", region_id, orders.len());
 
-    market_order_repository
-        .insert_active_items(orders, region_id)
+    // Saving deactivates the orders that are gone, once it started it has to
+    // finish.
+    if stop.load(Ordering::Relaxed) {
+        return Err(UpdateError::Interrupted(RunStats {
+            pages_fetched,
+            ..RunStats::default()
+        }));
+    }
+
+    // The first snapshot of a region would report every item, it only
+    // counts as updated.
+    let before = if events.has_subscribers() {
+        best_orders(&market_order_repository, region_id)
+            .await
+            .filter(|(sell, buy)| !sell.is_empty() || !buy.is_empty())
+    } else {
+        None
+    };
+    let order_ids = before.as_ref().map(|_| {
+        orders
+            .iter()
+            .map(|order| order.order_id)
+            .collect::<HashSet<_>>()
+    });
+
+    progress(format!("saving {} orders", orders.len()));
+
+    let (rows_inserted, rows_deactivated) = market_order_repository
+        .insert_active_items(orders, region_id, types.as_deref())
         .await
-        .map_err(|e| UpdateError::UpdateOrderSql(e, region_id))?;
+        .map_err(|e| {
+            let stats = RunStats {
+                pages_fetched,
+                ..RunStats::default()
+            };
+            UpdateError::UpdateOrderSql(e, region_id, stats)
+        })?;
 
     log::debug!("Inserted orders for region: {}", region_id);
 
+    if let (Some((sell, buy)), Some(order_ids)) = (before, order_ids) {
+        if let Some((new_sell, new_buy)) = best_orders(&market_order_repository, region_id).await {
+            price_events(region_id, Side::Sell, &sell, &new_sell, &order_ids)
+                .chain(price_events(
+                    region_id,
+                    Side::Buy,
+                    &buy,
+                    &new_buy,
+                    &order_ids,
+                ))
+                .for_each(|event| events.publish(event));
+        }
+    }
+
+    let mut stats = RunStats {
+        pages_fetched,
+        rows_inserted,
+        rows_deactivated,
+        ..RunStats::default()
+    };
+
+    if types.is_none() && client.is_authenticated() {
+        update_structures(
+            region_id,
+            &all_items,
+            &mut market_order_repository,
+            &progress,
+            &stop,
+            &mut stats,
+        )
+        .await?;
+    }
+
+    Ok(stats)
+}
+
+/// Replaces the orders of every tracked structure in the region. A structure
+/// the character cannot see any more is skipped, only an error limit or an
+/// unreachable ESI fail the run.
+async fn update_structures(
+    region_id: RegionId,
+    all_items: &HashSet<TypeId>,
+    market_order_repository: &mut MarketOrderRepository,
+    progress: &impl Fn(String),
+    stop: &AtomicBool,
+    stats: &mut RunStats,
+) -> Result<(), UpdateError> {
+    let client = EsiClient::current();
+    let structures = market_order_repository
+        .tracked_structures(region_id)
+        .await
+        .map_err(|e| UpdateError::UpdateOrderSql(e, region_id, *stats))?;
+
+    for (index, structure) in structures.iter().enumerate() {
+        if stop.load(Ordering::Relaxed) {
+            return Err(UpdateError::Interrupted(*stats));
+        }
+
+        progress(format!(
+            "structure {}/{}: {}",
+            index + 1,
+            structures.len(),
+            structure.name
+        ));
+
+        let (orders, pages) =
+            match get_structure_orders(client.clone(), structure.structure_id).await {
+                Ok(fetched) => fetched,
+                Err(e @ (EsiError::ErrorLimited | EsiError::ConnectionError(_))) => {
+                    return Err(UpdateError::UpdateOrderEsi(e, region_id, *stats));
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Skipping orders of structure: {} ({}), {}",
+                        structure.structure_id,
+                        structure.name,
+                        e
+                    );
+                    continue;
+                }
+            };
+        stats.pages_fetched += pages;
+
+        let orders = orders
+            .into_iter()
+            .filter(|order| all_items.contains(&order.type_id))
+            .map(|order| order.in_system(structure.system_id))
+            .collect::<Vec<_>>();
+
+        let (inserted, deactivated) = market_order_repository
+            .insert_structure_items(orders, structure.structure_id)
+            .await
+            .map_err(|e| UpdateError::UpdateOrderSql(e, region_id, *stats))?;
+        stats.rows_inserted += inserted;
+        stats.rows_deactivated += deactivated;
+    }
+
     Ok(())
 }
+
+/// Best sell and buy orders per item. Events are not worth failing the run
+/// for, errors are only logged.
+async fn best_orders(
+    market_order_repository: &MarketOrderRepository,
+    region_id: RegionId,
+) -> Option<BestOrders> {
+    let best = futures::try_join!(
+        market_order_repository.region_best_orders(region_id, false),
+        market_order_repository.region_best_orders(region_id, true),
+    );
+
+    match best {
+        Ok(best) => Some(best),
+        Err(e) => {
+            log::error!(
+                "Could not read best orders for region: {}, {:?}",
+                region_id,
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Compares the best orders of one side before and after a snapshot.
+/// `order_ids` are the orders of the snapshot, an order that is gone was
+/// filled or cancelled instead of undercut.
+fn price_events<'a>(
+    region_id: RegionId,
+    side: Side,
+    before: &'a HashMap<TypeId, BestOrder>,
+    after: &'a HashMap<TypeId, BestOrder>,
+    order_ids: &'a HashSet<OrderId>,
+) -> impl Iterator<Item = MarketEvent> + 'a {
+    let type_ids = before
+        .keys()
+        .chain(after.keys())
+        .copied()
+        .collect::<HashSet<_>>();
+
+    type_ids.into_iter().flat_map(move |type_id| {
+        let old = before.get(&type_id);
+        let new = after.get(&type_id);
+        let mut events = Vec::new();
+
+        if old.map(|order| order.price) != new.map(|order| order.price) {
+            events.push(MarketEvent::BestPriceChanged {
+                region_id,
+                type_id,
+                side,
+                old_price: old.map(|order| order.price),
+                new_price: new.map(|order| order.price),
+            });
+        }
+
+        if let (Some(old), Some(new)) = (old, new) {
+            let beaten = match side {
+                Side::Sell => new.price < old.price,
+                Side::Buy => new.price > old.price,
+            };
+            if beaten && new.order_id != old.order_id && order_ids.contains(&old.order_id) {
+                events.push(MarketEvent::OrderUndercut {
+                    region_id,
+                    type_id,
+                    side,
+                    order_id: old.order_id,
+                    price: old.price,
+                    undercut_by: new.order_id,
+                    new_price: new.price,
+                });
+            }
+        }
+
+        events
+    })
+}
@@ -1,83 +1,57 @@
+use super::supervision::{Collector, CollectorRun};
 use crate::{
-    actions::update_history_for_region,
-    repository::{ItemRepository, MarketHistoryRepository},
+    actions::{update_history_for_region, UpdateError},
+    events::EventBus,
+    ids::{RegionId, TypeId},
+    repository::{ItemRepository, MarketHistoryRepository, RunKind, RunStats, UpdateLogRepository},
+};
+use std::{
+    future::Future,
+    sync::{atomic::AtomicBool, Arc},
 };
-use actix::{Actor, Context, Handler};
-
 
-use super::StartActor;
+pub type MarketHistoryActor = Collector<HistoryRun>;
 
 #[derive(Debug)]
-pub struct MarketHistoryActor {
-    pub region_id: usize,
+pub struct HistoryRun {
     pub market_history_repository: MarketHistoryRepository,
     pub item_repository: ItemRepository,
-
-    handle: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl MarketHistoryActor {
     pub fn new(
-        region_id: usize,
+        region_id: RegionId,
         market_history_repository: MarketHistoryRepository,
         item_repository: ItemRepository,
+        update_log_repository: UpdateLogRepository,
+        events: EventBus,
     ) -> Self {
-        Self {
-            region_id,
+        let action = HistoryRun {
             market_history_repository,
             item_repository,
-            handle: None,
-        }
+        };
+        Collector::for_run(region_id, action, update_log_repository, events)
     }
 }
 
-impl Actor for MarketHistoryActor {
-    type Context = Context<Self>;
-
-    fn started(&mut self, _ctx: &mut Self::Context) {
-        log::debug!("MarketHistoryActor created for region: {}", self.region_id);
-    }
-
-    fn stopping(&mut self, _ctx: &mut Self::Context) -> actix::Running {
-        log::debug!("MarketHistoryActor stopping for region: {}", self.region_id);
-        if let Some(handle) = self.handle.take() {
-            handle.abort();
-        }
-        actix::Running::Stop
-    }
-}
-
-impl Handler<StartActor> for MarketHistoryActor {
-    type Result = ();
-
-    fn handle(&mut self, _: StartActor, _ctx: &mut Self::Context) -> Self::Result {
-        log::trace!("MarketHistoryActor received StartActor message");
-        if let Some(handle) = &self.handle {
-            if !handle.is_finished() {
-                log::warn!(
-                    "MarketHistoryActor already running for region: {}",
-                    self.region_id
-                );
-                return;
-            }
-        }
-        log::debug!("MarketHistoryActor starting for region: {}", self.region_id);
-        let region_id = self.region_id;
-        let market_history_repository = self.market_history_repository.clone();
-        let item_repository = self.item_repository.clone();
-
-        let handle = tokio::spawn(async move {
-            match update_history_for_region(region_id, market_history_repository, item_repository)
-                .await
-            {
-                Ok(_) => log::info!("MarketHistoryActor finished for region: {}", region_id),
-                Err(e) => log::error!(
-                    "MarketHistoryActor failed for region: {}, {:?}",
-                    region_id,
-                    e
-                ),
-            }
-        });
-        self.handle = Some(handle);
+impl CollectorRun for HistoryRun {
+    const NAME: &'static str = "MarketHistoryActor";
+    const KIND: RunKind = RunKind::History;
+
+    fn run(
+        &self,
+        region_id: RegionId,
+        types: Option<Vec<TypeId>>,
+        progress: impl Fn(String) + Send + Sync + 'static,
+        stop: Arc<AtomicBool>,
+    ) -> impl Future<Output = Result<RunStats, UpdateError>> + Send + 'static {
+        update_history_for_region(
+            region_id,
+            types,
+            self.market_history_repository.clone(),
+            self.item_repository.clone(),
+            progress,
+            stop,
+        )
     }
 }
@@ -1,52 +1,99 @@
-use crate::repository::{ItemRepository, MarketHistoryRepository, MarketOrderRepository};
-use actix::{Actor, Addr, Message};
+use crate::{
+    actions::UpdateError,
+    ids::TypeId,
+    repository::{RunStats, UpdateLogRepository},
+};
+use actix::Message;
+use chrono::{DateTime, Utc};
+use std::{fmt::Display, future::Future, time::Duration};
 
 pub use market_history_actor::MarketHistoryActor;
 pub use market_order_actor::MarketOrderActor;
+pub use region_manager::{RefreshRegion, RegionManager, Shutdown, TrackRegion, UntrackRegion};
+pub use status::{CollectorState, GetStatusReport, StatusReport};
+pub use supervision::CircuitState;
 pub use update_scheduler::UpdateScheduler;
 
 mod market_history_actor;
 mod market_order_actor;
+mod region_manager;
+mod status;
+mod supervision;
 mod update_scheduler;
 
+/// Starts a run of a collector unless it is already running, and returns the
+/// id of the run in the update log.
+#[derive(Message, Debug, Default)]
+#[rtype(result = "Result<i64, StartError>")]
+pub struct StartActor {
+    /// Only these types instead of the whole region.
+    pub types: Option<Vec<TypeId>>,
+    /// Asked for through the API, runs even when the circuit breaker skips
+    /// scheduled runs.
+    pub manual: bool,
+}
+
+#[derive(Debug)]
+pub enum StartError {
+    /// The collector is busy with this run.
+    AlreadyRunning(Option<i64>),
+    /// The region has no collector of this kind, or it is paused.
+    NotTracked,
+    /// Scheduled runs are skipped after repeated failures until then.
+    CircuitOpen(DateTime<Utc>),
+    Sql(sqlx::Error),
+    Mailbox(actix::MailboxError),
+}
+
+impl Display for StartError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartError::AlreadyRunning(Some(run_id)) => write!(f, "already running run {}", run_id),
+            StartError::AlreadyRunning(None) => write!(f, "already running"),
+            StartError::NotTracked => write!(f, "region is not tracked"),
+            StartError::CircuitOpen(until) => {
+                write!(f, "scheduled runs are skipped until {}", until)
+            }
+            StartError::Sql(e) => write!(f, "Could not record run: {}", e),
+            StartError::Mailbox(e) => write!(f, "Collector is not reachable: {}", e),
+        }
+    }
+}
+
+/// Stops a collector. The run in progress stops at its next safe point.
 #[derive(Message, Debug)]
 #[rtype(result = "()")]
-pub struct StartActor;
-
-pub fn load_market_history_actors(
-    regions: &[usize],
-    market_history_repository: MarketHistoryRepository,
-    item_repository: ItemRepository,
-) -> Vec<Addr<MarketHistoryActor>> {
-    regions
-        .iter()
-        .map(|region_id| {
-            let actor = MarketHistoryActor::new(
-                *region_id,
-                market_history_repository.clone(),
-                item_repository.clone(),
-            );
-
-            actor.start()
-        })
-        .collect()
-}
+pub struct StopActor;
+
+/// Asks the run in progress to stop at its next safe point and waits for it,
+/// at most for the given time before it is aborted.
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Drain(pub Duration);
+
+/// Runs a collector and records its end and outcome in the update log.
+async fn tracked_run(
+    mut update_log_repository: UpdateLogRepository,
+    run_id: i64,
+    run: impl Future<Output = Result<RunStats, UpdateError>>,
+) -> Result<RunStats, UpdateError> {
+    let result = run.await;
+
+    let recorded = match &result {
+        Ok(stats) => update_log_repository.finish_run(run_id, *stats).await,
+        Err(UpdateError::Interrupted(stats)) => {
+            update_log_repository.interrupt_run(run_id, *stats).await
+        }
+        Err(e) => {
+            update_log_repository
+                .fail_run(run_id, e.stats(), e.to_string())
+                .await
+        }
+    };
+
+    if let Err(e) = recorded {
+        log::error!("Could not finish run {}: {:?}", run_id, e);
+    }
 
-pub fn load_market_order_actors(
-    regions: &[usize],
-    market_order_repository: MarketOrderRepository,
-    item_repository: ItemRepository,
-) -> Vec<Addr<MarketOrderActor>> {
-    regions
-        .iter()
-        .map(|region_id| {
-            let actor = MarketOrderActor::new(
-                *region_id,
-                market_order_repository.clone(),
-                item_repository.clone(),
-            );
-
-            actor.start()
-        })
-        .collect()
+    result
 }
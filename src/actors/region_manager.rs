@@ -0,0 +1,289 @@
+use super::{
+    status::{GetSchedulerStatus, GetStatus, GetStatusReport, StatusReport},
+    update_scheduler::{StopDispatching, Subscribe, Unsubscribe},
+    Drain, MarketHistoryActor, MarketOrderActor, StartActor, StartError, StopActor,
+    UpdateScheduler,
+};
+use crate::{
+    events::EventBus,
+    ids::{RegionId, TypeId},
+    repository::{
+        ItemRepository, MarketHistoryRepository, MarketOrderRepository, RunKind,
+        UpdateLogRepository,
+    },
+};
+use actix::{Actor, Addr, Context, Handler, Message, ResponseFuture};
+use futures::{future::join_all, FutureExt};
+use std::{collections::HashMap, time::Duration};
+
+/// Owns the collectors of every tracked region and registers them with the
+/// schedulers, so regions can be added and removed while the server runs.
+#[derive(Debug)]
+pub struct RegionManager {
+    market_history_repository: MarketHistoryRepository,
+    market_order_repository: MarketOrderRepository,
+    item_repository: ItemRepository,
+    update_log_repository: UpdateLogRepository,
+    events: EventBus,
+    history_scheduler: Addr<UpdateScheduler>,
+    order_scheduler: Addr<UpdateScheduler>,
+    regions: HashMap<RegionId, RegionCollectors>,
+    /// No collectors are started once the server is shutting down.
+    shutting_down: bool,
+}
+
+#[derive(Debug, Default)]
+struct RegionCollectors {
+    history: Option<Addr<MarketHistoryActor>>,
+    orders: Option<Addr<MarketOrderActor>>,
+}
+
+impl RegionManager {
+    pub fn new(
+        market_history_repository: MarketHistoryRepository,
+        market_order_repository: MarketOrderRepository,
+        item_repository: ItemRepository,
+        update_log_repository: UpdateLogRepository,
+        events: EventBus,
+        history_scheduler: Addr<UpdateScheduler>,
+        order_scheduler: Addr<UpdateScheduler>,
+    ) -> Self {
+        Self {
+            market_history_repository,
+            market_order_repository,
+            item_repository,
+            update_log_repository,
+            events,
+            history_scheduler,
+            order_scheduler,
+            regions: HashMap::new(),
+            shutting_down: false,
+        }
+    }
+
+    fn stop_region(&mut self, region_id: RegionId) {
+        let Some(collectors) = self.regions.remove(&region_id) else {
+            return;
+        };
+
+        if let Some(history) = collectors.history {
+            self.history_scheduler.do_send(Unsubscribe(region_id));
+            history.do_send(StopActor);
+        }
+        if let Some(orders) = collectors.orders {
+            self.order_scheduler.do_send(Unsubscribe(region_id));
+            orders.do_send(StopActor);
+        }
+    }
+}
+
+impl Actor for RegionManager {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _ctx: &mut Self::Context) {
+        log::debug!("RegionManager started");
+    }
+}
+
+/// Starts the collectors of a region, replacing the ones it already has.
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct TrackRegion {
+    pub region_id: RegionId,
+    pub history: bool,
+    pub orders: bool,
+    /// Run the history collector now instead of waiting for the schedule.
+    pub start_history: bool,
+    /// Run the order collector now instead of waiting for the schedule.
+    pub start_orders: bool,
+}
+
+/// Stops the collectors of a region. A run in progress is not cancelled, it
+/// stops at its next safe point: between two chunks of history, or before the
+/// orders are saved.
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct UntrackRegion(pub RegionId);
+
+/// Stops the schedulers from starting runs and drains every collector, each
+/// with the given time to reach a safe point.
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Shutdown(pub Duration);
+
+/// Starts a run of a collector right away, for the whole region or only some
+/// types, and returns its id in the update log.
+#[derive(Message, Debug)]
+#[rtype(result = "Result<i64, StartError>")]
+pub struct RefreshRegion {
+    pub region_id: RegionId,
+    pub kind: RunKind,
+    pub types: Option<Vec<TypeId>>,
+}
+
+impl Handler<TrackRegion> for RegionManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: TrackRegion, _ctx: &mut Self::Context) -> Self::Result {
+        if self.shutting_down {
+            return;
+        }
+        self.stop_region(msg.region_id);
+        log::info!(
+            "Tracking region: {}, history: {}, orders: {}",
+            msg.region_id,
+            msg.history,
+            msg.orders
+        );
+
+        let mut collectors = RegionCollectors::default();
+
+        if msg.history {
+            let actor = MarketHistoryActor::new(
+                msg.region_id,
+                self.market_history_repository.clone(),
+                self.item_repository.clone(),
+                self.update_log_repository.clone(),
+                self.events.clone(),
+            )
+            .start();
+
+            self.history_scheduler
+                .do_send(Subscribe(msg.region_id, actor.clone().recipient()));
+            if msg.start_history {
+                actor.do_send(StartActor::default());
+            }
+            collectors.history = Some(actor);
+        }
+
+        if msg.orders {
+            let actor = MarketOrderActor::new(
+                msg.region_id,
+                self.market_order_repository.clone(),
+                self.item_repository.clone(),
+                self.update_log_repository.clone(),
+                self.events.clone(),
+            )
+            .start();
+
+            self.order_scheduler
+                .do_send(Subscribe(msg.region_id, actor.clone().recipient()));
+            if msg.start_orders {
+                actor.do_send(StartActor::default());
+            }
+            collectors.orders = Some(actor);
+        }
+
+        self.regions.insert(msg.region_id, collectors);
+    }
+}
+
+impl Handler<UntrackRegion> for RegionManager {
+    type Result = ();
+
+    fn handle(&mut self, msg: UntrackRegion, _ctx: &mut Self::Context) -> Self::Result {
+        log::info!("Stopped tracking region: {}", msg.0);
+        self.stop_region(msg.0);
+    }
+}
+
+impl Handler<RefreshRegion> for RegionManager {
+    type Result = ResponseFuture<Result<i64, StartError>>;
+
+    fn handle(&mut self, msg: RefreshRegion, _ctx: &mut Self::Context) -> Self::Result {
+        let collectors = self.regions.get(&msg.region_id);
+        let start = StartActor {
+            types: msg.types,
+            manual: true,
+        };
+
+        let request = match msg.kind {
+            RunKind::History => collectors
+                .and_then(|collectors| collectors.history.as_ref())
+                .map(|actor| actor.send(start).boxed()),
+            RunKind::Orders => collectors
+                .and_then(|collectors| collectors.orders.as_ref())
+                .map(|actor| actor.send(start).boxed()),
+        };
+
+        Box::pin(async move {
+            match request {
+                Some(request) => request.await.map_err(StartError::Mailbox)?,
+                None => Err(StartError::NotTracked),
+            }
+        })
+    }
+}
+
+impl Handler<Shutdown> for RegionManager {
+    type Result = ResponseFuture<()>;
+
+    fn handle(&mut self, msg: Shutdown, _ctx: &mut Self::Context) -> Self::Result {
+        self.shutting_down = true;
+
+        let schedulers = [
+            self.history_scheduler.send(StopDispatching),
+            self.order_scheduler.send(StopDispatching),
+        ];
+        let history = self
+            .regions
+            .values()
+            .filter_map(|collectors| collectors.history.as_ref())
+            .map(|actor| actor.send(Drain(msg.0)))
+            .collect::<Vec<_>>();
+        let orders = self
+            .regions
+            .values()
+            .filter_map(|collectors| collectors.orders.as_ref())
+            .map(|actor| actor.send(Drain(msg.0)))
+            .collect::<Vec<_>>();
+
+        Box::pin(async move {
+            join_all(schedulers).await;
+            log::info!("Draining {} collectors", history.len() + orders.len());
+            futures::join!(join_all(history), join_all(orders));
+        })
+    }
+}
+
+impl Handler<GetStatusReport> for RegionManager {
+    type Result = ResponseFuture<StatusReport>;
+
+    fn handle(&mut self, _: GetStatusReport, _ctx: &mut Self::Context) -> Self::Result {
+        let history = self
+            .regions
+            .values()
+            .filter_map(|collectors| collectors.history.as_ref())
+            .map(|actor| actor.send(GetStatus))
+            .collect::<Vec<_>>();
+        let orders = self
+            .regions
+            .values()
+            .filter_map(|collectors| collectors.orders.as_ref())
+            .map(|actor| actor.send(GetStatus))
+            .collect::<Vec<_>>();
+        let history_scheduler = self.history_scheduler.send(GetSchedulerStatus);
+        let order_scheduler = self.order_scheduler.send(GetSchedulerStatus);
+
+        Box::pin(async move {
+            // A collector that stopped in the meantime is left out.
+            let mut collectors = join_all(history)
+                .await
+                .into_iter()
+                .chain(join_all(orders).await)
+                .filter_map(Result::ok)
+                .collect::<Vec<_>>();
+            collectors.sort_by_key(|status| (status.region_id, status.kind.as_str()));
+
+            let schedulers = [history_scheduler.await, order_scheduler.await]
+                .into_iter()
+                .filter_map(Result::ok)
+                .collect();
+
+            StatusReport {
+                schedulers,
+                collectors,
+            }
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use super::supervision::{RunFailure, SupervisionStatus, Supervisor};
+use crate::{
+    ids::RegionId,
+    repository::{LastRuns, RunKind},
+};
+use actix::Message;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollectorState {
+    Idle,
+    Running,
+}
+
+/// What a collector is doing and how its last runs went.
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectorStatus {
+    pub region_id: RegionId,
+    pub kind: RunKind,
+    pub state: CollectorState,
+    /// Update log id of the current or the last run.
+    pub run_id: Option<i64>,
+    /// How far the current run got, e.g. `chunk 12/40`.
+    pub progress: Option<String>,
+    /// Start of the current or the last run.
+    pub started: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// Retries and circuit breaker.
+    #[serde(flatten)]
+    pub supervision: SupervisionStatus,
+}
+
+impl CollectorStatus {
+    pub fn new(region_id: RegionId, kind: RunKind) -> Self {
+        Self {
+            region_id,
+            kind,
+            state: CollectorState::Idle,
+            run_id: None,
+            progress: None,
+            started: None,
+            last_success: None,
+            last_error: None,
+            last_error_at: None,
+            supervision: Supervisor::default().status(),
+        }
+    }
+
+    /// Fills in the runs from before the actor started, unless it already
+    /// finished one of its own.
+    pub fn restore(&mut self, last_runs: LastRuns) {
+        if self.last_success.is_none() {
+            self.last_success = last_runs.last_success;
+        }
+        if self.last_error_at.is_none() {
+            self.last_error = last_runs.last_error;
+            self.last_error_at = last_runs.last_error_at;
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.state = CollectorState::Running;
+        self.progress = None;
+        self.started = Some(Utc::now());
+    }
+
+    pub fn finish(&mut self, result: Result<(), String>) {
+        self.state = CollectorState::Idle;
+        self.progress = None;
+
+        match result {
+            Ok(()) => self.last_success = Some(Utc::now()),
+            Err(e) => {
+                self.last_error = Some(e);
+                self.last_error_at = Some(Utc::now());
+            }
+        }
+    }
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "CollectorStatus")]
+pub struct GetStatus;
+
+/// Sent by a run to its actor whenever it gets further.
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct RunProgress(pub String);
+
+/// Sent by a run to its actor when it is done, with the error if it failed.
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct RunFinished(pub Result<(), RunFailure>);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerStatus {
+    pub kind: RunKind,
+    pub schedule: String,
+    pub next_run: Option<DateTime<Utc>>,
+    pub regions: Vec<RegionId>,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "SchedulerStatus")]
+pub struct GetSchedulerStatus;
+
+/// Status of every scheduler and running collector.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusReport {
+    pub schedulers: Vec<SchedulerStatus>,
+    pub collectors: Vec<CollectorStatus>,
+}
+
+#[derive(Message, Debug)]
+#[rtype(result = "StatusReport")]
+pub struct GetStatusReport;
@@ -0,0 +1,751 @@
+use super::{
+    status::{CollectorState, CollectorStatus, GetStatus, RunFinished, RunProgress},
+    tracked_run, Drain, StartActor, StartError, StopActor,
+};
+use crate::{
+    actions::UpdateError,
+    esi::errors::EsiError,
+    events::{EventBus, MarketEvent},
+    ids::{RegionId, TypeId},
+    repository::{RunKind, RunStats, UpdateLogRepository},
+};
+use actix::{
+    fut, Actor, ActorContext, ActorFutureExt, AsyncContext, Context, Handler, MailboxError,
+    MessageResult, ResponseActFuture, ResponseFuture, SpawnHandle, WrapFuture,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{sync::oneshot, task::JoinHandle};
+
+/// Retries of a failed run before waiting for the next scheduled one.
+const MAX_RETRIES: u32 = 3;
+/// Delay before the first retry, doubled for every further one.
+const RETRY_BACKOFF: Duration = Duration::from_secs(30);
+/// ESI resets its error limit every minute, retrying sooner fails again.
+const ERROR_LIMIT_BACKOFF: Duration = Duration::from_secs(60);
+/// Runs in a row that failed even after retrying before scheduled runs of the
+/// region are skipped.
+const FAILURE_THRESHOLD: u32 = 3;
+/// How long scheduled runs are skipped before one is tried again.
+const CIRCUIT_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// ESI answered 420, every region is affected so it does not count
+    /// towards the circuit breaker.
+    ErrorLimited,
+    Esi,
+    /// Retrying does not help a failing database, it is not retried.
+    Sql,
+    /// Stopped for shutting down, neither retried nor counted.
+    Interrupted,
+}
+
+impl FailureKind {
+    pub fn of(error: &UpdateError) -> Self {
+        match error {
+            UpdateError::MarketHistoryEsi(EsiError::ErrorLimited, _, _)
+            | UpdateError::UpdateOrderEsi(EsiError::ErrorLimited, _, _) => {
+                FailureKind::ErrorLimited
+            }
+            UpdateError::MarketHistoryEsi(_, _, _) | UpdateError::UpdateOrderEsi(_, _, _) => {
+                FailureKind::Esi
+            }
+            UpdateError::MarketHistorySql(_, _, _) | UpdateError::UpdateOrderSql(_, _, _) => {
+                FailureKind::Sql
+            }
+            UpdateError::Interrupted(_) => FailureKind::Interrupted,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RunFailure {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    /// Scheduled runs are skipped until `circuit_open_until`.
+    Open,
+    /// The cooldown passed, the next run decides whether it closes or opens
+    /// again.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SupervisionStatus {
+    /// Retries of the current failure so far.
+    pub retries: u32,
+    pub retry_at: Option<DateTime<Utc>>,
+    pub last_failure: Option<FailureKind>,
+    /// Runs in a row that failed after retrying.
+    pub consecutive_failures: u32,
+    pub circuit: CircuitState,
+    pub circuit_open_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Decision {
+    Retry(Duration),
+    GiveUp,
+}
+
+/// Decides when a collector retries a failed run and when it stops running
+/// on schedule.
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    retries: u32,
+    retry: Option<(SpawnHandle, DateTime<Utc>)>,
+    last_failure: Option<FailureKind>,
+    consecutive_failures: u32,
+    circuit_open_until: Option<DateTime<Utc>>,
+    /// Types of the last run, a retry repeats them.
+    pub types: Option<Vec<TypeId>>,
+}
+
+impl Supervisor {
+    pub fn circuit(&self) -> CircuitState {
+        match self.circuit_open_until {
+            Some(until) if until > Utc::now() => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+            None => CircuitState::Closed,
+        }
+    }
+
+    /// When scheduled runs are skipped, the time they resume.
+    pub fn open_until(&self) -> Option<DateTime<Utc>> {
+        self.circuit_open_until.filter(|until| *until > Utc::now())
+    }
+
+    pub fn succeeded(&mut self) {
+        self.retries = 0;
+        self.last_failure = None;
+        self.consecutive_failures = 0;
+        self.circuit_open_until = None;
+    }
+
+    pub fn failed(&mut self, kind: FailureKind) -> Decision {
+        self.last_failure = Some(kind);
+
+        let delay = match kind {
+            FailureKind::Interrupted => return Decision::GiveUp,
+            FailureKind::Sql => None,
+            FailureKind::ErrorLimited => {
+                Some(ERROR_LIMIT_BACKOFF.max(RETRY_BACKOFF * 2u32.pow(self.retries)))
+            }
+            FailureKind::Esi => Some(RETRY_BACKOFF * 2u32.pow(self.retries)),
+        };
+
+        if let Some(delay) = delay.filter(|_| self.retries < MAX_RETRIES) {
+            self.retries += 1;
+            return Decision::Retry(delay);
+        }
+
+        self.retries = 0;
+        if kind != FailureKind::ErrorLimited {
+            self.consecutive_failures += 1;
+        }
+        if self.consecutive_failures >= FAILURE_THRESHOLD {
+            self.circuit_open_until = Some(Utc::now() + CIRCUIT_COOLDOWN);
+        }
+
+        Decision::GiveUp
+    }
+
+    pub fn retry_scheduled(&mut self, handle: SpawnHandle, delay: Duration) {
+        let at = Utc::now() + chrono::Duration::from_std(delay).unwrap_or_default();
+        self.retry = Some((handle, at));
+    }
+
+    /// The pending retry started, or was replaced by another run.
+    pub fn take_retry(&mut self) -> Option<SpawnHandle> {
+        self.retry.take().map(|(handle, _)| handle)
+    }
+
+    /// A run that was not a retry starts a new series of retries.
+    pub fn reset_retries(&mut self) {
+        self.retries = 0;
+    }
+
+    pub fn status(&self) -> SupervisionStatus {
+        SupervisionStatus {
+            retries: self.retries,
+            retry_at: self.retry.map(|(_, at)| at),
+            last_failure: self.last_failure,
+            consecutive_failures: self.consecutive_failures,
+            circuit: self.circuit(),
+            circuit_open_until: self.circuit_open_until,
+        }
+    }
+}
+
+/// The work of a collector. Starting, retrying, draining and the status of
+/// its runs are the same for every collector.
+pub trait CollectorRun: Unpin + 'static {
+    /// Name of the collector in the logs.
+    const NAME: &'static str;
+    const KIND: RunKind;
+
+    fn run(
+        &self,
+        region_id: RegionId,
+        types: Option<Vec<TypeId>>,
+        progress: impl Fn(String) + Send + Sync + 'static,
+        stop: Arc<AtomicBool>,
+    ) -> impl Future<Output = Result<RunStats, UpdateError>> + Send + 'static;
+}
+
+/// Runs a `CollectorRun` for a region, one run at a time.
+#[derive(Debug)]
+pub struct Collector<R> {
+    pub region_id: RegionId,
+    pub update_log_repository: UpdateLogRepository,
+    pub events: EventBus,
+    action: R,
+
+    /// The run from recording its start until it finished.
+    handle: Option<JoinHandle<()>>,
+    /// Tells the run in progress to stop at its next safe point.
+    stop: Arc<AtomicBool>,
+    status: CollectorStatus,
+    supervisor: Supervisor,
+}
+
+impl<R: CollectorRun> Collector<R> {
+    pub fn for_run(
+        region_id: RegionId,
+        action: R,
+        update_log_repository: UpdateLogRepository,
+        events: EventBus,
+    ) -> Self {
+        Self {
+            region_id,
+            update_log_repository,
+            events,
+            action,
+            handle: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            status: CollectorStatus::new(region_id, R::KIND),
+            supervisor: Supervisor::default(),
+        }
+    }
+
+    /// A run is starting or its task has not finished yet.
+    fn is_running(&self) -> bool {
+        self.status.state == CollectorState::Running
+            && self
+                .handle
+                .as_ref()
+                .is_none_or(|handle| !handle.is_finished())
+    }
+
+    /// Records the run and starts it, the future resolves to its id.
+    fn begin(
+        &mut self,
+        types: Option<Vec<TypeId>>,
+        ctx: &mut Context<Self>,
+    ) -> ResponseActFuture<Self, Result<i64, StartError>> {
+        log::debug!("{} starting for region: {}", R::NAME, self.region_id);
+        self.status.start();
+        self.supervisor.types = types.clone();
+
+        let region_id = self.region_id;
+        let mut update_log_repository = self.update_log_repository.clone();
+        let events = self.events.clone();
+        let address = ctx.address();
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = stop.clone();
+
+        let progress = {
+            let address = address.clone();
+            move |progress| address.do_send(RunProgress(progress))
+        };
+        let run = self
+            .action
+            .run(region_id, types.clone(), progress, stop.clone());
+        let (started, run_id) = oneshot::channel();
+
+        // The run is recorded in its own task, so a drain waits for a run
+        // that is still starting as well.
+        self.handle = Some(tokio::spawn(async move {
+            let run_id = match update_log_repository.start_run(region_id, R::KIND).await {
+                Ok(run_id) => run_id,
+                Err(e) => {
+                    let _ = started.send(Err(e));
+                    return;
+                }
+            };
+            let _ = started.send(Ok(run_id));
+
+            let run = async move {
+                // Drained while the run was recorded.
+                if stop.load(Ordering::Relaxed) {
+                    return Err(UpdateError::Interrupted(RunStats::default()));
+                }
+                run.await
+            };
+
+            let result = match tracked_run(update_log_repository, run_id, run).await {
+                Ok(stats) => {
+                    log::info!("{} finished for region: {}", R::NAME, region_id);
+                    events.publish(MarketEvent::RegionUpdated {
+                        region_id,
+                        kind: R::KIND,
+                        run_id,
+                        types,
+                        stats,
+                        at: Utc::now(),
+                    });
+                    Ok(())
+                }
+                Err(e) => {
+                    let kind = FailureKind::of(&e);
+                    if kind == FailureKind::Interrupted {
+                        log::warn!("{} interrupted for region: {}", R::NAME, region_id);
+                    } else {
+                        log::error!("{} failed for region: {}, {}", R::NAME, region_id, e);
+                    }
+                    Err(RunFailure {
+                        kind,
+                        message: e.to_string(),
+                    })
+                }
+            };
+            address.do_send(RunFinished(result));
+        }));
+
+        Box::pin(
+            run_id
+                .into_actor(self)
+                .map(|run_id, actor, _| match run_id {
+                    Ok(Ok(run_id)) => {
+                        actor.status.run_id = Some(run_id);
+                        Ok(run_id)
+                    }
+                    Ok(Err(e)) => {
+                        log::error!(
+                            "Could not record {} run for region: {}, {:?}",
+                            R::KIND.as_str(),
+                            actor.region_id,
+                            e
+                        );
+                        actor.status.finish(Err(format!("{:?}", e)));
+                        Err(StartError::Sql(e))
+                    }
+                    // Aborted by a drain before it was recorded.
+                    Err(_) => Err(StartError::Mailbox(MailboxError::Closed)),
+                }),
+        )
+    }
+
+    fn retry(&mut self, ctx: &mut Context<Self>) {
+        self.supervisor.take_retry();
+        if self.is_running() {
+            return;
+        }
+
+        log::info!("{} retrying region: {}", R::NAME, self.region_id);
+        let types = self.supervisor.types.clone();
+        let begin = self.begin(types, ctx);
+        ctx.spawn(begin.map(|_, _, _| ()));
+    }
+}
+
+impl<R: CollectorRun> Actor for Collector<R> {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        log::debug!("{} created for region: {}", R::NAME, self.region_id);
+
+        let update_log_repository = self.update_log_repository.clone();
+        let region_id = self.region_id;
+        ctx.spawn(
+            async move { update_log_repository.last_runs(region_id, R::KIND).await }
+                .into_actor(self)
+                .map(|result, actor, _| match result {
+                    Ok(last_runs) => actor.status.restore(last_runs),
+                    Err(e) => log::error!(
+                        "Could not read last runs for region: {}, {:?}",
+                        actor.region_id,
+                        e
+                    ),
+                }),
+        );
+    }
+
+    fn stopping(&mut self, _ctx: &mut Self::Context) -> actix::Running {
+        log::debug!("{} stopping for region: {}", R::NAME, self.region_id);
+        // Aborting could leave a half written snapshot, the run stops itself.
+        self.stop.store(true, Ordering::Relaxed);
+        actix::Running::Stop
+    }
+}
+
+impl<R: CollectorRun> Handler<StartActor> for Collector<R> {
+    type Result = ResponseActFuture<Self, Result<i64, StartError>>;
+
+    fn handle(&mut self, msg: StartActor, ctx: &mut Self::Context) -> Self::Result {
+        log::trace!("{} received StartActor message", R::NAME);
+        if self.is_running() {
+            log::warn!("{} already running for region: {}", R::NAME, self.region_id);
+            return Box::pin(fut::ready(Err(StartError::AlreadyRunning(
+                self.status.run_id,
+            ))));
+        }
+        if let Some(until) = self.supervisor.open_until().filter(|_| !msg.manual) {
+            log::warn!(
+                "{} skipped for region: {}, it failed repeatedly, next run after {}",
+                R::NAME,
+                self.region_id,
+                until
+            );
+            return Box::pin(fut::ready(Err(StartError::CircuitOpen(until))));
+        }
+
+        if let Some(retry) = self.supervisor.take_retry() {
+            ctx.cancel_future(retry);
+        }
+        self.supervisor.reset_retries();
+
+        self.begin(msg.types, ctx)
+    }
+}
+
+impl<R: CollectorRun> Handler<StopActor> for Collector<R> {
+    type Result = ();
+
+    fn handle(&mut self, _: StopActor, ctx: &mut Self::Context) -> Self::Result {
+        ctx.stop();
+    }
+}
+
+impl<R: CollectorRun> Handler<Drain> for Collector<R> {
+    type Result = ResponseFuture<()>;
+
+    fn handle(&mut self, msg: Drain, ctx: &mut Self::Context) -> Self::Result {
+        if let Some(retry) = self.supervisor.take_retry() {
+            ctx.cancel_future(retry);
+        }
+        let Some(handle) = self.handle.take().filter(|handle| !handle.is_finished()) else {
+            return Box::pin(async {});
+        };
+
+        log::info!("{} draining region: {}", R::NAME, self.region_id);
+        self.stop.store(true, Ordering::Relaxed);
+
+        let abort = handle.abort_handle();
+        let region_id = self.region_id;
+        let run_id = self.status.run_id;
+        let mut update_log_repository = self.update_log_repository.clone();
+
+        Box::pin(async move {
+            if tokio::time::timeout(msg.0, handle).await.is_ok() {
+                return;
+            }
+
+            log::warn!(
+                "{} did not stop in time for region: {}, aborting",
+                R::NAME,
+                region_id
+            );
+            abort.abort();
+
+            if let Some(run_id) = run_id {
+                if let Err(e) = update_log_repository
+                    .interrupt_run(run_id, RunStats::default())
+                    .await
+                {
+                    log::error!("Could not record interrupted run {}: {:?}", run_id, e);
+                }
+            }
+        })
+    }
+}
+
+impl<R: CollectorRun> Handler<RunProgress> for Collector<R> {
+    type Result = ();
+
+    fn handle(&mut self, msg: RunProgress, _ctx: &mut Self::Context) -> Self::Result {
+        self.status.progress = Some(msg.0);
+    }
+}
+
+impl<R: CollectorRun> Handler<RunFinished> for Collector<R> {
+    type Result = ();
+
+    fn handle(&mut self, msg: RunFinished, ctx: &mut Self::Context) -> Self::Result {
+        match &msg.0 {
+            Ok(()) => self.supervisor.succeeded(),
+            // Draining, the run is not retried.
+            Err(_) if self.stop.load(Ordering::Relaxed) => {}
+            Err(failure) => match self.supervisor.failed(failure.kind) {
+                Decision::Retry(delay) => {
+                    log::warn!(
+                        "{} retrying region: {} in {}s",
+                        R::NAME,
+                        self.region_id,
+                        delay.as_secs()
+                    );
+                    let retry = ctx.run_later(delay, |actor, ctx| actor.retry(ctx));
+                    self.supervisor.retry_scheduled(retry, delay);
+                }
+                Decision::GiveUp => {
+                    if let Some(until) = self.supervisor.open_until() {
+                        log::error!(
+                            "{} failed repeatedly for region: {}, skipping scheduled runs until {}",
+                            R::NAME,
+                            self.region_id,
+                            until
+                        );
+                    }
+                }
+            },
+        }
+
+        self.status.finish(msg.0.map_err(|failure| failure.message));
+    }
+}
+
+impl<R: CollectorRun> Handler<GetStatus> for Collector<R> {
+    type Result = MessageResult<GetStatus>;
+
+    fn handle(&mut self, _: GetStatus, _ctx: &mut Self::Context) -> Self::Result {
+        let mut status = self.status.clone();
+        status.supervision = self.supervisor.status();
+        MessageResult(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sde::{
+        fixtures::{memory_pool, sde},
+        import_sde,
+    };
+    use tokio::sync::Mutex;
+
+    /// Remembers whether it ran.
+    #[derive(Debug)]
+    struct Noted(Arc<AtomicBool>);
+
+    impl CollectorRun for Noted {
+        const NAME: &'static str = "Noted";
+        const KIND: RunKind = RunKind::Orders;
+
+        fn run(
+            &self,
+            _region_id: RegionId,
+            _types: Option<Vec<TypeId>>,
+            _progress: impl Fn(String) + Send + Sync + 'static,
+            _stop: Arc<AtomicBool>,
+        ) -> impl Future<Output = Result<RunStats, UpdateError>> + Send + 'static {
+            let ran = self.0.clone();
+            async move {
+                ran.store(true, Ordering::Relaxed);
+                Ok(RunStats::default())
+            }
+        }
+    }
+
+    fn give_up(supervisor: &mut Supervisor, kind: FailureKind) {
+        while supervisor.failed(kind) != Decision::GiveUp {}
+    }
+
+    #[test]
+    fn classifies_update_errors() {
+        let region_id = RegionId(10000002);
+        let stats = RunStats::default();
+
+        assert_eq!(
+            FailureKind::of(&UpdateError::MarketHistoryEsi(
+                EsiError::ErrorLimited,
+                region_id,
+                stats
+            )),
+            FailureKind::ErrorLimited
+        );
+        assert_eq!(
+            FailureKind::of(&UpdateError::UpdateOrderEsi(
+                EsiError::NoPages,
+                region_id,
+                stats
+            )),
+            FailureKind::Esi
+        );
+        assert_eq!(
+            FailureKind::of(&UpdateError::UpdateOrderSql(
+                sqlx::Error::RowNotFound,
+                region_id,
+                stats
+            )),
+            FailureKind::Sql
+        );
+        assert_eq!(
+            FailureKind::of(&UpdateError::Interrupted(stats)),
+            FailureKind::Interrupted
+        );
+    }
+
+    #[test]
+    fn esi_failures_back_off_exponentially() {
+        let mut supervisor = Supervisor::default();
+
+        assert_eq!(
+            supervisor.failed(FailureKind::Esi),
+            Decision::Retry(RETRY_BACKOFF)
+        );
+        assert_eq!(
+            supervisor.failed(FailureKind::Esi),
+            Decision::Retry(RETRY_BACKOFF * 2)
+        );
+        assert_eq!(
+            supervisor.failed(FailureKind::Esi),
+            Decision::Retry(RETRY_BACKOFF * 4)
+        );
+        assert_eq!(supervisor.failed(FailureKind::Esi), Decision::GiveUp);
+
+        let status = supervisor.status();
+        assert_eq!(status.retries, 0);
+        assert_eq!(status.consecutive_failures, 1);
+        assert_eq!(status.last_failure, Some(FailureKind::Esi));
+        assert_eq!(status.circuit, CircuitState::Closed);
+    }
+
+    #[test]
+    fn error_limits_wait_a_minute_and_do_not_count() {
+        let mut supervisor = Supervisor::default();
+
+        assert_eq!(
+            supervisor.failed(FailureKind::ErrorLimited),
+            Decision::Retry(ERROR_LIMIT_BACKOFF)
+        );
+        assert_eq!(
+            supervisor.failed(FailureKind::ErrorLimited),
+            Decision::Retry(ERROR_LIMIT_BACKOFF)
+        );
+        assert_eq!(
+            supervisor.failed(FailureKind::ErrorLimited),
+            Decision::Retry(RETRY_BACKOFF * 4)
+        );
+        assert_eq!(
+            supervisor.failed(FailureKind::ErrorLimited),
+            Decision::GiveUp
+        );
+
+        for _ in 0..FAILURE_THRESHOLD {
+            give_up(&mut supervisor, FailureKind::ErrorLimited);
+        }
+        assert_eq!(supervisor.status().consecutive_failures, 0);
+        assert_eq!(supervisor.circuit(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn sql_and_interrupted_runs_are_not_retried() {
+        let mut supervisor = Supervisor::default();
+
+        assert_eq!(supervisor.failed(FailureKind::Sql), Decision::GiveUp);
+        assert_eq!(supervisor.status().consecutive_failures, 1);
+
+        assert_eq!(
+            supervisor.failed(FailureKind::Interrupted),
+            Decision::GiveUp
+        );
+        assert_eq!(supervisor.status().consecutive_failures, 1);
+        assert_eq!(
+            supervisor.status().last_failure,
+            Some(FailureKind::Interrupted)
+        );
+    }
+
+    #[test]
+    fn circuit_opens_after_repeated_failures() {
+        let mut supervisor = Supervisor::default();
+
+        for _ in 1..FAILURE_THRESHOLD {
+            give_up(&mut supervisor, FailureKind::Esi);
+            assert_eq!(supervisor.circuit(), CircuitState::Closed);
+            assert_eq!(supervisor.open_until(), None);
+        }
+        give_up(&mut supervisor, FailureKind::Sql);
+
+        assert_eq!(supervisor.circuit(), CircuitState::Open);
+        let until = supervisor.open_until().unwrap();
+        let cooldown = chrono::Duration::from_std(CIRCUIT_COOLDOWN).unwrap();
+        assert!(until > Utc::now() + cooldown - chrono::Duration::minutes(1));
+        assert!(until <= Utc::now() + cooldown);
+    }
+
+    #[test]
+    fn half_open_circuit_closes_on_success_and_reopens_on_failure() {
+        let mut supervisor = Supervisor::default();
+        for _ in 0..FAILURE_THRESHOLD {
+            give_up(&mut supervisor, FailureKind::Sql);
+        }
+
+        // The cooldown passed.
+        supervisor.circuit_open_until = Some(Utc::now() - chrono::Duration::seconds(1));
+        assert_eq!(supervisor.circuit(), CircuitState::HalfOpen);
+        assert_eq!(supervisor.open_until(), None);
+
+        give_up(&mut supervisor, FailureKind::Sql);
+        assert_eq!(supervisor.circuit(), CircuitState::Open);
+
+        supervisor.circuit_open_until = Some(Utc::now() - chrono::Duration::seconds(1));
+        supervisor.succeeded();
+        let status = supervisor.status();
+        assert_eq!(status.circuit, CircuitState::Closed);
+        assert_eq!(status.consecutive_failures, 0);
+        assert_eq!(status.last_failure, None);
+    }
+
+    #[test]
+    fn a_new_run_starts_a_new_series_of_retries() {
+        let mut supervisor = Supervisor::default();
+
+        supervisor.failed(FailureKind::Esi);
+        supervisor.failed(FailureKind::Esi);
+        supervisor.reset_retries();
+
+        assert_eq!(
+            supervisor.failed(FailureKind::Esi),
+            Decision::Retry(RETRY_BACKOFF)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn draining_a_starting_run_waits_for_it() {
+        let pool = memory_pool().await;
+        import_sde(&pool, &sde()).await.unwrap();
+        let update_log_repository = UpdateLogRepository::new(Arc::new(Mutex::new(pool)));
+        let ran = Arc::new(AtomicBool::new(false));
+        let collector = Collector::for_run(
+            RegionId(10000002),
+            Noted(ran.clone()),
+            update_log_repository.clone(),
+            EventBus::new(),
+        )
+        .start();
+
+        // The run is still being recorded when the drain arrives.
+        collector.do_send(StartActor::default());
+        collector.send(Drain(Duration::from_secs(5))).await.unwrap();
+
+        assert!(!ran.load(Ordering::Relaxed));
+        let run = update_log_repository.run(1).await.unwrap().unwrap();
+        assert_eq!(run.status, "interrupted");
+    }
+}
@@ -1,50 +1,146 @@
-use super::StartActor;
-use actix::{Actor, Context, Recipient};
+use super::{
+    status::{GetSchedulerStatus, SchedulerStatus},
+    StartActor,
+};
+use crate::{ids::RegionId, repository::RunKind};
+use actix::{Actor, Context, Handler, Message, MessageResult, Recipient};
+use chrono::Utc;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 use tokio::task::JoinHandle;
 use tokio_cron_scheduler::{Job, JobScheduler};
 
+type Recipients = Arc<Mutex<HashMap<RegionId, Recipient<StartActor>>>>;
+
+/// Sends `StartActor` to the subscribed collectors on every tick of the cron
+/// expression. Collectors can subscribe and unsubscribe while it runs.
 #[derive(Debug)]
-pub struct UpdateScheduler(String, Vec<Recipient<StartActor>>, Option<JoinHandle<()>>);
+pub struct UpdateScheduler {
+    kind: RunKind,
+    cron: String,
+    recipients: Recipients,
+    handle: Option<JoinHandle<()>>,
+}
 
 impl UpdateScheduler {
-    pub fn new(cron: String, recipients: Vec<Recipient<StartActor>>) -> Self {
-        Self(cron, recipients, None)
+    pub fn new(kind: RunKind, cron: String) -> Self {
+        Self {
+            kind,
+            cron,
+            recipients: Arc::new(Mutex::new(HashMap::new())),
+            handle: None,
+        }
     }
 }
 
+/// Replaces the collector of the region, if there is one.
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Subscribe(pub RegionId, pub Recipient<StartActor>);
+
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct Unsubscribe(pub RegionId);
+
+/// Unsubscribes every collector, for shutting down.
+#[derive(Message, Debug)]
+#[rtype(result = "()")]
+pub struct StopDispatching;
+
 impl Actor for UpdateScheduler {
     type Context = Context<Self>;
 
     fn started(&mut self, _ctx: &mut Self::Context) {
-        let cron = self.0.clone();
+        let cron = self.cron.clone();
         log::debug!("Started UpdateScheduler with cron: {}", cron);
-        let recipients = self.1.clone();
-        self.2 = Some(tokio::spawn(async move {
+        let recipients = self.recipients.clone();
+        self.handle = Some(tokio::spawn(async move {
             let scheduler = JobScheduler::new().await.unwrap();
             let cron = cron.clone();
             let recipients = recipients.clone();
             let job = Job::new_cron_job(cron.as_str(), move |_, _| {
-                for recipient in recipients.iter() {
-                    match recipient.do_send(StartActor) {
-                        Ok(_) => {}
-                        Err(_) => {}
+                let recipients = recipients.lock().unwrap();
+                for recipient in recipients.values() {
+                    if let Err(e) = recipient.do_send(StartActor::default()) {
+                        log::warn!("Could not start scheduled run: {}", e);
                     }
                 }
             })
             .unwrap();
             scheduler.add(job).await.unwrap();
 
-            match scheduler.start().await {
-                Ok(_) => {}
-                Err(_) => {}
+            if let Err(e) = scheduler.start().await {
+                log::error!("Could not start scheduler: {:?}", e);
             }
         }));
     }
 
     fn stopping(&mut self, _ctx: &mut Self::Context) -> actix::Running {
-        if let Some(handle) = self.2.take() {
+        if let Some(handle) = self.handle.take() {
             handle.abort();
         }
         actix::Running::Stop
     }
 }
+
+impl Handler<Subscribe> for UpdateScheduler {
+    type Result = ();
+
+    fn handle(&mut self, msg: Subscribe, _ctx: &mut Self::Context) -> Self::Result {
+        log::debug!("UpdateScheduler {} subscribed region: {}", self.cron, msg.0);
+        self.recipients.lock().unwrap().insert(msg.0, msg.1);
+    }
+}
+
+impl Handler<Unsubscribe> for UpdateScheduler {
+    type Result = ();
+
+    fn handle(&mut self, msg: Unsubscribe, _ctx: &mut Self::Context) -> Self::Result {
+        log::debug!(
+            "UpdateScheduler {} unsubscribed region: {}",
+            self.cron,
+            msg.0
+        );
+        self.recipients.lock().unwrap().remove(&msg.0);
+    }
+}
+
+impl Handler<StopDispatching> for UpdateScheduler {
+    type Result = ();
+
+    fn handle(&mut self, _: StopDispatching, _ctx: &mut Self::Context) -> Self::Result {
+        log::debug!("UpdateScheduler {} stopped dispatching", self.cron);
+        self.recipients.lock().unwrap().clear();
+    }
+}
+
+impl Handler<GetSchedulerStatus> for UpdateScheduler {
+    type Result = MessageResult<GetSchedulerStatus>;
+
+    fn handle(&mut self, _: GetSchedulerStatus, _ctx: &mut Self::Context) -> Self::Result {
+        let mut regions = self
+            .recipients
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        regions.sort();
+
+        // The same cron crate parses it for the job, it was validated with the
+        // configuration.
+        let next_run = cron::Schedule::from_str(&self.cron)
+            .ok()
+            .and_then(|schedule| schedule.upcoming(Utc).next());
+
+        MessageResult(SchedulerStatus {
+            kind: self.kind,
+            schedule: self.cron.clone(),
+            next_run,
+            regions,
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{
+    export::{ExportFilter, ExportFormat, ExportTable},
+    ids::{RegionId, TypeId},
+};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Collects EVE Online market data and serves trading views"
+)]
+pub struct Cli {
+    /// Configuration file, defaults to `NOICE_CONFIG` or `noice.toml`
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    pub fn config(&self) -> Option<PathBuf> {
+        self.config
+            .clone()
+            .or_else(|| std::env::var_os("NOICE_CONFIG").map(PathBuf::from))
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the collectors and the web server (default)
+    Serve,
+    /// Export a table to CSV or Parquet
+    Export(ExportArgs),
+    /// Back up the database and check it for corruption and orphaned rows
+    Maintenance(MaintenanceArgs),
+    /// Import the static data export into the database
+    ImportSde(SdeSourceArgs),
+    /// Compare a static data export with the imported one without importing it
+    DiffSde(DiffSdeArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Table to export
+    #[arg(value_enum)]
+    pub table: ExportTable,
+    /// File to write, defaults to `<table>.<format>`
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    #[arg(short, long, value_enum, default_value = "csv")]
+    pub format: ExportFormat,
+    /// Only export rows for this region
+    #[arg(long)]
+    pub region: Option<RegionId>,
+    /// Only export rows for this item type
+    #[arg(long)]
+    pub item: Option<TypeId>,
+    /// First day to export (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+    /// Last day to export (YYYY-MM-DD)
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+}
+
+impl ExportArgs {
+    pub fn filter(&self) -> ExportFilter {
+        ExportFilter {
+            region: self.region,
+            item: self.item,
+            from: self.from,
+            to: self.to,
+        }
+    }
+
+    pub fn output(&self) -> PathBuf {
+        self.output.clone().unwrap_or_else(|| {
+            PathBuf::from(format!("{}.{}", self.table.name(), self.format.extension()))
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct MaintenanceArgs {
+    /// Write an online backup of the database to this file
+    #[arg(short, long)]
+    pub backup: Option<PathBuf>,
+    /// Only take the backup, skip integrity and orphan checks
+    #[arg(long)]
+    pub skip_checks: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SdeSourceArgs {
+    /// `sde.zip` as downloaded from CCP, or an extracted `sde` folder
+    #[arg(default_value = "./data/sde.zip")]
+    pub path: PathBuf,
+    /// Checksum file published next to `sde.zip`, defaults to `checksum` in
+    /// the same folder as the archive
+    #[arg(long)]
+    pub checksum: Option<PathBuf>,
+    /// Read the archive without verifying its checksum
+    #[arg(long)]
+    pub skip_checksum: bool,
+}
+
+impl SdeSourceArgs {
+    pub fn checksum(&self) -> PathBuf {
+        self.checksum.clone().unwrap_or_else(|| {
+            self.path
+                .parent()
+                .map(|folder| folder.join("checksum"))
+                .unwrap_or_else(|| PathBuf::from("checksum"))
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct DiffSdeArgs {
+    #[command(flatten)]
+    pub source: SdeSourceArgs,
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+    /// File to write the report to instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
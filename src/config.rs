@@ -1,7 +1,576 @@
+use crate::ids::RegionId;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    net::ToSocketAddrs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-#[derive(Debug, Deserialize, Clone)]
+/// Read when no `--config` is given, missing is fine and means all defaults.
+const DEFAULT_CONFIG: &str = "noice.toml";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error, PathBuf),
+    Toml(toml::de::Error, PathBuf),
+    /// Environment variable and the value that could not be parsed.
+    Env(String, String),
+    Invalid(Vec<String>),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(e, path) => write!(f, "Could not read {:?}: {}", path, e),
+            ConfigError::Toml(e, path) => write!(f, "Could not parse {:?}: {}", path, e),
+            ConfigError::Env(name, value) => write!(f, "Invalid value for {}: {:?}", name, value),
+            ConfigError::Invalid(problems) => {
+                write!(f, "Invalid configuration: {}", problems.join(", "))
+            }
+        }
+    }
+}
+
+/// Settings of the server and the collectors, read from `noice.toml` and
+/// overridden by `NOICE_*` environment variables.
+#[derive(Debug, Clone)]
 pub struct AppConfig {
-    pub regions: HashMap<String, usize>,
+    pub database_url: String,
+    /// Address the web server listens on, e.g. `127.0.0.1:8080`.
+    pub bind: String,
+    /// Number of ESI requests that may run at the same time.
+    pub esi_concurrency: usize,
+    /// Bearer token of the `/admin` endpoints, they are disabled without one.
+    pub admin_token: Option<String>,
+    /// Seconds open requests and running collectors get to finish on shutdown.
+    pub shutdown_timeout: u64,
+    pub history: CollectorConfig,
+    pub orders: CollectorConfig,
+    /// Credentials of a character with access to the tracked structures,
+    /// their markets are only collected with them.
+    pub sso: Option<SsoConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectorConfig {
+    /// Cron expression with seconds, e.g. `0 23 11 * * * *`.
+    pub schedule: String,
+    /// Run once for every region when the server starts.
+    pub run_on_start: bool,
+    pub regions: Vec<RegionId>,
+}
+
+/// An application registered at developers.eveonline.com and the refresh
+/// token of a character that authorized it with
+/// `esi-markets.structure_markets.v1` and `esi-universe.read_structures.v1`.
+#[derive(Debug, Clone)]
+pub struct SsoConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub refresh_token: String,
+}
+
+/// The file as written, everything is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    database_url: Option<String>,
+    bind: Option<String>,
+    esi_concurrency: Option<usize>,
+    admin_token: Option<String>,
+    shutdown_timeout: Option<u64>,
+    /// Regions of both collectors unless a collector lists its own.
+    regions: Option<Vec<RegionId>>,
+    #[serde(default)]
+    history: CollectorFile,
+    #[serde(default)]
+    orders: CollectorFile,
+    #[serde(default)]
+    sso: SsoFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CollectorFile {
+    schedule: Option<String>,
+    run_on_start: Option<bool>,
+    regions: Option<Vec<RegionId>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SsoFile {
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    refresh_token: Option<String>,
+}
+
+impl AppConfig {
+    /// Loads `path`, or `noice.toml` when it exists, applies the environment
+    /// overrides and validates the result.
+    pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let mut file = ConfigFile::read(path)?;
+
+        file.apply_env()?;
+
+        let config = file.resolve();
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Loads only the database url, for the commands that work on the
+    /// database alone. The server and collector settings are neither read
+    /// from the environment nor validated.
+    pub fn load_database_url(path: Option<&Path>) -> Result<String, ConfigError> {
+        let mut file = ConfigFile::read(path)?;
+
+        file.apply_database_env()?;
+
+        let database_url = file.resolve_database_url();
+        let mut problems = Vec::new();
+        check_database_url(&database_url, &mut problems);
+
+        if problems.is_empty() {
+            Ok(database_url)
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        check_database_url(&self.database_url, &mut problems);
+
+        if !self
+            .bind
+            .to_socket_addrs()
+            .is_ok_and(|mut addrs| addrs.next().is_some())
+        {
+            problems.push(format!("bind is not a valid address: {:?}", self.bind));
+        }
+
+        if self.esi_concurrency == 0 {
+            problems.push("esi_concurrency must be at least 1".to_string());
+        }
+
+        if self
+            .admin_token
+            .as_ref()
+            .is_some_and(|token| token.trim().is_empty())
+        {
+            problems.push("admin_token must not be empty".to_string());
+        }
+
+        for (name, collector) in [("history", &self.history), ("orders", &self.orders)] {
+            if let Err(e) = cron::Schedule::from_str(&collector.schedule) {
+                problems.push(format!(
+                    "{}.schedule {:?} is not a valid cron expression: {}",
+                    name, collector.schedule, e
+                ));
+            }
+        }
+
+        // A partial section is a mistake rather than no credentials.
+        if let Some(sso) = &self.sso {
+            for (name, value) in [
+                ("client_id", &sso.client_id),
+                ("client_secret", &sso.client_secret),
+                ("refresh_token", &sso.refresh_token),
+            ] {
+                if value.trim().is_empty() {
+                    problems.push(format!("sso.{} must be set", name));
+                }
+            }
+        }
+
+        // Usually both collectors use the top-level list, report it once.
+        if self.history.regions == self.orders.regions {
+            check_regions("regions", &self.history.regions, &mut problems);
+        } else {
+            check_regions("history.regions", &self.history.regions, &mut problems);
+            check_regions("orders.regions", &self.orders.regions, &mut problems);
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    /// Regions that have at least one collector.
+    pub fn regions(&self) -> Vec<RegionId> {
+        let mut regions = self.history.regions.clone();
+        regions.extend(self.orders.regions.iter().copied());
+        regions.sort();
+        regions.dedup();
+        regions
+    }
+}
+
+fn check_database_url(database_url: &str, problems: &mut Vec<String>) {
+    if !database_url.starts_with("sqlite:") {
+        problems.push(format!(
+            "database_url must start with `sqlite:`, got {:?}",
+            database_url
+        ));
+    }
+}
+
+fn check_regions(name: &str, regions: &[RegionId], problems: &mut Vec<String>) {
+    let mut seen = HashSet::new();
+
+    for region in regions.iter() {
+        if !(10000000..11000000).contains(&region.0) {
+            problems.push(format!("{}: {} is not a region id", name, region));
+        } else if !seen.insert(region) {
+            problems.push(format!("{}: {} is listed twice", name, region));
+        }
+    }
+}
+
+fn read_file(path: &Path) -> Result<ConfigFile, ConfigError> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| ConfigError::Io(e, path.to_path_buf()))?;
+
+    toml::from_str(&content).map_err(|e| ConfigError::Toml(e, path.to_path_buf()))
+}
+
+impl ConfigFile {
+    /// Reads `path`, or `noice.toml` when it exists.
+    fn read(path: Option<&Path>) -> Result<Self, ConfigError> {
+        match path {
+            Some(path) => read_file(path),
+            None if Path::new(DEFAULT_CONFIG).exists() => read_file(Path::new(DEFAULT_CONFIG)),
+            None => Ok(ConfigFile::default()),
+        }
+    }
+
+    fn apply_database_env(&mut self) -> Result<(), ConfigError> {
+        // `DATABASE_URL` is what sqlx and the Dockerfile already use.
+        if let Some(url) = env("DATABASE_URL")? {
+            self.database_url = Some(url);
+        }
+        if let Some(url) = env("NOICE_DATABASE_URL")? {
+            self.database_url = Some(url);
+        }
+
+        Ok(())
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        self.apply_database_env()?;
+
+        if let Some(bind) = env("NOICE_BIND")? {
+            self.bind = Some(bind);
+        }
+        if let Some(concurrency) = env("NOICE_ESI_CONCURRENCY")? {
+            self.esi_concurrency = Some(concurrency);
+        }
+        if let Some(token) = env("NOICE_ADMIN_TOKEN")? {
+            self.admin_token = Some(token);
+        }
+        if let Some(timeout) = env("NOICE_SHUTDOWN_TIMEOUT")? {
+            self.shutdown_timeout = Some(timeout);
+        }
+        if let Some(regions) = env_regions("NOICE_REGIONS")? {
+            self.regions = Some(regions);
+        }
+        if let Some(client_id) = env("NOICE_SSO_CLIENT_ID")? {
+            self.sso.client_id = Some(client_id);
+        }
+        if let Some(client_secret) = env("NOICE_SSO_CLIENT_SECRET")? {
+            self.sso.client_secret = Some(client_secret);
+        }
+        if let Some(refresh_token) = env("NOICE_SSO_REFRESH_TOKEN")? {
+            self.sso.refresh_token = Some(refresh_token);
+        }
+
+        for (prefix, collector) in [
+            ("NOICE_HISTORY", &mut self.history),
+            ("NOICE_ORDERS", &mut self.orders),
+        ] {
+            if let Some(schedule) = env(&format!("{}_SCHEDULE", prefix))? {
+                collector.schedule = Some(schedule);
+            }
+            if let Some(run_on_start) = env(&format!("{}_RUN_ON_START", prefix))? {
+                collector.run_on_start = Some(run_on_start);
+            }
+            if let Some(regions) = env_regions(&format!("{}_REGIONS", prefix))? {
+                collector.regions = Some(regions);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_database_url(&self) -> String {
+        self.database_url
+            .clone()
+            .unwrap_or_else(|| "sqlite:database.db".to_string())
+    }
+
+    fn resolve(self) -> AppConfig {
+        let database_url = self.resolve_database_url();
+        let regions = self
+            .regions
+            .unwrap_or_else(|| vec![RegionId(10000002), RegionId(10000043)]);
+
+        AppConfig {
+            database_url,
+            bind: self.bind.unwrap_or_else(|| "127.0.0.1:8080".to_string()),
+            esi_concurrency: self.esi_concurrency.unwrap_or(20),
+            admin_token: self.admin_token,
+            shutdown_timeout: self.shutdown_timeout.unwrap_or(30),
+            history: self.history.resolve("0 23 11 * * * *", true, &regions),
+            orders: self.orders.resolve("0 */6 * * * * *", false, &regions),
+            sso: self.sso.resolve(),
+        }
+    }
+}
+
+impl SsoFile {
+    fn resolve(self) -> Option<SsoConfig> {
+        if self.client_id.is_none() && self.client_secret.is_none() && self.refresh_token.is_none()
+        {
+            return None;
+        }
+
+        Some(SsoConfig {
+            client_id: self.client_id.unwrap_or_default(),
+            client_secret: self.client_secret.unwrap_or_default(),
+            refresh_token: self.refresh_token.unwrap_or_default(),
+        })
+    }
+}
+
+impl CollectorFile {
+    fn resolve(self, schedule: &str, run_on_start: bool, regions: &[RegionId]) -> CollectorConfig {
+        CollectorConfig {
+            schedule: self.schedule.unwrap_or_else(|| schedule.to_string()),
+            run_on_start: self.run_on_start.unwrap_or(run_on_start),
+            regions: self.regions.unwrap_or_else(|| regions.to_vec()),
+        }
+    }
+}
+
+fn env<T: FromStr>(name: &str) -> Result<Option<T>, ConfigError> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| ConfigError::Env(name.to_string(), value)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Comma separated region ids, an empty value disables the collector.
+fn env_regions(name: &str) -> Result<Option<Vec<RegionId>>, ConfigError> {
+    let Some(value) = env::<String>(name)? else {
+        return Ok(None);
+    };
+
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|region| !region.is_empty())
+        .map(|region| region.parse())
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+        .map_err(|_| ConfigError::Env(name.to_string(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(toml: &str) -> AppConfig {
+        toml::from_str::<ConfigFile>(toml).unwrap().resolve()
+    }
+
+    fn problems(toml: &str) -> Vec<String> {
+        match resolve(toml).validate() {
+            Ok(()) => Vec::new(),
+            Err(ConfigError::Invalid(problems)) => problems,
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let config = resolve("");
+
+        assert_eq!(config.validate().map_err(|e| e.to_string()), Ok(()));
+        assert_eq!(config.bind, "127.0.0.1:8080");
+        assert_eq!(
+            config.regions(),
+            vec![RegionId(10000002), RegionId(10000043)]
+        );
+        assert!(config.history.run_on_start);
+        assert!(!config.orders.run_on_start);
+    }
+
+    #[test]
+    fn collectors_inherit_the_top_level_regions() {
+        let config = resolve(
+            r#"
+            regions = [10000002]
+
+            [orders]
+            regions = [10000030, 10000002]
+            "#,
+        );
+
+        assert_eq!(config.history.regions, vec![RegionId(10000002)]);
+        assert_eq!(
+            config.orders.regions,
+            vec![RegionId(10000030), RegionId(10000002)]
+        );
+        assert_eq!(
+            config.regions(),
+            vec![RegionId(10000002), RegionId(10000030)]
+        );
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let problems = problems(
+            r#"
+            database_url = "postgres://localhost"
+            bind = "nowhere"
+            esi_concurrency = 0
+            admin_token = " "
+
+            [history]
+            schedule = "every day"
+            "#,
+        );
+
+        assert_eq!(problems.len(), 5, "{:?}", problems);
+        assert!(problems[0].starts_with("database_url"));
+        assert!(problems[1].starts_with("bind"));
+        assert!(problems[2].starts_with("esi_concurrency"));
+        assert!(problems[3].starts_with("admin_token"));
+        assert!(problems[4].starts_with("history.schedule"));
+    }
+
+    #[test]
+    fn shared_regions_are_checked_once() {
+        assert_eq!(
+            problems("regions = [10000002, 10000002, 30000142]"),
+            vec![
+                "regions: 10000002 is listed twice",
+                "regions: 30000142 is not a region id"
+            ]
+        );
+        assert_eq!(
+            problems("[orders]\nregions = [42]"),
+            vec!["orders.regions: 42 is not a region id"]
+        );
+    }
+
+    #[test]
+    fn database_url_ignores_the_other_sections() {
+        let file = toml::from_str::<ConfigFile>(
+            r#"
+            bind = "nowhere"
+            esi_concurrency = 0
+            regions = [42]
+
+            [history]
+            schedule = "every day"
+            "#,
+        )
+        .unwrap();
+
+        let database_url = file.resolve_database_url();
+        let mut problems = Vec::new();
+        check_database_url(&database_url, &mut problems);
+
+        assert_eq!(database_url, "sqlite:database.db");
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn sso_needs_every_credential() {
+        assert!(resolve("").sso.is_none());
+        assert_eq!(
+            problems("[sso]\nclient_id = \"abc\""),
+            vec![
+                "sso.client_secret must be set",
+                "sso.refresh_token must be set"
+            ]
+        );
+        assert!(problems(
+            r#"
+            [sso]
+            client_id = "abc"
+            client_secret = "secret"
+            refresh_token = "token"
+            "#
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<ConfigFile>("region = [10000002]").is_err());
+        assert!(toml::from_str::<ConfigFile>("[history]\ncron = \"\"").is_err());
+    }
+
+    // The only test that touches the environment, tests run in parallel.
+    #[test]
+    fn environment_overrides_the_file() {
+        std::env::set_var("NOICE_DATABASE_URL", "sqlite:env.db");
+        std::env::set_var("NOICE_ESI_CONCURRENCY", "5");
+        std::env::set_var("NOICE_REGIONS", "10000043, 10000002");
+        std::env::set_var("NOICE_ORDERS_REGIONS", "");
+        std::env::set_var("NOICE_HISTORY_RUN_ON_START", "false");
+
+        let mut file = toml::from_str::<ConfigFile>(
+            r#"
+            database_url = "sqlite:file.db"
+            esi_concurrency = 10
+            regions = [10000030]
+            "#,
+        )
+        .unwrap();
+        let applied = file.apply_env();
+
+        std::env::set_var("NOICE_ESI_CONCURRENCY", "many");
+        let invalid = ConfigFile::default().apply_env();
+
+        for name in [
+            "NOICE_DATABASE_URL",
+            "NOICE_ESI_CONCURRENCY",
+            "NOICE_REGIONS",
+            "NOICE_ORDERS_REGIONS",
+            "NOICE_HISTORY_RUN_ON_START",
+        ] {
+            std::env::remove_var(name);
+        }
+
+        applied.unwrap();
+        let config = file.resolve();
+        assert_eq!(config.database_url, "sqlite:env.db");
+        assert_eq!(config.esi_concurrency, 5);
+        assert_eq!(
+            config.history.regions,
+            vec![RegionId(10000043), RegionId(10000002)]
+        );
+        assert!(config.orders.regions.is_empty());
+        assert!(!config.history.run_on_start);
+
+        match invalid {
+            Err(ConfigError::Env(name, value)) => {
+                assert_eq!(name, "NOICE_ESI_CONCURRENCY");
+                assert_eq!(value, "many");
+            }
+            other => panic!("expected an Env error, got {:?}", other),
+        }
+    }
 }
@@ -1,11 +1,42 @@
+use crate::ids::{RegionId, TypeId};
+use std::fmt::Display;
+
 #[derive(Debug)]
 pub enum EsiError {
     ErrorResponse,
     NoPages,
-    MarketRegionType(reqwest::Error, usize, usize),
-    MarketOrder(reqwest::Error, usize, usize),
+    MarketRegionType(reqwest::Error, RegionId, usize),
+    MarketOrder(reqwest::Error, RegionId, usize),
     ErrorLimited,
-    NotPublished(usize),
+    NotPublished(TypeId),
     JsonError(reqwest::Error),
     ConnectionError(reqwest::Error),
+    /// An authenticated endpoint without SSO credentials configured.
+    Unauthenticated,
+    Sso(reqwest::Error),
+}
+
+impl Display for EsiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EsiError::ErrorResponse => write!(f, "ESI answered with an error"),
+            EsiError::NoPages => write!(f, "ESI response has no X-Pages header"),
+            EsiError::MarketRegionType(e, region_id, page) => write!(
+                f,
+                "Could not read page {} of the market types of region {}: {}",
+                page, region_id, e
+            ),
+            EsiError::MarketOrder(e, region_id, page) => write!(
+                f,
+                "Could not read page {} of the market orders of region {}: {}",
+                page, region_id, e
+            ),
+            EsiError::ErrorLimited => write!(f, "ESI error limit reached"),
+            EsiError::NotPublished(type_id) => write!(f, "type {} is not published", type_id),
+            EsiError::JsonError(e) => write!(f, "Could not parse ESI response: {}", e),
+            EsiError::ConnectionError(e) => write!(f, "Could not reach ESI: {}", e),
+            EsiError::Unauthenticated => write!(f, "ESI endpoint needs SSO credentials"),
+            EsiError::Sso(e) => write!(f, "Could not refresh the SSO token: {}", e),
+        }
+    }
 }
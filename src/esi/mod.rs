@@ -2,6 +2,11 @@ use self::{
     errors::EsiError,
     models::{MarketRegionHistory, UniverseTypeId},
 };
+use crate::{
+    config::SsoConfig,
+    eve_auth::EveAuth,
+    ids::{RegionId, StationId, TypeId},
+};
 use futures::future::try_join_all;
 use reqwest::{Client, Response};
 use serde::de::DeserializeOwned;
@@ -19,6 +24,7 @@ static mut ESI_CLIENT: Option<EsiClient> = None;
 pub struct EsiClient {
     client: Client,
     semaphore: Arc<Semaphore>,
+    auth: Option<EveAuth>,
 }
 
 impl Clone for EsiClient {
@@ -26,6 +32,7 @@ impl Clone for EsiClient {
         Self {
             client: self.client.clone(),
             semaphore: self.semaphore.clone(),
+            auth: self.auth.clone(),
         }
     }
 }
@@ -39,7 +46,7 @@ impl EsiClient {
             }
         }
     }
-    pub fn new(permits: usize) -> Self {
+    pub fn new(permits: usize, sso: Option<SsoConfig>) -> Self {
         let client = Client::builder()
             .user_agent(USER_AGENT)
             .gzip(true)
@@ -50,6 +57,7 @@ impl EsiClient {
             .expect("Failed to create client");
 
         let client = Self {
+            auth: sso.map(|sso| EveAuth::new(client.clone(), sso)),
             client,
             semaphore: Arc::new(Semaphore::new(permits)),
         };
@@ -60,7 +68,24 @@ impl EsiClient {
         client
     }
 
+    /// Whether SSO credentials are configured for the authenticated endpoints.
+    pub fn is_authenticated(&self) -> bool {
+        self.auth.is_some()
+    }
+
     pub async fn get_response(&self, path: &str) -> Result<Response, EsiError> {
+        self.send(path, None).await
+    }
+
+    /// Like `get_response`, with the access token of the configured character.
+    pub async fn get_authenticated_response(&self, path: &str) -> Result<Response, EsiError> {
+        let auth = self.auth.as_ref().ok_or(EsiError::Unauthenticated)?;
+        let token = auth.access_token().await?;
+
+        self.send(path, Some(&token)).await
+    }
+
+    async fn send(&self, path: &str, token: Option<&str>) -> Result<Response, EsiError> {
         let path = format!("{}{}", BASE_URL, path);
         tokio::time::sleep(std::time::Duration::from_millis(100)).await;
         let permit = self
@@ -68,12 +93,11 @@ impl EsiClient {
             .acquire()
             .await
             .expect("Could not acquire permit");
-        let response = self
-            .client
-            .get(path)
-            .send()
-            .await
-            .map_err(EsiError::ConnectionError)?;
+        let mut request = self.client.get(path);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await.map_err(EsiError::ConnectionError)?;
         drop(permit);
 
         if response.status().as_u16() == 420 {
@@ -94,12 +118,18 @@ impl EsiClient {
         let data = response.json::<D>().await.map_err(EsiError::JsonError)?;
         Ok(data)
     }
+
+    pub async fn get_authenticated<D: DeserializeOwned>(&self, path: &str) -> Result<D, EsiError> {
+        let response = self.get_authenticated_response(path).await?;
+        let data = response.json::<D>().await.map_err(EsiError::JsonError)?;
+        Ok(data)
+    }
 }
 
 pub async fn get_market_history(
     client: EsiClient,
-    region: usize,
-    type_id: usize,
+    region: RegionId,
+    type_id: TypeId,
 ) -> Result<MarketRegionHistory, EsiError> {
     if !is_published(client.clone(), type_id).await? {
         return Err(EsiError::NotPublished(type_id));
@@ -112,10 +142,33 @@ pub async fn get_market_history(
 
 pub async fn get_market_orders(
     client: EsiClient,
-    region: usize,
-) -> Result<Vec<models::MarketRegionOrdersItem>, EsiError> {
+    region: RegionId,
+) -> Result<(Vec<models::MarketRegionOrdersItem>, usize), EsiError> {
+    get_paged_market_orders(client, region, String::new()).await
+}
+
+/// Buy and sell orders of one type in the region.
+pub async fn get_market_orders_for_type(
+    client: EsiClient,
+    region: RegionId,
+    type_id: TypeId,
+) -> Result<(Vec<models::MarketRegionOrdersItem>, usize), EsiError> {
+    get_paged_market_orders(
+        client,
+        region,
+        format!("order_type=all&type_id={}&", type_id),
+    )
+    .await
+}
+
+/// `query` goes in front of the page parameter and ends with `&`.
+async fn get_paged_market_orders(
+    client: EsiClient,
+    region: RegionId,
+    query: String,
+) -> Result<(Vec<models::MarketRegionOrdersItem>, usize), EsiError> {
     let response = client
-        .get_response(&format!("/markets/{}/orders/?page=1", region))
+        .get_response(&format!("/markets/{}/orders/?{}page=1", region, query))
         .await?;
 
     let pages = match extract_pages(&response) {
@@ -130,30 +183,80 @@ pub async fn get_market_orders(
         .await
         .map_err(|e| EsiError::MarketOrder(e, region, 1))?;
 
-    let paged_orders =
-        try_join_all((2..pages).map(|page| get_market_orders_page(client.clone(), region, page)))
-            .await
-            .map(|x| x.into_iter().flatten().collect::<Vec<_>>())?;
+    let paged_orders = try_join_all(
+        (2..=pages).map(|page| get_market_orders_page(client.clone(), region, &query, page)),
+    )
+    .await
+    .map(|x| x.into_iter().flatten().collect::<Vec<_>>())?;
 
     orders.extend(paged_orders);
 
-    Ok(orders)
+    Ok((orders, pages))
 }
 
 async fn get_market_orders_page(
     client: EsiClient,
-    region: usize,
+    region: RegionId,
+    query: &str,
     page: usize,
 ) -> Result<Vec<models::MarketRegionOrdersItem>, EsiError> {
     client
-        .get(&format!("/markets/{}/orders/?page={}", region, page))
+        .get(&format!(
+            "/markets/{}/orders/?{}page={}",
+            region, query, page
+        ))
+        .await
+}
+
+/// Orders in a player structure, the configured character needs docking
+/// access to it.
+pub async fn get_structure_orders(
+    client: EsiClient,
+    structure_id: StationId,
+) -> Result<(Vec<models::MarketStructureOrdersItem>, usize), EsiError> {
+    let response = client
+        .get_authenticated_response(&format!("/markets/structures/{}/?page=1", structure_id))
+        .await?;
+    let pages = extract_pages(&response)?;
+
+    let mut orders = response
+        .json::<models::MarketStructureOrders>()
+        .await
+        .map_err(EsiError::JsonError)?;
+
+    let paged_orders = try_join_all((2..=pages).map(|page| {
+        let client = client.clone();
+        async move {
+            client
+                .get_authenticated::<models::MarketStructureOrders>(&format!(
+                    "/markets/structures/{}/?page={}",
+                    structure_id, page
+                ))
+                .await
+        }
+    }))
+    .await
+    .map(|x| x.into_iter().flatten().collect::<Vec<_>>())?;
+
+    orders.extend(paged_orders);
+
+    Ok((orders, pages))
+}
+
+/// Name and system of a player structure the configured character can see.
+pub async fn get_structure(
+    client: EsiClient,
+    structure_id: StationId,
+) -> Result<models::UniverseStructure, EsiError> {
+    client
+        .get_authenticated(&format!("/universe/structures/{}/", structure_id))
         .await
 }
 
 pub async fn get_market_region_types(
     client: EsiClient,
-    region: usize,
-) -> Result<Vec<i32>, EsiError> {
+    region: RegionId,
+) -> Result<Vec<TypeId>, EsiError> {
     let response = client
         .get_response(&format!("/markets/{}/types/?page=1", region))
         .await?;
@@ -165,7 +268,7 @@ pub async fn get_market_region_types(
         .map_err(|e| EsiError::MarketRegionType(e, region, 1))?;
 
     let paged_types = try_join_all(
-        (2..pages).map(|page| get_market_region_types_page(client.clone(), region, page)),
+        (2..=pages).map(|page| get_market_region_types_page(client.clone(), region, page)),
     )
     .await
     .map(|x| x.into_iter().flatten().collect::<Vec<_>>())?;
@@ -179,9 +282,9 @@ pub async fn get_market_region_types(
 
 async fn get_market_region_types_page(
     client: EsiClient,
-    region: usize,
+    region: RegionId,
     page: usize,
-) -> Result<Vec<i32>, EsiError> {
+) -> Result<Vec<TypeId>, EsiError> {
     client
         .get(&format!(
             "/markets/{}/types/?datasource=tranquility&page={}",
@@ -190,7 +293,7 @@ async fn get_market_region_types_page(
         .await
 }
 
-pub async fn is_published(client: EsiClient, type_id: usize) -> Result<bool, EsiError> {
+pub async fn is_published(client: EsiClient, type_id: TypeId) -> Result<bool, EsiError> {
     let path = format!("/universe/types/{}/", type_id);
     client
         .get::<UniverseTypeId>(&path)
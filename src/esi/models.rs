@@ -1,3 +1,4 @@
+use crate::ids::{OrderId, StationId, SystemId, TypeId};
 use chrono::{NaiveDate, DateTime, Utc};
 use serde::Deserialize;
 
@@ -20,17 +21,55 @@ pub struct MarketRegionOrdersItem {
     pub duration: u64,
     pub is_buy_order: bool,
     pub issued: DateTime<Utc>,
-    pub location_id: u64,
+    pub location_id: StationId,
     pub min_volume: u64,
-    pub order_id: u64,
+    pub order_id: OrderId,
     pub price: f64,
     pub range: MarketRegionOrderRange,
-    pub system_id: u64,
-    pub type_id: u64,
+    pub system_id: SystemId,
+    pub type_id: TypeId,
     pub volume_remain: u64,
     pub volume_total: u64,
 }
 
+pub type MarketStructureOrders = Vec<MarketStructureOrdersItem>;
+
+/// An order in a player structure, without the system the region orders have.
+#[derive(Debug, Deserialize)]
+pub struct MarketStructureOrdersItem {
+    pub duration: u64,
+    pub is_buy_order: bool,
+    pub issued: DateTime<Utc>,
+    pub location_id: StationId,
+    pub min_volume: u64,
+    pub order_id: OrderId,
+    pub price: f64,
+    pub range: MarketRegionOrderRange,
+    pub type_id: TypeId,
+    pub volume_remain: u64,
+    pub volume_total: u64,
+}
+
+impl MarketStructureOrdersItem {
+    /// The order as the region orders list it, in the system of the structure.
+    pub fn in_system(self, system_id: SystemId) -> MarketRegionOrdersItem {
+        MarketRegionOrdersItem {
+            duration: self.duration,
+            is_buy_order: self.is_buy_order,
+            issued: self.issued,
+            location_id: self.location_id,
+            min_volume: self.min_volume,
+            order_id: self.order_id,
+            price: self.price,
+            range: self.range,
+            system_id,
+            type_id: self.type_id,
+            volume_remain: self.volume_remain,
+            volume_total: self.volume_total,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub enum MarketRegionOrderRange {
     #[serde(rename = "station")]
@@ -59,10 +98,34 @@ pub enum MarketRegionOrderRange {
     R40,
 }
 
-pub type MarketRegionTypes = Vec<i32>;
+impl MarketRegionOrderRange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarketRegionOrderRange::Station => "station",
+            MarketRegionOrderRange::Region => "region",
+            MarketRegionOrderRange::SolarSystem => "solarsystem",
+            MarketRegionOrderRange::R1 => "1",
+            MarketRegionOrderRange::R2 => "2",
+            MarketRegionOrderRange::R3 => "3",
+            MarketRegionOrderRange::R4 => "4",
+            MarketRegionOrderRange::R5 => "5",
+            MarketRegionOrderRange::R10 => "10",
+            MarketRegionOrderRange::R20 => "20",
+            MarketRegionOrderRange::R30 => "30",
+            MarketRegionOrderRange::R40 => "40",
+        }
+    }
+}
+
+pub type MarketRegionTypes = Vec<TypeId>;
 
 #[derive(Debug, Deserialize)]
 pub struct UniverseTypeId {
-    pub name: String,
     pub published: bool,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct UniverseStructure {
+    pub name: String,
+    pub solar_system_id: SystemId,
+}
@@ -1,29 +1,89 @@
-// use rocket::response::Redirect;
-// use rocket_oauth2::{OAuth2, TokenResponse};
-
-// #[get("/login/eve")]
-// fn eve_login(oauth2: OAuth2<GitHub>, mut cookies: Cookies<'_>) -> Redirect {
-//     // We want the "user:read" scope. For some providers, scopes may be
-//     // pre-selected or restricted during application registration. We could
-//     // use `&[]` instead to not request any scopes, but usually scopes
-//     // should be requested during registation, in the redirect, or both.
-//     oauth2.get_redirect(&mut cookies, &["user:read"]).unwrap()
-// }
-
-// // This route, mounted at the application's Redirect URI, uses the
-// // `TokenResponse` request guard to complete the token exchange and obtain
-// // the token.
-// //
-// // The order is important here! If Cookies is positioned before
-// // TokenResponse, TokenResponse will be unable to verify the token exchange
-// // and return a failure.
-// #[get("/auth/eve")]
-// fn eve_callback(token: TokenResponse<GitHub>, mut cookies: Cookies<'_>) -> Redirect {
-//     // Set a private cookie with the access token
-//     cookies.add_private(
-//         Cookie::build("token", token.access_token().to_string())
-//             .same_site(SameSite::Lax)
-//             .finish(),
-//     );
-//     Redirect::to("/")
-// }
+use crate::{config::SsoConfig, esi::errors::EsiError};
+use reqwest::Client;
+use serde::Deserialize;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+const TOKEN_URL: &str = "https://login.eveonline.com/v2/oauth/token";
+
+/// Tokens are refreshed this long before they expire, so a token does not
+/// run out during a paged request.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Access tokens of the character of the configured refresh token, for the
+/// ESI endpoints that need one.
+#[derive(Debug, Clone)]
+pub struct EveAuth {
+    client: Client,
+    config: SsoConfig,
+    /// The current token, and the refresh token to get the next one since SSO
+    /// may rotate it.
+    state: Arc<Mutex<TokenState>>,
+}
+
+#[derive(Debug)]
+struct TokenState {
+    refresh_token: String,
+    access_token: Option<(String, Instant)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+    refresh_token: Option<String>,
+}
+
+impl EveAuth {
+    pub fn new(client: Client, config: SsoConfig) -> Self {
+        let state = TokenState {
+            refresh_token: config.refresh_token.clone(),
+            access_token: None,
+        };
+
+        Self {
+            client,
+            config,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// A valid access token, refreshed when the last one is about to expire.
+    pub async fn access_token(&self) -> Result<String, EsiError> {
+        let mut state = self.state.lock().await;
+
+        if let Some((token, expires)) = &state.access_token {
+            if Instant::now() + EXPIRY_MARGIN < *expires {
+                return Ok(token.clone());
+            }
+        }
+
+        log::debug!("Refreshing SSO access token");
+        let response = self
+            .client
+            .post(TOKEN_URL)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", state.refresh_token.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(EsiError::Sso)?
+            .json::<TokenResponse>()
+            .await
+            .map_err(EsiError::Sso)?;
+
+        if let Some(refresh_token) = response.refresh_token {
+            state.refresh_token = refresh_token;
+        }
+        let expires = Instant::now() + Duration::from_secs(response.expires_in);
+        state.access_token = Some((response.access_token.clone(), expires));
+
+        Ok(response.access_token)
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    ids::{OrderId, RegionId, TypeId},
+    repository::{RunKind, RunStats},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tokio::sync::broadcast;
+
+/// Events a subscriber can fall behind before it misses some. A snapshot of a
+/// large region changes the best price of a few thousand items.
+const CAPACITY: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// Published when new market data was saved.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MarketEvent {
+    /// A run of a collector finished, sent after the price events of the run.
+    RegionUpdated {
+        region_id: RegionId,
+        kind: RunKind,
+        run_id: i64,
+        /// Only these types were updated.
+        types: Option<Vec<TypeId>>,
+        #[serde(flatten)]
+        stats: RunStats,
+        at: DateTime<Utc>,
+    },
+    /// The lowest sell or highest buy price of an item moved, `None` when
+    /// there is no order on that side.
+    BestPriceChanged {
+        region_id: RegionId,
+        type_id: TypeId,
+        side: Side,
+        old_price: Option<f64>,
+        new_price: Option<f64>,
+    },
+    /// The order with the best price was beaten by another one while it is
+    /// still on the market.
+    OrderUndercut {
+        region_id: RegionId,
+        type_id: TypeId,
+        side: Side,
+        order_id: OrderId,
+        price: f64,
+        undercut_by: OrderId,
+        new_price: f64,
+    },
+}
+
+impl MarketEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarketEvent::RegionUpdated { .. } => "region_updated",
+            MarketEvent::BestPriceChanged { .. } => "best_price_changed",
+            MarketEvent::OrderUndercut { .. } => "order_undercut",
+        }
+    }
+
+    pub fn region_id(&self) -> RegionId {
+        match self {
+            MarketEvent::RegionUpdated { region_id, .. }
+            | MarketEvent::BestPriceChanged { region_id, .. }
+            | MarketEvent::OrderUndercut { region_id, .. } => *region_id,
+        }
+    }
+
+    pub fn type_id(&self) -> Option<TypeId> {
+        match self {
+            MarketEvent::RegionUpdated { .. } => None,
+            MarketEvent::BestPriceChanged { type_id, .. }
+            | MarketEvent::OrderUndercut { type_id, .. } => Some(*type_id),
+        }
+    }
+}
+
+/// Broadcasts market events to every connected client.
+#[derive(Debug, Clone)]
+pub struct EventBus(broadcast::Sender<MarketEvent>);
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self(broadcast::channel(CAPACITY).0)
+    }
+
+    pub fn publish(&self, event: MarketEvent) {
+        // Nobody listening is not an error.
+        let _ = self.0.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MarketEvent> {
+        self.0.subscribe()
+    }
+
+    /// Changes are only worth computing when someone receives them.
+    pub fn has_subscribers(&self) -> bool {
+        self.0.receiver_count() > 0
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The events a client wants, `None` lets everything through. Region updates
+/// have no type and only depend on the regions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventFilter {
+    pub regions: Option<HashSet<RegionId>>,
+    pub types: Option<HashSet<TypeId>>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &MarketEvent) -> bool {
+        let region_id = event.region_id();
+        self.regions
+            .as_ref()
+            .is_none_or(|regions| regions.contains(&region_id))
+            && event.type_id().is_none_or(|type_id| {
+                self.types
+                    .as_ref()
+                    .is_none_or(|types| types.contains(&type_id))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THE_FORGE: RegionId = RegionId(10000002);
+    const DOMAIN: RegionId = RegionId(10000043);
+    const TRITANIUM: TypeId = TypeId(34);
+    const PYERITE: TypeId = TypeId(35);
+
+    fn price_changed(region_id: RegionId, type_id: TypeId) -> MarketEvent {
+        MarketEvent::BestPriceChanged {
+            region_id,
+            type_id,
+            side: Side::Sell,
+            old_price: Some(4.0),
+            new_price: Some(3.9),
+        }
+    }
+
+    fn region_updated(region_id: RegionId) -> MarketEvent {
+        MarketEvent::RegionUpdated {
+            region_id,
+            kind: RunKind::Orders,
+            run_id: 1,
+            types: None,
+            stats: RunStats::default(),
+            at: Utc::now(),
+        }
+    }
+
+    fn filter(regions: Option<&[RegionId]>, types: Option<&[TypeId]>) -> EventFilter {
+        EventFilter {
+            regions: regions.map(|regions| regions.iter().copied().collect()),
+            types: types.map(|types| types.iter().copied().collect()),
+        }
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter = EventFilter::default();
+
+        assert!(filter.matches(&price_changed(THE_FORGE, TRITANIUM)));
+        assert!(filter.matches(&region_updated(DOMAIN)));
+    }
+
+    #[test]
+    fn matches_regions_and_types() {
+        let filter = filter(Some(&[THE_FORGE]), Some(&[TRITANIUM]));
+
+        assert!(filter.matches(&price_changed(THE_FORGE, TRITANIUM)));
+        assert!(!filter.matches(&price_changed(THE_FORGE, PYERITE)));
+        assert!(!filter.matches(&price_changed(DOMAIN, TRITANIUM)));
+    }
+
+    #[test]
+    fn region_updates_only_depend_on_the_regions() {
+        let types_only = filter(None, Some(&[TRITANIUM]));
+        assert!(types_only.matches(&region_updated(DOMAIN)));
+
+        let regions = filter(Some(&[THE_FORGE]), Some(&[TRITANIUM]));
+        assert!(regions.matches(&region_updated(THE_FORGE)));
+        assert!(!regions.matches(&region_updated(DOMAIN)));
+    }
+
+    #[test]
+    fn empty_lists_match_nothing() {
+        let filter = filter(Some(&[]), None);
+
+        assert!(!filter.matches(&region_updated(THE_FORGE)));
+    }
+
+    #[test]
+    fn events_are_tagged_with_their_name() {
+        let event = price_changed(THE_FORGE, TRITANIUM);
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["event"], event.name());
+        assert_eq!(json["side"], "sell");
+        assert_eq!(json["type_id"], 34);
+
+        let json = serde_json::to_value(region_updated(THE_FORGE)).unwrap();
+        assert_eq!(json["event"], "region_updated");
+        assert_eq!(json["pages_fetched"], 0);
+        assert_eq!(json["failed_types"], 0);
+    }
+}
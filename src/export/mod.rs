@@ -0,0 +1,368 @@
+use crate::ids::{RegionId, TypeId};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use futures::{stream::BoxStream, StreamExt};
+use serde::Deserialize;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use std::fmt::Display;
+
+pub use writer::ExportWriter;
+
+mod writer;
+
+/// Rows written per parquet row group, and the batch size at which buffered
+/// output is handed to the caller.
+const ROW_GROUP_SIZE: usize = 50_000;
+
+#[derive(Debug)]
+pub enum ExportError {
+    Sql(sqlx::Error),
+    Io(std::io::Error),
+    Csv(csv::Error),
+    Parquet(parquet::errors::ParquetError),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::Sql(e) => write!(f, "Could not read rows: {}", e),
+            ExportError::Io(e) => write!(f, "Could not write output: {}", e),
+            ExportError::Csv(e) => write!(f, "Could not write CSV: {}", e),
+            ExportError::Parquet(e) => write!(f, "Could not write Parquet: {}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum ExportTable {
+    MarketHistory,
+    MarketOrders,
+    Items,
+    Regions,
+    Constellations,
+    Systems,
+    Categories,
+    Groups,
+    MarketGroups,
+    MetaGroups,
+    Stargates,
+    Stations,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ColumnType {
+    Integer,
+    Real,
+    Text,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExportValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+/// Filters applied to an export. Filters that do not apply to a table, such
+/// as a date range on `eve_items`, are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub region: Option<RegionId>,
+    pub item: Option<TypeId>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl ExportTable {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportTable::MarketHistory => "market_history",
+            ExportTable::MarketOrders => "market_orders",
+            ExportTable::Items => "eve_items",
+            ExportTable::Regions => "eve_region",
+            ExportTable::Constellations => "eve_constellation",
+            ExportTable::Systems => "eve_system",
+            ExportTable::Categories => "eve_categories",
+            ExportTable::Groups => "eve_groups",
+            ExportTable::MarketGroups => "eve_market_groups",
+            ExportTable::MetaGroups => "eve_meta_groups",
+            ExportTable::Stargates => "eve_stargates",
+            ExportTable::Stations => "eve_station",
+        }
+    }
+
+    pub fn columns(&self) -> &'static [(&'static str, ColumnType)] {
+        use ColumnType::*;
+
+        match self {
+            ExportTable::MarketHistory => &[
+                ("date", Text),
+                ("item_id", Integer),
+                ("region_id", Integer),
+                ("low_price", Real),
+                ("high_price", Real),
+                ("average_price", Real),
+                ("order_count", Integer),
+                ("volume", Integer),
+            ],
+            ExportTable::MarketOrders => &[
+                ("order_id", Integer),
+                ("item_id", Integer),
+                ("region_id", Integer),
+                ("system_id", Integer),
+                ("location_id", Integer),
+                ("buy_order", Integer),
+                ("price", Real),
+                ("volume_remain", Integer),
+                ("volume_total", Integer),
+                ("min_volume", Integer),
+                ("range", Text),
+                ("duration", Integer),
+                ("issued", Text),
+                ("expiry", Text),
+                ("active", Integer),
+                ("created", Text),
+            ],
+            ExportTable::Items => &[
+                ("id", Integer),
+                ("name", Text),
+                ("published", Integer),
+                ("group_id", Integer),
+                ("market_group_id", Integer),
+                ("volume", Real),
+                ("packaged_volume", Real),
+                ("portion_size", Integer),
+                ("base_price", Real),
+                ("mass", Real),
+                ("meta_group_id", Integer),
+                ("meta_level", Integer),
+                ("tech_level", Integer),
+            ],
+            ExportTable::Regions => &[("id", Integer), ("name", Text)],
+            ExportTable::Constellations => {
+                &[("id", Integer), ("name", Text), ("region_id", Integer)]
+            }
+            ExportTable::Systems => &[
+                ("id", Integer),
+                ("name", Text),
+                ("region_id", Integer),
+                ("constellation_id", Integer),
+                ("security", Real),
+                ("security_rounded", Real),
+                ("security_class", Text),
+                ("x", Real),
+                ("y", Real),
+                ("z", Real),
+            ],
+            ExportTable::Categories => &[("id", Integer), ("name", Text), ("published", Integer)],
+            ExportTable::Groups => &[
+                ("id", Integer),
+                ("name", Text),
+                ("category_id", Integer),
+                ("published", Integer),
+            ],
+            ExportTable::MarketGroups => &[
+                ("id", Integer),
+                ("name", Text),
+                ("parent_id", Integer),
+                ("description", Text),
+                ("has_types", Integer),
+                ("icon_id", Integer),
+            ],
+            ExportTable::MetaGroups => &[("id", Integer), ("name", Text)],
+            ExportTable::Stargates => {
+                &[("source_system_id", Integer), ("target_system_id", Integer)]
+            }
+            ExportTable::Stations => &[
+                ("id", Integer),
+                ("name", Text),
+                ("system_id", Integer),
+                ("corporation_id", Integer),
+                ("type_id", Integer),
+                ("reprocessing_efficiency", Real),
+                ("reprocessing_stations_take", Real),
+            ],
+        }
+    }
+
+    /// Query selecting `columns()` in order, with one placeholder per entry
+    /// of `filters()`.
+    fn query(&self) -> &'static str {
+        match self {
+            ExportTable::MarketHistory => {
+                "SELECT date, item_id, region_id, CAST(low_price AS REAL), CAST(high_price AS REAL), CAST(average_price AS REAL), order_count, volume
+                FROM market_history
+                WHERE (?1 IS NULL OR region_id = ?1) AND (?2 IS NULL OR item_id = ?2) AND (?3 IS NULL OR date >= ?3) AND (?4 IS NULL OR date <= ?4)
+                ORDER BY date, item_id"
+            }
+            ExportTable::MarketOrders => {
+                "SELECT o.order_id, o.item_id, s.region_id, o.system_id, o.location_id, o.buy_order, CAST(o.price AS REAL), o.volume_remain, o.volume_total, o.min_volume, o.range, o.duration, o.issued, o.expiry, o.active, o.created
+                FROM market_orders o JOIN eve_system s ON s.id = o.system_id
+                WHERE (?1 IS NULL OR s.region_id = ?1) AND (?2 IS NULL OR o.item_id = ?2) AND (?3 IS NULL OR date(o.created) >= ?3) AND (?4 IS NULL OR date(o.created) <= ?4)
+                ORDER BY o.created, o.order_id"
+            }
+            ExportTable::Items => {
+                "SELECT id, name, published, group_id, market_group_id, volume, packaged_volume, portion_size, base_price, mass, meta_group_id, meta_level, tech_level
+                FROM eve_items WHERE (?1 IS NULL OR id = ?1) ORDER BY id"
+            }
+            ExportTable::Regions => "SELECT id, name FROM eve_region WHERE (?1 IS NULL OR id = ?1) ORDER BY id",
+            ExportTable::Constellations => {
+                "SELECT id, name, region_id FROM eve_constellation WHERE (?1 IS NULL OR region_id = ?1) ORDER BY id"
+            }
+            ExportTable::Systems => {
+                "SELECT id, name, region_id, constellation_id, security, security_rounded, security_class, x, y, z FROM eve_system WHERE (?1 IS NULL OR region_id = ?1) ORDER BY id"
+            }
+            ExportTable::Categories => "SELECT id, name, published FROM eve_categories ORDER BY id",
+            ExportTable::Groups => {
+                "SELECT id, name, category_id, published FROM eve_groups ORDER BY id"
+            }
+            ExportTable::MarketGroups => {
+                "SELECT id, name, parent_id, description, has_types, icon_id FROM eve_market_groups ORDER BY id"
+            }
+            ExportTable::MetaGroups => "SELECT id, name FROM eve_meta_groups ORDER BY id",
+            ExportTable::Stargates => {
+                "SELECT g.source_system_id, g.target_system_id FROM eve_stargates g JOIN eve_system s ON s.id = g.source_system_id
+                WHERE (?1 IS NULL OR s.region_id = ?1)
+                ORDER BY g.source_system_id, g.target_system_id"
+            }
+            ExportTable::Stations => {
+                "SELECT st.id, st.name, st.system_id, st.corporation_id, st.type_id, st.reprocessing_efficiency, st.reprocessing_stations_take
+                FROM eve_station st JOIN eve_system s ON s.id = st.system_id
+                WHERE (?1 IS NULL OR s.region_id = ?1)
+                ORDER BY st.id"
+            }
+        }
+    }
+
+    fn filters(&self) -> &'static [FilterField] {
+        use FilterField::*;
+
+        match self {
+            ExportTable::MarketHistory | ExportTable::MarketOrders => &[Region, Item, From, To],
+            ExportTable::Items => &[Item],
+            ExportTable::Regions
+            | ExportTable::Constellations
+            | ExportTable::Systems
+            | ExportTable::Stargates
+            | ExportTable::Stations => &[Region],
+            ExportTable::Categories
+            | ExportTable::Groups
+            | ExportTable::MarketGroups
+            | ExportTable::MetaGroups => &[],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FilterField {
+    Region,
+    Item,
+    From,
+    To,
+}
+
+/// Streams the rows of `table` matching `filter` without loading them into memory.
+pub fn rows<'a>(
+    pool: &'a SqlitePool,
+    table: ExportTable,
+    filter: &ExportFilter,
+) -> BoxStream<'a, Result<Vec<ExportValue>, sqlx::Error>> {
+    let columns = table.columns();
+
+    let mut query = sqlx::query(table.query());
+    for field in table.filters() {
+        query = match field {
+            FilterField::Region => query.bind(filter.region),
+            FilterField::Item => query.bind(filter.item),
+            FilterField::From => query.bind(filter.from),
+            FilterField::To => query.bind(filter.to),
+        };
+    }
+
+    query
+        .fetch(pool)
+        .map(move |row| row.and_then(|row| read_row(&row, columns)))
+        .boxed()
+}
+
+/// Reads `columns` from `row`. SQLite columns can hold values of any type, so
+/// values are converted to the export type instead of being type checked.
+fn read_row(
+    row: &SqliteRow,
+    columns: &[(&str, ColumnType)],
+) -> Result<Vec<ExportValue>, sqlx::Error> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(index, (_, column_type))| {
+            let value = match column_type {
+                ColumnType::Integer => row
+                    .try_get_unchecked::<Option<i64>, _>(index)?
+                    .map(ExportValue::Integer),
+                ColumnType::Real => row
+                    .try_get_unchecked::<Option<f64>, _>(index)?
+                    .map(ExportValue::Real),
+                ColumnType::Text => row
+                    .try_get_unchecked::<Option<String>, _>(index)?
+                    .map(ExportValue::Text),
+            };
+
+            Ok(value.unwrap_or(ExportValue::Null))
+        })
+        .collect()
+}
+
+/// Exports `table` to the file at `path`.
+pub async fn export_to_file(
+    pool: &SqlitePool,
+    table: ExportTable,
+    format: ExportFormat,
+    filter: &ExportFilter,
+    path: &std::path::Path,
+) -> Result<usize, ExportError> {
+    let file = std::fs::File::create(path).map_err(ExportError::Io)?;
+    let mut writer = ExportWriter::new(
+        format,
+        table.name(),
+        table.columns(),
+        std::io::BufWriter::new(file),
+    )?;
+
+    let mut rows = rows(pool, table, filter);
+    let mut count = 0;
+
+    while let Some(row) = rows.next().await {
+        writer.write_row(row.map_err(ExportError::Sql)?)?;
+        count += 1;
+    }
+
+    writer.finish()?;
+
+    Ok(count)
+}
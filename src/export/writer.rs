@@ -0,0 +1,298 @@
+use super::{ColumnType, ExportError, ExportFormat, ExportValue, ROW_GROUP_SIZE};
+use parquet::{
+    basic::{ConvertedType, Repetition, Type as PhysicalType},
+    data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::types::Type,
+};
+use std::{io::Write, sync::Arc};
+
+pub enum ExportWriter<W: Write + Send> {
+    Csv(csv::Writer<W>),
+    Parquet(ParquetWriter<W>),
+}
+
+pub struct ParquetWriter<W: Write + Send> {
+    writer: SerializedFileWriter<W>,
+    columns: &'static [(&'static str, ColumnType)],
+    rows: Vec<Vec<ExportValue>>,
+}
+
+impl<W: Write + Send> ExportWriter<W> {
+    pub fn new(
+        format: ExportFormat,
+        name: &str,
+        columns: &'static [(&'static str, ColumnType)],
+        inner: W,
+    ) -> Result<Self, ExportError> {
+        match format {
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(inner);
+                writer
+                    .write_record(columns.iter().map(|(column, _)| column))
+                    .map_err(ExportError::Csv)?;
+
+                Ok(ExportWriter::Csv(writer))
+            }
+            ExportFormat::Parquet => {
+                let fields = columns
+                    .iter()
+                    .map(|(column, column_type)| {
+                        let builder = match column_type {
+                            ColumnType::Integer => {
+                                Type::primitive_type_builder(column, PhysicalType::INT64)
+                            }
+                            ColumnType::Real => {
+                                Type::primitive_type_builder(column, PhysicalType::DOUBLE)
+                            }
+                            ColumnType::Text => {
+                                Type::primitive_type_builder(column, PhysicalType::BYTE_ARRAY)
+                                    .with_converted_type(ConvertedType::UTF8)
+                            }
+                        };
+
+                        builder
+                            .with_repetition(Repetition::OPTIONAL)
+                            .build()
+                            .map(Arc::new)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(ExportError::Parquet)?;
+
+                let schema = Type::group_type_builder(name)
+                    .with_fields(fields)
+                    .build()
+                    .map_err(ExportError::Parquet)?;
+
+                let writer = SerializedFileWriter::new(
+                    inner,
+                    Arc::new(schema),
+                    Arc::new(WriterProperties::builder().build()),
+                )
+                .map_err(ExportError::Parquet)?;
+
+                Ok(ExportWriter::Parquet(ParquetWriter {
+                    writer,
+                    columns,
+                    rows: Vec::with_capacity(ROW_GROUP_SIZE),
+                }))
+            }
+        }
+    }
+
+    pub fn write_row(&mut self, row: Vec<ExportValue>) -> Result<(), ExportError> {
+        match self {
+            ExportWriter::Csv(writer) => writer
+                .write_record(row.iter().map(|value| match value {
+                    ExportValue::Null => String::new(),
+                    ExportValue::Integer(value) => value.to_string(),
+                    ExportValue::Real(value) => value.to_string(),
+                    ExportValue::Text(value) => value.clone(),
+                }))
+                .map_err(ExportError::Csv),
+            ExportWriter::Parquet(writer) => {
+                writer.rows.push(row);
+                if writer.rows.len() >= ROW_GROUP_SIZE {
+                    writer.flush_row_group()?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes any buffered rows and the file footer, returning the inner writer.
+    pub fn finish(self) -> Result<W, ExportError> {
+        match self {
+            ExportWriter::Csv(writer) => writer
+                .into_inner()
+                .map_err(|e| ExportError::Io(e.into_error())),
+            ExportWriter::Parquet(mut writer) => {
+                writer.flush_row_group()?;
+                writer.writer.into_inner().map_err(ExportError::Parquet)
+            }
+        }
+    }
+}
+
+impl ExportWriter<Vec<u8>> {
+    /// Takes the output produced so far, so it can be sent while the export
+    /// continues. Parquet output only grows once a row group is complete.
+    pub fn take_buffered(&mut self) -> Result<Vec<u8>, ExportError> {
+        match self {
+            ExportWriter::Csv(writer) => {
+                let written = std::mem::replace(writer, csv::Writer::from_writer(Vec::new()));
+                written
+                    .into_inner()
+                    .map_err(|e| ExportError::Io(e.into_error()))
+            }
+            ExportWriter::Parquet(writer) => Ok(std::mem::take(writer.writer.inner_mut())),
+        }
+    }
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    fn flush_row_group(&mut self) -> Result<(), ExportError> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        let mut row_group = self.writer.next_row_group().map_err(ExportError::Parquet)?;
+
+        for (index, (_, column_type)) in self.columns.iter().enumerate() {
+            let mut column = row_group
+                .next_column()
+                .map_err(ExportError::Parquet)?
+                .expect("Parquet schema has a column for every export column");
+
+            let levels = self
+                .rows
+                .iter()
+                .map(|row| match row[index] {
+                    ExportValue::Null => 0,
+                    _ => 1,
+                })
+                .collect::<Vec<i16>>();
+
+            let written = match column_type {
+                ColumnType::Integer => {
+                    let values = self
+                        .rows
+                        .iter()
+                        .filter_map(|row| match row[index] {
+                            ExportValue::Integer(value) => Some(value),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+                ColumnType::Real => {
+                    let values = self
+                        .rows
+                        .iter()
+                        .filter_map(|row| match row[index] {
+                            ExportValue::Real(value) => Some(value),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
+                    column
+                        .typed::<DoubleType>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+                ColumnType::Text => {
+                    let values = self
+                        .rows
+                        .iter()
+                        .filter_map(|row| match &row[index] {
+                            ExportValue::Text(value) => Some(ByteArray::from(value.as_str())),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+            };
+
+            written.map_err(ExportError::Parquet)?;
+            column.close().map_err(ExportError::Parquet)?;
+        }
+
+        row_group.close().map_err(ExportError::Parquet)?;
+        self.rows.clear();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parquet::{
+        file::reader::{FileReader, SerializedFileReader},
+        record::Field,
+    };
+
+    const COLUMNS: &[(&str, ColumnType)] = &[
+        ("id", ColumnType::Integer),
+        ("price", ColumnType::Real),
+        ("name", ColumnType::Text),
+    ];
+
+    fn write(format: ExportFormat) -> Vec<u8> {
+        let mut writer = ExportWriter::new(format, "items", COLUMNS, Vec::new()).unwrap();
+        writer
+            .write_row(vec![
+                ExportValue::Integer(34),
+                ExportValue::Real(3.5),
+                ExportValue::Text("Tritanium, \"raw\"".to_string()),
+            ])
+            .unwrap();
+        writer
+            .write_row(vec![
+                ExportValue::Integer(35),
+                ExportValue::Null,
+                ExportValue::Null,
+            ])
+            .unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn csv_has_a_header_and_empty_nulls() {
+        let csv = String::from_utf8(write(ExportFormat::Csv)).unwrap();
+
+        assert_eq!(
+            csv,
+            "id,price,name\n34,3.5,\"Tritanium, \"\"raw\"\"\"\n35,,\n"
+        );
+    }
+
+    #[test]
+    fn parquet_keeps_types_and_nulls() {
+        let parquet = write(ExportFormat::Parquet);
+        let reader = SerializedFileReader::new(actix_web::web::Bytes::from(parquet)).unwrap();
+
+        let rows = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                row.unwrap()
+                    .get_column_iter()
+                    .map(|(_, field)| field.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rows,
+            vec![
+                vec![
+                    Field::Long(34),
+                    Field::Double(3.5),
+                    Field::Str("Tritanium, \"raw\"".to_string()),
+                ],
+                vec![Field::Long(35), Field::Null, Field::Null],
+            ]
+        );
+    }
+
+    #[test]
+    fn buffered_output_is_taken_in_parts() {
+        let mut writer =
+            ExportWriter::new(ExportFormat::Csv, "items", COLUMNS, Vec::new()).unwrap();
+        let header = writer.take_buffered().unwrap();
+        writer
+            .write_row(vec![
+                ExportValue::Integer(34),
+                ExportValue::Real(3.5),
+                ExportValue::Text("Tritanium".to_string()),
+            ])
+            .unwrap();
+        let row = writer.take_buffered().unwrap();
+
+        assert_eq!(header, b"id,price,name\n");
+        assert_eq!(row, b"34,3.5,Tritanium\n");
+    }
+}
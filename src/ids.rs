@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, num::ParseIntError, str::FromStr};
+
+/// Declares an EVE id newtype. Ids are stored as `INTEGER` in SQLite and as
+/// plain numbers in JSON, so the newtypes are transparent for sqlx and serde.
+macro_rules! eve_id {
+    ($($(#[$meta:meta])* $name:ident),* $(,)?) => {
+        $(
+            $(#[$meta])*
+            #[derive(
+                Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+            )]
+            #[serde(transparent)]
+            #[sqlx(transparent)]
+            pub struct $name(pub i64);
+
+            impl Display for $name {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    write!(f, "{}", self.0)
+                }
+            }
+
+            impl FromStr for $name {
+                type Err = ParseIntError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    s.parse().map($name)
+                }
+            }
+
+            impl From<i64> for $name {
+                fn from(id: i64) -> Self {
+                    $name(id)
+                }
+            }
+        )*
+    };
+}
+
+eve_id!(
+    /// Item type, `eve_items.id`.
+    TypeId,
+    /// Region, `eve_region.id`.
+    RegionId,
+    /// Constellation, `eve_constellation.id`.
+    ConstellationId,
+    /// Solar system, `eve_system.id`.
+    SystemId,
+    /// NPC station or player structure an order is placed in.
+    StationId,
+    /// Market order.
+    OrderId,
+    /// Character, for authenticated ESI calls.
+    #[allow(dead_code)]
+    CharacterId,
+);
@@ -1,21 +1,38 @@
 use actix::{Actor, Addr};
 use actix_web::{web, App, HttpServer};
-use actors::{MarketHistoryActor, MarketOrderActor, StartActor, UpdateScheduler};
+use actors::{RegionManager, Shutdown, TrackRegion, UpdateScheduler};
+use clap::Parser;
+use cli::{Cli, Command, DiffSdeArgs, ExportArgs, MaintenanceArgs, SdeSourceArgs};
+use config::{AppConfig, ConfigError};
 use esi::EsiClient;
+use events::EventBus;
+use ids::RegionId;
 use log::LevelFilter;
-use repository::{ItemRepository, MarketHistoryRepository, MarketOrderRepository};
-use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
-use std::{sync::Arc, time::Duration};
+use repository::{
+    IndustryRepository, ItemRepository, MarketHistoryRepository, MarketOrderRepository, RunKind,
+    StationRepository, SystemRepository, TrackedRegion, TrackedRegionRepository,
+    TrackedStructureRepository, TranslationRepository, UpdateLogRepository,
+};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    SqlitePool,
+};
+use std::{path::Path, str::FromStr, sync::Arc, time::Duration};
 use tokio::{sync::Mutex, task::JoinHandle};
 
 mod actions;
 mod actors;
-mod cache;
+mod cli;
 mod config;
 mod esi;
 mod eve_auth;
+mod events;
+mod export;
+mod ids;
+mod maintenance;
 mod repository;
 mod routes;
+mod sde;
 
 pub struct ActixHandle(Arc<JoinHandle<()>>);
 
@@ -33,119 +50,435 @@ impl ActixHandle {
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    let cli = Cli::parse();
+
     env_logger::builder()
         .filter(Some("noice"), LevelFilter::Trace)
         .filter(None, LevelFilter::Info)
         .init();
 
-    EsiClient::new(20);
+    let config_path = cli.config();
+    let config_path = config_path.as_deref();
+
+    match cli.command {
+        Some(Command::Export(args)) => export(open_database(config_path).await?, args).await,
+        Some(Command::Maintenance(args)) => {
+            maintenance(open_database(config_path).await?, args).await
+        }
+        Some(Command::ImportSde(args)) => import_sde(open_database(config_path).await?, args).await,
+        Some(Command::DiffSde(args)) => diff_sde(open_database(config_path).await?, args).await,
+        Some(Command::Serve) | None => {
+            let config = AppConfig::load(config_path).map_err(config_error)?;
+            let pool = load_sqlite(&config.database_url).await;
+
+            serve(pool, config).await
+        }
+    }
+}
+
+fn config_error(e: ConfigError) -> std::io::Error {
+    std::io::Error::other(e.to_string())
+}
+
+/// Opens the database for the commands other than `serve`, they only read the
+/// database url from the configuration.
+async fn open_database(config_path: Option<&Path>) -> std::io::Result<SqlitePool> {
+    let database_url = AppConfig::load_database_url(config_path).map_err(config_error)?;
 
-    let pool = load_sqlite().await;
+    Ok(load_sqlite(&database_url).await)
+}
+
+async fn serve(pool: SqlitePool, config: AppConfig) -> std::io::Result<()> {
+    let tracked_region_repository =
+        TrackedRegionRepository::new(Arc::new(Mutex::new(pool.clone())));
+    let tracked_regions = load_tracked_regions(&pool, &tracked_region_repository, &config).await?;
+
+    EsiClient::new(config.esi_concurrency, config.sso.clone());
 
     let market_history_repository =
         MarketHistoryRepository::new(Arc::new(Mutex::new(pool.clone())));
     let item_repository = ItemRepository::new(Arc::new(Mutex::new(pool.clone())));
     let market_order_repository = MarketOrderRepository::new(Arc::new(Mutex::new(pool.clone())));
+    let update_log_repository = UpdateLogRepository::new(Arc::new(Mutex::new(pool.clone())));
+    let station_repository = StationRepository::new(Arc::new(Mutex::new(pool.clone())));
+    let system_repository = SystemRepository::new(Arc::new(Mutex::new(pool.clone())));
+    let industry_repository = IndustryRepository::new(Arc::new(Mutex::new(pool.clone())));
+    let translation_repository = TranslationRepository::new(Arc::new(Mutex::new(pool.clone())));
+    let tracked_structure_repository =
+        TrackedStructureRepository::new(Arc::new(Mutex::new(pool.clone())));
+
+    match update_log_repository.clone().interrupt_unfinished().await {
+        Ok(0) => {}
+        Ok(count) => log::warn!("Marked {} runs of the last start as interrupted", count),
+        Err(e) => log::error!("Could not mark unfinished runs: {}", e),
+    }
+
+    let events = EventBus::new();
 
-    let _system = start_actors(
+    let system = start_actors(
         market_history_repository.clone(),
         item_repository.clone(),
         market_order_repository.clone(),
+        update_log_repository.clone(),
+        events.clone(),
+        tracked_regions,
+        config.clone(),
     )
-    .await;
+    .await
+    .map_err(std::io::Error::other)?;
+    let bind = config.bind.clone();
+    let shutdown_timeout = Duration::from_secs(config.shutdown_timeout);
+    let region_manager = system.region_manager.clone();
 
     HttpServer::new(move || {
         let mhr = market_history_repository.clone();
         let ir = item_repository.clone();
         let mor = market_order_repository.clone();
+        let ulr = update_log_repository.clone();
+        let sr = station_repository.clone();
+        let syr = system_repository.clone();
+        let inr = industry_repository.clone();
+        let tr = translation_repository.clone();
+        let trr = tracked_region_repository.clone();
+        let tsr = tracked_structure_repository.clone();
         App::new()
             .app_data(web::Data::new(mhr))
             .app_data(web::Data::new(ir))
             .app_data(web::Data::new(mor))
+            .app_data(web::Data::new(ulr))
+            .app_data(web::Data::new(sr))
+            .app_data(web::Data::new(syr))
+            .app_data(web::Data::new(inr))
+            .app_data(web::Data::new(tr))
+            .app_data(web::Data::new(trr))
+            .app_data(web::Data::new(tsr))
+            .app_data(web::Data::new(config.clone()))
+            .app_data(web::Data::new(system.region_manager.clone()))
+            .app_data(web::Data::new(events.clone()))
             .app_data(web::Data::new(pool.clone()))
+            .service(routes::margin_table)
+            .service(routes::item_search)
+            .service(routes::item_details)
+            .service(routes::item_history)
+            .service(routes::market_group_tree)
+            .service(routes::categories)
+            .service(routes::meta_groups)
+            .service(routes::status_report)
+            .service(routes::status_runs)
+            .service(routes::status_run)
+            .service(routes::status_page)
+            .service(routes::station_search)
+            .service(routes::station_details)
+            .service(routes::station_prices)
+            .service(routes::system_route)
+            .service(routes::blueprint_details)
+            .service(routes::item_manufacturing)
+            .service(routes::item_reprocessing)
+            .service(routes::reprocessing_table)
+            .service(routes::export_table)
+            .service(routes::admin_regions)
+            .service(routes::add_region)
+            .service(routes::pause_region)
+            .service(routes::resume_region)
+            .service(routes::remove_region)
+            .service(routes::refresh_region)
+            .service(routes::admin_structures)
+            .service(routes::add_structure)
+            .service(routes::pause_structure)
+            .service(routes::resume_structure)
+            .service(routes::remove_structure)
+            .service(routes::event_stream)
+            .service(routes::event_socket)
             // .app_data(web::Data::new(system.clone()))
-            // .service(routes::margin_table)
 
         // .service(factory)
     })
-    .bind(("127.0.0.1", 8080))?
+    .bind(bind.as_str())?
+    .shutdown_timeout(shutdown_timeout.as_secs())
     .run()
     .await?;
 
-    // _system.abort();
+    // The server stops on SIGINT and SIGTERM, then the collectors get the same
+    // time to reach a safe point.
+    log::info!("Server stopped, stopping collectors");
+    if let Err(e) = region_manager.send(Shutdown(shutdown_timeout)).await {
+        log::error!("Could not stop collectors: {}", e);
+    }
+
+    Ok(())
+}
+
+async fn export(pool: SqlitePool, args: ExportArgs) -> std::io::Result<()> {
+    let output = args.output();
+
+    log::info!("Exporting {} to {:?}", args.table.name(), output);
+
+    let rows = export::export_to_file(&pool, args.table, args.format, &args.filter(), &output)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Export failed: {}", e)))?;
+
+    log::info!("Exported {} rows to {:?}", rows, output);
+
+    Ok(())
+}
+
+async fn maintenance(pool: SqlitePool, args: MaintenanceArgs) -> std::io::Result<()> {
+    if let Some(path) = &args.backup {
+        log::info!("Backing up database to {:?}", path);
+
+        maintenance::backup(&pool, path)
+            .await
+            .map_err(|e| std::io::Error::other(format!("Backup failed: {}", e)))?;
+
+        log::info!("Backup written to {:?}", path);
+    }
+
+    if args.skip_checks {
+        return Ok(());
+    }
+
+    let report = maintenance::check(&pool)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Checks failed: {}", e)))?;
+
+    for line in report.integrity.iter().filter(|line| *line != "ok") {
+        log::error!("Integrity check: {}", line);
+    }
+
+    for violation in report.foreign_keys.iter() {
+        log::warn!(
+            "{} rows in {} violate their foreign key to {}",
+            violation.count,
+            violation.table,
+            violation.parent
+        );
+    }
+
+    for orphan in report.orphans.iter().filter(|orphan| orphan.count > 0) {
+        log::warn!(
+            "{} rows in {} reference a missing {}, e.g. {:?}",
+            orphan.count,
+            orphan.table,
+            orphan.column,
+            orphan.sample_ids
+        );
+    }
+
+    if !report.is_healthy() {
+        return Err(std::io::Error::other("Database checks found problems"));
+    }
+
+    log::info!("Database checks passed");
 
     Ok(())
 }
 
-async fn load_sqlite() -> SqlitePool {
-    let sqlite_path = std::env::var("DATABASE_URL").unwrap_or("sqlite:database.db".to_string());
+/// Verifies the checksum of an archive and loads the SDE from it.
+fn read_sde(args: &SdeSourceArgs) -> std::io::Result<sde::Sde> {
+    log::info!("Reading SDE from: {:?}", args.path);
+
+    if args.path.is_file() && !args.skip_checksum {
+        let checksum = args.checksum();
+
+        log::info!("Verifying {:?} against {:?}", args.path, checksum);
+
+        sde::verify_checksum(&args.path, &checksum)
+            .map_err(|e| std::io::Error::other(format!("SDE checksum failed: {}", e)))?;
+    }
+
+    let sde = sde::SdeSource::open(&args.path)
+        .and_then(|mut source| sde::read_sde(&mut source))
+        .map_err(|e| std::io::Error::other(format!("Could not read SDE: {}", e)))?;
+
+    Ok(sde)
+}
+
+async fn import_sde(pool: SqlitePool, args: SdeSourceArgs) -> std::io::Result<()> {
+    let sde = read_sde(&args)?;
 
+    let report = sde::import_sde(&pool, &sde)
+        .await
+        .map_err(|e| std::io::Error::other(format!("SDE import failed: {}", e)))?;
+
+    for table in report.tables.iter() {
+        log::info!(
+            "{}: {} added, {} updated, {} removed",
+            table.table,
+            table.added,
+            table.updated,
+            table.removed
+        );
+
+        if table.stale > 0 {
+            log::warn!(
+                "{}: {} rows are no longer in the SDE and were kept",
+                table.table,
+                table.stale
+            );
+        }
+    }
+
+    if !report.has_changes() {
+        log::info!("SDE is up to date");
+    }
+
+    Ok(())
+}
+
+async fn diff_sde(pool: SqlitePool, args: DiffSdeArgs) -> std::io::Result<()> {
+    let sde = read_sde(&args.source)?;
+
+    let diff = sde::diff_sde(&pool, &sde)
+        .await
+        .map_err(|e| std::io::Error::other(format!("SDE diff failed: {}", e)))?;
+
+    let report = if args.json {
+        serde_json::to_string_pretty(&diff).map_err(std::io::Error::other)?
+    } else {
+        diff.to_string()
+    };
+
+    match args.output {
+        Some(output) => {
+            std::fs::write(&output, report)?;
+            log::info!("Wrote SDE diff to {:?}", output);
+        }
+        None => println!("{}", report.trim_end()),
+    }
+
+    Ok(())
+}
+
+/// Seeds the tracked regions from the configuration on the first start, after
+/// that the admin API manages them.
+async fn load_tracked_regions(
+    pool: &SqlitePool,
+    tracked_region_repository: &TrackedRegionRepository,
+    config: &AppConfig,
+) -> std::io::Result<Vec<TrackedRegion>> {
+    let read_error = |e| std::io::Error::other(format!("Could not read tracked regions: {}", e));
+
+    let regions = tracked_region_repository.all().await.map_err(read_error)?;
+    if !regions.is_empty() {
+        log::info!(
+            "Tracking {} regions from the database, the configured regions are ignored",
+            regions.len()
+        );
+
+        let ignored = config
+            .regions()
+            .into_iter()
+            .filter(|region_id| !regions.iter().any(|region| region.region_id == *region_id))
+            .map(|region_id| region_id.to_string())
+            .collect::<Vec<_>>();
+        if !ignored.is_empty() {
+            log::warn!(
+                "Configured regions {} are not tracked, add them with POST /admin/regions",
+                ignored.join(", ")
+            );
+        }
+
+        return Ok(regions);
+    }
+
+    check_regions(pool, config).await?;
+    tracked_region_repository
+        .seed(&config.history.regions, &config.orders.regions)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Could not seed tracked regions: {}", e)))?;
+
+    tracked_region_repository.all().await.map_err(read_error)
+}
+
+/// The collectors log every run against its region, so configured regions must
+/// be imported from the SDE.
+async fn check_regions(pool: &SqlitePool, config: &AppConfig) -> std::io::Result<()> {
+    let known = sqlx::query_scalar!(r#"SELECT id as "id: RegionId" FROM eve_region"#)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| std::io::Error::other(format!("Could not read regions: {}", e)))?;
+
+    let unknown = config
+        .regions()
+        .into_iter()
+        .filter(|region| !known.contains(region))
+        .map(|region| region.to_string())
+        .collect::<Vec<_>>();
+
+    if !unknown.is_empty() {
+        return Err(std::io::Error::other(format!(
+            "Unknown regions in configuration: {}, import the SDE first with `noice2 import-sde`",
+            unknown.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+async fn load_sqlite(sqlite_path: &str) -> SqlitePool {
     log::info!("Reading sqlite path: {}", sqlite_path);
 
+    // WAL lets readers, like a backup, run while the collectors write.
+    let options = SqliteConnectOptions::from_str(sqlite_path)
+        .unwrap()
+        .journal_mode(SqliteJournalMode::Wal);
+
     SqlitePoolOptions::new()
         .acquire_timeout(Duration::from_secs(30))
         .idle_timeout(Some(Duration::from_secs(30)))
         .max_lifetime(Some(Duration::from_secs(200)))
-        .connect(&sqlite_path)
+        .connect_with(options)
         .await
         .unwrap()
 }
 
-async fn start_actors(
+fn start_actors(
     market_history_repository: MarketHistoryRepository,
     item_repository: ItemRepository,
     market_order_repository: MarketOrderRepository,
+    update_log_repository: UpdateLogRepository,
+    events: EventBus,
+    tracked_regions: Vec<TrackedRegion>,
+    config: AppConfig,
 ) -> tokio::task::JoinHandle<ActorHolder> {
     actix::spawn(async move {
-        let history_actors = actors::load_market_history_actors(
-            &[10000002, 10000043],
-            market_history_repository,
-            item_repository.clone(),
-        );
+        let history_scheduler =
+            UpdateScheduler::new(RunKind::History, config.history.schedule.clone()).start();
+        let order_scheduler =
+            UpdateScheduler::new(RunKind::Orders, config.orders.schedule.clone()).start();
 
-        let order_actors = actors::load_market_order_actors(
-            &[10000002, 10000043],
+        let region_manager = RegionManager::new(
+            market_history_repository,
             market_order_repository,
-            item_repository.clone(),
-        );
+            item_repository,
+            update_log_repository,
+            events,
+            history_scheduler.clone(),
+            order_scheduler.clone(),
+        )
+        .start();
 
-        history_actors.iter().for_each(|s| s.do_send(StartActor));
-        // order_actors.iter().for_each(|s| s.do_send(StartActor));
-
-        let history_scheduler = actors::UpdateScheduler::new(
-            "0 23 11 * * * *".to_string(),
-            history_actors
-                .iter()
-                .map(|x| x.clone().recipient())
-                .collect(),
-        );
-        let order_scheduler = actors::UpdateScheduler::new(
-            "0 */6 * * * * *".to_string(),
-            order_actors.iter().map(|x| x.clone().recipient()).collect(),
-        );
-
-        let history_actors = MarketHistoryActors(history_actors);
-        let order_actors = MarketOrderActors(order_actors);
+        for region in tracked_regions.iter().filter(|region| !region.paused) {
+            region_manager.do_send(TrackRegion {
+                region_id: region.region_id,
+                history: region.history,
+                orders: region.orders,
+                start_history: config.history.run_on_start,
+                start_orders: config.orders.run_on_start,
+            });
+        }
 
         ActorHolder {
-            _history_actors: history_actors,
-            _order_actors: order_actors,
-            _history_scheduler: history_scheduler.start(),
-            _order_scheduler: order_scheduler.start(),
+            region_manager,
+            _history_scheduler: history_scheduler,
+            _order_scheduler: order_scheduler,
         }
     })
 }
 
-#[derive(Debug, Clone)]
-pub struct MarketHistoryActors(Vec<Addr<MarketHistoryActor>>);
-
-#[derive(Debug, Clone)]
-pub struct MarketOrderActors(Vec<Addr<MarketOrderActor>>);
-
 #[derive(Debug, Clone)]
 pub struct ActorHolder {
-    _history_actors: MarketHistoryActors,
-    _order_actors: MarketOrderActors,
+    region_manager: Addr<RegionManager>,
     _history_scheduler: Addr<UpdateScheduler>,
     _order_scheduler: Addr<UpdateScheduler>,
 }
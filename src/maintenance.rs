@@ -0,0 +1,134 @@
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use std::{fmt::Display, path::Path};
+
+#[derive(Debug)]
+pub enum MaintenanceError {
+    BackupExists(String),
+    Sql(sqlx::Error),
+}
+
+impl Display for MaintenanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaintenanceError::BackupExists(path) => write!(f, "{} already exists", path),
+            MaintenanceError::Sql(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ForeignKeyViolations {
+    pub table: String,
+    pub parent: String,
+    pub count: i64,
+}
+
+#[derive(Debug)]
+pub struct OrphanedRows {
+    pub table: &'static str,
+    pub column: &'static str,
+    pub count: i64,
+    pub sample_ids: Vec<i64>,
+}
+
+#[derive(Debug)]
+pub struct MaintenanceReport {
+    pub integrity: Vec<String>,
+    pub foreign_keys: Vec<ForeignKeyViolations>,
+    pub orphans: Vec<OrphanedRows>,
+}
+
+impl MaintenanceReport {
+    pub fn is_healthy(&self) -> bool {
+        self.integrity.iter().all(|line| line == "ok")
+            && self.foreign_keys.is_empty()
+            && self.orphans.iter().all(|orphan| orphan.count == 0)
+    }
+}
+
+/// Orphan checks as (table, column, parent table). Rows are orphaned when the
+/// value in `column` no longer exists as an id in the parent SDE table.
+const ORPHAN_CHECKS: [(&str, &str, &str); 4] = [
+    ("market_orders", "item_id", "eve_items"),
+    ("market_orders", "system_id", "eve_system"),
+    ("market_history", "item_id", "eve_items"),
+    ("market_history", "region_id", "eve_region"),
+];
+
+/// Writes a consistent copy of the database to `path` with `VACUUM INTO`.
+/// The pool runs the database in WAL mode, so the copy reads a snapshot and
+/// collectors can keep committing while it is written.
+pub async fn backup(pool: &SqlitePool, path: &Path) -> Result<(), MaintenanceError> {
+    if path.exists() {
+        return Err(MaintenanceError::BackupExists(
+            path.to_string_lossy().to_string(),
+        ));
+    }
+
+    let path = path.to_string_lossy().to_string();
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(path)
+        .execute(pool)
+        .await
+        .map_err(MaintenanceError::Sql)?;
+
+    Ok(())
+}
+
+pub async fn check(pool: &SqlitePool) -> Result<MaintenanceReport, MaintenanceError> {
+    let integrity = sqlx::query("PRAGMA integrity_check")
+        .map(|row: SqliteRow| row.get::<String, _>(0))
+        .fetch_all(pool)
+        .await
+        .map_err(MaintenanceError::Sql)?;
+
+    let foreign_keys = sqlx::query(
+        r#"SELECT "table", parent, COUNT(1) FROM pragma_foreign_key_check GROUP BY "table", parent"#,
+    )
+    .map(|row: SqliteRow| ForeignKeyViolations {
+        table: row.get(0),
+        parent: row.get(1),
+        count: row.get(2),
+    })
+        .fetch_all(pool)
+        .await
+        .map_err(MaintenanceError::Sql)?;
+
+    let mut orphans = Vec::new();
+
+    for (table, column, parent) in ORPHAN_CHECKS {
+        let orphaned = format!(
+            "FROM {table} WHERE {column} NOT IN (SELECT id FROM {parent})",
+            table = table,
+            column = column,
+            parent = parent
+        );
+
+        let count = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(1) {}", orphaned))
+            .fetch_one(pool)
+            .await
+            .map_err(MaintenanceError::Sql)?;
+
+        let sample_ids = sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT DISTINCT {} {} LIMIT 20",
+            column, orphaned
+        ))
+        .fetch_all(pool)
+        .await
+        .map_err(MaintenanceError::Sql)?;
+
+        orphans.push(OrphanedRows {
+            table,
+            column,
+            count,
+            sample_ids,
+        });
+    }
+
+    Ok(MaintenanceReport {
+        integrity,
+        foreign_keys,
+        orphans,
+    })
+}
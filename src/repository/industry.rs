@@ -0,0 +1,262 @@
+use crate::ids::TypeId;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndustryActivity {
+    Manufacturing,
+    Copying,
+    Invention,
+    ResearchMaterial,
+    ResearchTime,
+    Reaction,
+}
+
+impl IndustryActivity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IndustryActivity::Manufacturing => "manufacturing",
+            IndustryActivity::Copying => "copying",
+            IndustryActivity::Invention => "invention",
+            IndustryActivity::ResearchMaterial => "research_material",
+            IndustryActivity::ResearchTime => "research_time",
+            IndustryActivity::Reaction => "reaction",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Blueprint {
+    pub id: TypeId,
+    pub name: Option<String>,
+    pub max_production_limit: i64,
+    pub activities: Vec<BlueprintActivity>,
+}
+
+impl Blueprint {
+    pub fn activity(&self, activity: IndustryActivity) -> Option<&BlueprintActivity> {
+        self.activities
+            .iter()
+            .find(|a| a.activity == activity.as_str())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlueprintActivity {
+    pub activity: String,
+    /// Time for one run in seconds.
+    pub time: i64,
+    pub materials: Vec<IndustryMaterial>,
+    pub products: Vec<IndustryProduct>,
+    pub skills: Vec<IndustrySkill>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndustryMaterial {
+    pub type_id: TypeId,
+    pub name: Option<String>,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndustryProduct {
+    pub type_id: TypeId,
+    pub name: Option<String>,
+    pub quantity: i64,
+    pub probability: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndustrySkill {
+    pub type_id: TypeId,
+    pub name: Option<String>,
+    pub level: i64,
+}
+
+/// What one portion of a type reprocesses into, before efficiency.
+#[derive(Debug)]
+pub struct Reprocessing {
+    pub name: String,
+    pub portion_size: i64,
+    pub materials: Vec<IndustryMaterial>,
+}
+
+#[derive(Debug)]
+pub struct IndustryRepository(Arc<Mutex<SqlitePool>>);
+
+impl Clone for IndustryRepository {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl IndustryRepository {
+    pub fn new(pool: Arc<Mutex<SqlitePool>>) -> Self {
+        Self(pool)
+    }
+
+    /// The blueprint that produces `product_id` with `activity`, e.g. the
+    /// blueprint to manufacture an item.
+    pub async fn product_blueprint(
+        &self,
+        product_id: TypeId,
+        activity: IndustryActivity,
+    ) -> Result<Option<TypeId>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let activity = activity.as_str();
+
+        let blueprint_id = sqlx::query!(
+            r#"SELECT blueprint_id as "blueprint_id: TypeId" FROM eve_blueprint_product WHERE type_id = ? AND activity = ? ORDER BY blueprint_id LIMIT 1"#,
+            product_id,
+            activity
+        )
+        .fetch_optional(connection.as_mut())
+        .await?
+        .map(|row| row.blueprint_id);
+
+        Ok(blueprint_id)
+    }
+
+    pub async fn blueprint(&self, blueprint_id: TypeId) -> Result<Option<Blueprint>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let Some(blueprint) = sqlx::query!(
+            r#"SELECT b.max_production_limit, i.name as "name?" FROM eve_blueprint b LEFT JOIN eve_items i ON i.id = b.id WHERE b.id = ?"#,
+            blueprint_id
+        )
+        .fetch_optional(connection.as_mut())
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut activities = sqlx::query!(
+            "SELECT activity, time FROM eve_blueprint_activity WHERE blueprint_id = ? ORDER BY activity",
+            blueprint_id
+        )
+        .fetch_all(connection.as_mut())
+        .await?
+        .into_iter()
+        .map(|row| BlueprintActivity {
+            activity: row.activity,
+            time: row.time,
+            materials: Vec::new(),
+            products: Vec::new(),
+            skills: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+        let materials = sqlx::query!(
+            r#"SELECT m.activity, m.type_id as "type_id: TypeId", i.name as "name?", m.quantity
+            FROM eve_blueprint_material m LEFT JOIN eve_items i ON i.id = m.type_id
+            WHERE m.blueprint_id = ? ORDER BY m.type_id"#,
+            blueprint_id
+        )
+        .fetch_all(connection.as_mut())
+        .await?;
+
+        for row in materials {
+            if let Some(activity) = activities.iter_mut().find(|a| a.activity == row.activity) {
+                activity.materials.push(IndustryMaterial {
+                    type_id: row.type_id,
+                    name: row.name,
+                    quantity: row.quantity,
+                });
+            }
+        }
+
+        let products = sqlx::query!(
+            r#"SELECT p.activity, p.type_id as "type_id: TypeId", i.name as "name?", p.quantity, p.probability
+            FROM eve_blueprint_product p LEFT JOIN eve_items i ON i.id = p.type_id
+            WHERE p.blueprint_id = ? ORDER BY p.type_id"#,
+            blueprint_id
+        )
+        .fetch_all(connection.as_mut())
+        .await?;
+
+        for row in products {
+            if let Some(activity) = activities.iter_mut().find(|a| a.activity == row.activity) {
+                activity.products.push(IndustryProduct {
+                    type_id: row.type_id,
+                    name: row.name,
+                    quantity: row.quantity,
+                    probability: row.probability,
+                });
+            }
+        }
+
+        let skills = sqlx::query!(
+            r#"SELECT s.activity, s.skill_id as "skill_id: TypeId", i.name as "name?", s.level
+            FROM eve_blueprint_skill s LEFT JOIN eve_items i ON i.id = s.skill_id
+            WHERE s.blueprint_id = ? ORDER BY s.skill_id"#,
+            blueprint_id
+        )
+        .fetch_all(connection.as_mut())
+        .await?;
+
+        for row in skills {
+            if let Some(activity) = activities.iter_mut().find(|a| a.activity == row.activity) {
+                activity.skills.push(IndustrySkill {
+                    type_id: row.skill_id,
+                    name: row.name,
+                    level: row.level,
+                });
+            }
+        }
+
+        Ok(Some(Blueprint {
+            id: blueprint_id,
+            name: blueprint.name,
+            max_production_limit: blueprint.max_production_limit,
+            activities,
+        }))
+    }
+
+    /// Reprocessing materials of every type that can be reprocessed, or only of
+    /// `type_id`.
+    pub async fn reprocessing(
+        &self,
+        type_id: Option<TypeId>,
+    ) -> Result<HashMap<TypeId, Reprocessing>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"SELECT tm.type_id as "type_id: TypeId", i.name, i.portion_size, tm.material_type_id as "material_type_id: TypeId", m.name as "material_name?", tm.quantity
+            FROM type_materials tm
+            JOIN eve_items i ON i.id = tm.type_id
+            LEFT JOIN eve_items m ON m.id = tm.material_type_id
+            WHERE (?1 IS NULL OR tm.type_id = ?1)
+            ORDER BY tm.type_id, tm.material_type_id"#,
+            type_id
+        )
+        .fetch_all(connection.as_mut())
+        .await?;
+
+        let mut reprocessing = HashMap::<TypeId, Reprocessing>::new();
+
+        for row in rows {
+            reprocessing
+                .entry(row.type_id)
+                .or_insert_with(|| Reprocessing {
+                    name: row.name,
+                    portion_size: row.portion_size,
+                    materials: Vec::new(),
+                })
+                .materials
+                .push(IndustryMaterial {
+                    type_id: row.material_type_id,
+                    name: row.material_name,
+                    quantity: row.quantity,
+                });
+        }
+
+        Ok(reprocessing)
+    }
+}
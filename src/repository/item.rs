@@ -1,8 +1,88 @@
+use crate::ids::TypeId;
+use serde::Serialize;
 use std::{collections::HashSet, sync::Arc};
 
 use sqlx::SqlitePool;
 use tokio::sync::Mutex;
 
+#[derive(Debug, Serialize)]
+pub struct Item {
+    pub id: TypeId,
+    pub name: String,
+    pub published: bool,
+    pub group_id: i64,
+    pub market_group_id: Option<i64>,
+    /// Assembled volume in m3.
+    pub volume: Option<f64>,
+    /// Volume in m3 when packaged, the size that counts for hauling.
+    pub packaged_volume: Option<f64>,
+    /// Number of units produced or reprocessed as one batch.
+    pub portion_size: i64,
+    pub base_price: Option<f64>,
+    /// Mass in kg.
+    pub mass: Option<f64>,
+    /// Tech I, Tech II, Faction, Officer, ...
+    pub meta_group_id: Option<i64>,
+    pub meta_level: Option<i64>,
+    pub tech_level: Option<i64>,
+}
+
+/// Narrows a list of items down, filters that are not set match every item.
+#[derive(Debug, Default)]
+pub struct ItemFilter {
+    /// Items in this market group or any group below it.
+    pub market_group: Option<i64>,
+    pub category: Option<i64>,
+    /// Items without a meta group count as Tech I (meta group 1).
+    pub meta_group: Option<i64>,
+    pub tech_level: Option<i64>,
+    pub min_meta_level: Option<i64>,
+    pub max_meta_level: Option<i64>,
+}
+
+impl ItemFilter {
+    pub fn is_empty(&self) -> bool {
+        self.market_group.is_none()
+            && self.category.is_none()
+            && self.meta_group.is_none()
+            && self.tech_level.is_none()
+            && self.min_meta_level.is_none()
+            && self.max_meta_level.is_none()
+    }
+}
+
+/// A node of the in-game market browser.
+#[derive(Debug, Serialize)]
+pub struct MarketGroup {
+    pub id: i64,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    pub description: Option<String>,
+    /// Whether items are listed directly in this group, otherwise it only
+    /// holds other groups.
+    pub has_types: bool,
+    pub icon_id: Option<i64>,
+    /// Published items directly in this group.
+    pub item_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MetaGroup {
+    pub id: i64,
+    pub name: String,
+    /// Published items on the market in this meta group.
+    pub item_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Category {
+    pub id: i64,
+    pub name: String,
+    pub published: bool,
+    /// Published items on the market in this category.
+    pub item_count: i64,
+}
+
 #[derive(Debug)]
 pub struct ItemRepository(Arc<Mutex<SqlitePool>>);
 
@@ -17,7 +97,7 @@ impl ItemRepository {
         Self(pool)
     }
 
-    pub async fn tradeable_item_ids(&mut self) -> Result<HashSet<usize>, sqlx::Error> {
+    pub async fn tradeable_item_ids(&mut self) -> Result<HashSet<TypeId>, sqlx::Error> {
         let lock = self.0.lock().await;
         let mut connection = lock.acquire().await?;
 
@@ -27,7 +107,7 @@ impl ItemRepository {
         .fetch_all(connection.as_mut())
         .await?
         .into_iter()
-        .map(|x| x.id as usize)
+        .map(|x| TypeId(x.id))
         .collect::<HashSet<_>>();
 
         drop(connection);
@@ -35,4 +115,110 @@ impl ItemRepository {
 
         Ok(all_items)
     }
+
+    pub async fn item(&self, type_id: TypeId) -> Result<Option<Item>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            Item,
+            r#"SELECT id as "id: TypeId", name, published as "published: bool", group_id, market_group_id, volume, packaged_volume, portion_size, base_price, mass,
+                meta_group_id, meta_level, tech_level
+            FROM eve_items WHERE id = ?"#,
+            type_id
+        )
+        .fetch_optional(connection.as_mut())
+        .await
+    }
+
+    /// All market groups, `item_count` only counts items in `category` when
+    /// given.
+    pub async fn market_groups(
+        &self,
+        category: Option<i64>,
+    ) -> Result<Vec<MarketGroup>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            MarketGroup,
+            r#"SELECT mg.id as "id!", mg.name, mg.parent_id, mg.description, mg.has_types as "has_types: bool", mg.icon_id,
+                (SELECT COUNT(*) FROM eve_items i JOIN eve_groups g ON g.id = i.group_id
+                    WHERE i.market_group_id = mg.id AND i.published = 1 AND (?1 IS NULL OR g.category_id = ?1)) as "item_count!: i64"
+            FROM eve_market_groups mg
+            ORDER BY mg.name"#,
+            category
+        )
+        .fetch_all(connection.as_mut())
+        .await
+    }
+
+    pub async fn categories(&self) -> Result<Vec<Category>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            Category,
+            r#"SELECT c.id as "id!", c.name, c.published as "published: bool",
+                (SELECT COUNT(*) FROM eve_items i JOIN eve_groups g ON g.id = i.group_id
+                    WHERE g.category_id = c.id AND i.published = 1 AND i.market_group_id IS NOT NULL) as "item_count!: i64"
+            FROM eve_categories c
+            ORDER BY c.name"#
+        )
+        .fetch_all(connection.as_mut())
+        .await
+    }
+
+    pub async fn meta_groups(&self) -> Result<Vec<MetaGroup>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            MetaGroup,
+            r#"SELECT mg.id as "id!", mg.name,
+                (SELECT COUNT(*) FROM eve_items i
+                    WHERE COALESCE(i.meta_group_id, 1) = mg.id AND i.published = 1 AND i.market_group_id IS NOT NULL) as "item_count!: i64"
+            FROM eve_meta_groups mg
+            ORDER BY mg.id"#
+        )
+        .fetch_all(connection.as_mut())
+        .await
+    }
+
+    pub async fn filtered_item_ids(
+        &self,
+        filter: &ItemFilter,
+    ) -> Result<HashSet<TypeId>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let items = sqlx::query!(
+            r#"WITH RECURSIVE tree(id) AS (
+                SELECT id FROM eve_market_groups WHERE id = ?1
+                UNION ALL
+                SELECT mg.id FROM eve_market_groups mg JOIN tree ON mg.parent_id = tree.id
+            )
+            SELECT i.id as "id: TypeId" FROM eve_items i
+            JOIN eve_groups g ON g.id = i.group_id
+            WHERE (?1 IS NULL OR i.market_group_id IN (SELECT id FROM tree))
+                AND (?2 IS NULL OR g.category_id = ?2)
+                AND (?3 IS NULL OR COALESCE(i.meta_group_id, 1) = ?3)
+                AND (?4 IS NULL OR i.tech_level = ?4)
+                AND (?5 IS NULL OR i.meta_level >= ?5)
+                AND (?6 IS NULL OR i.meta_level <= ?6)"#,
+            filter.market_group,
+            filter.category,
+            filter.meta_group,
+            filter.tech_level,
+            filter.min_meta_level,
+            filter.max_meta_level
+        )
+        .fetch_all(connection.as_mut())
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect();
+
+        Ok(items)
+    }
 }
@@ -1,5 +1,7 @@
+use crate::ids::{RegionId, TypeId};
 use chrono::{DateTime, NaiveDate, TimeZone, Utc};
 use futures::TryStreamExt;
+use serde::Serialize;
 use sqlx::{sqlite::SqliteRow, Connection, Row, SqlitePool};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::Mutex;
@@ -19,46 +21,155 @@ impl Clone for MarketHistoryRepository {
     }
 }
 
-#[derive(sqlx::FromRow)]
-pub struct MarketHistoryAverage {    
-    pub item_id: i64,
+#[derive(Debug, Clone)]
+pub struct MarketHistoryAverage {
+    pub days: i64,
     pub avg_price: f64,
     pub avg_volume: f64,
     pub avg_low: f64,
     pub avg_high: f64,
+    pub weighted_price: f64,
+    pub median_price: f64,
+    pub stddev_price: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarketHistoryDay {
+    pub date: NaiveDate,
+    pub low_price: f64,
+    pub high_price: f64,
+    pub average_price: f64,
+    pub volume: i64,
+    pub order_count: i64,
+    pub moving_average_7d: f64,
+    pub moving_average_30d: f64,
 }
 
 impl MarketHistoryRepository {
+    /// Per-item statistics over the last `days` days of history in `region_id`,
+    /// for every window in `windows`, in the same order. The weighted price is
+    /// the volume-weighted average price.
+    pub async fn averages(
+        &self,
+        region_id: RegionId,
+        windows: &[usize],
+    ) -> Result<Vec<HashMap<TypeId, MarketHistoryAverage>>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let largest = format!("-{} days", windows.iter().max().copied().unwrap_or(0));
+        let window_days = serde_json::to_string(windows).unwrap_or_else(|_| "[]".to_string());
+
+        // Every day of the largest window is joined to the windows it falls
+        // into, so all windows are aggregated in one pass. The variance is
+        // taken around the mean of the window, the difference of the averages
+        // of the squares loses all precision for large prices.
+        let rows = sqlx::query!(
+            r#"WITH windows AS (
+                SELECT DISTINCT CAST(value AS INTEGER) as window_days FROM json_each(?)
+            ),
+            history AS (
+                SELECT w.window_days, h.item_id, h.average_price, h.low_price, h.high_price, h.volume,
+                    ROW_NUMBER() OVER (PARTITION BY w.window_days, h.item_id ORDER BY h.average_price) as position,
+                    COUNT(1) OVER (PARTITION BY w.window_days, h.item_id) as total,
+                    AVG(h.average_price) OVER (PARTITION BY w.window_days, h.item_id) as mean_price
+                FROM market_history h
+                JOIN windows w ON h.date > date('now', '-' || w.window_days || ' days')
+                WHERE h.region_id = ? AND h.date > date('now', ?)
+            )
+            SELECT window_days as "window_days! : i64", item_id,
+                COUNT(1) as "days! : i64",
+                AVG(average_price) as "avg_price! : f64",
+                AVG(volume) as "avg_volume! : f64",
+                AVG(low_price) as "avg_low! : f64",
+                AVG(high_price) as "avg_high! : f64",
+                COALESCE(SUM(average_price * volume) / NULLIF(SUM(volume), 0), AVG(average_price)) as "weighted_price! : f64",
+                AVG(CASE WHEN position IN ((total + 1) / 2, (total + 2) / 2) THEN average_price END) as "median_price! : f64",
+                AVG((average_price - mean_price) * (average_price - mean_price)) as "variance! : f64"
+            FROM history
+            GROUP BY window_days, item_id"#,
+            window_days,
+            region_id,
+            largest
+        )
+        .fetch_all(connection.as_mut())
+        .await?;
+
+        let mut averages = vec![HashMap::new(); windows.len()];
+        for row in rows {
+            let average = MarketHistoryAverage {
+                days: row.days,
+                avg_price: row.avg_price,
+                avg_volume: row.avg_volume,
+                avg_low: row.avg_low,
+                avg_high: row.avg_high,
+                weighted_price: row.weighted_price,
+                median_price: row.median_price,
+                stddev_price: row.variance.max(0.0).sqrt(),
+            };
+
+            for (window, averages) in windows.iter().zip(averages.iter_mut()) {
+                if *window as i64 == row.window_days {
+                    averages.insert(TypeId(row.item_id), average.clone());
+                }
+            }
+        }
+
+        Ok(averages)
+    }
 
-    pub async fn averages(&self) -> Result<HashMap<usize, MarketHistoryAverage>, sqlx::Error> {
+    /// Daily history of a single item in a region between `from` and `to`
+    /// (inclusive). Moving averages also look at days before `from`, and cover
+    /// calendar days, days without trades are missing from the history.
+    pub async fn item_history(
+        &self,
+        item_id: TypeId,
+        region_id: RegionId,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<MarketHistoryDay>, sqlx::Error> {
         let lock = self.0.lock().await;
         let mut connection = lock.acquire().await?;
 
-        sqlx::query_as!(MarketHistoryAverage, r#"SELECT item_id, AVG(average_price) as avg_price, AVG(volume) as avg_volume, AVG(low_price) as avg_low, AVG(high_price) as avg_high FROM market_history WHERE datetime(date) >= datetime('now', '-31 Days') GROUP BY item_id"#)
-        .map(|row| {
-            (row.item_id as usize, row)
-        })
-        .fetch(connection.as_mut())
-        .try_collect::<HashMap<_,_>>().await
+        sqlx::query_as!(
+            MarketHistoryDay,
+            r#"SELECT date as "date!: NaiveDate", low_price as "low_price!: f64", high_price as "high_price!: f64", average_price as "average_price!: f64", volume as "volume!", order_count as "order_count!", moving_average_7d as "moving_average_7d!: f64", moving_average_30d as "moving_average_30d!: f64"
+            FROM (
+                SELECT date, low_price, high_price, average_price, volume, order_count,
+                    AVG(average_price) OVER (ORDER BY julianday(date) RANGE BETWEEN 6 PRECEDING AND CURRENT ROW) as moving_average_7d,
+                    AVG(average_price) OVER (ORDER BY julianday(date) RANGE BETWEEN 29 PRECEDING AND CURRENT ROW) as moving_average_30d
+                FROM market_history
+                WHERE item_id = ?1 AND region_id = ?2 AND (?4 IS NULL OR date <= ?4)
+            )
+            WHERE (?3 IS NULL OR date >= ?3)
+            ORDER BY date"#,
+            item_id,
+            region_id,
+            from,
+            to
+        )
+        .fetch_all(connection.as_mut())
+        .await
     }
+
     pub async fn latest_histories(
         &mut self,
-        region_id: usize,
-    ) -> Result<HashMap<usize, DateTime<Utc>>, sqlx::Error> {
+        region_id: RegionId,
+    ) -> Result<HashMap<TypeId, DateTime<Utc>>, sqlx::Error> {
         let lock = self.0.lock().await;
         let mut connection = lock.acquire().await?;
 
         log::trace!("Querying latest histories for region: {}", region_id);
 
         let latest_histories = sqlx::query("SELECT item_id, MAX(date) as date from market_history WHERE region_id = ? GROUP BY item_id")
-        .bind(region_id as i64)
+        .bind(region_id)
         .map(|r: SqliteRow| {
-            let item_id: i64 = r.try_get("item_id").unwrap();
+            let item_id: TypeId = r.try_get("item_id").unwrap();
             let date: NaiveDate = r.try_get("date").unwrap();
 
             let date = Utc.from_utc_datetime(&date.and_hms_opt(11, 0, 0).unwrap());
 
-            (item_id as usize, date)
+            (item_id, date)
         })        
         .fetch(connection.as_mut())
         .try_collect::<HashMap<_, _>>()        
@@ -74,20 +185,18 @@ impl MarketHistoryRepository {
 
     pub async fn insert_items(
         &mut self,
-        added: Vec<(usize, crate::esi::models::MarketRegionHistoryItem)>,
-        region_id: usize,
-    ) -> Result<(), sqlx::Error> {
+        added: Vec<(TypeId, crate::esi::models::MarketRegionHistoryItem)>,
+        region_id: RegionId,
+    ) -> Result<usize, sqlx::Error> {
         let lock = self.0.lock().await;
         let mut connection = lock.acquire().await?;
 
         let mut transaction = connection.begin().await?;
+        let mut inserted = 0;
 
         for (item_id, history) in added {
-            let item_id = item_id as i64;
-            let region_id = region_id as i64;
-            
 
-             sqlx::query!("INSERT OR REPLACE INTO market_history (date, item_id, region_id, low_price, high_price, average_price, order_count, volume) VALUES (?, ?, ?, ?, ?, ?, ?, ?)", 
+            let result = sqlx::query!("INSERT OR REPLACE INTO market_history (date, item_id, region_id, low_price, high_price, average_price, order_count, volume) VALUES (?, ?, ?, ?, ?, ?, ?, ?)", 
             history.date, item_id, region_id, history.lowest, history.highest, history.average, history.order_count, history.volume)
             .execute(transaction.as_mut())
             .await
@@ -96,9 +205,47 @@ impl MarketHistoryRepository {
             e
         })?;
 
+            inserted += result.rows_affected() as usize;
+
         }
         transaction.commit().await?;
 
-        Ok(())
+        Ok(inserted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sde::{
+        fixtures::{self, TRITANIUM},
+        import_sde,
+    };
+
+    #[tokio::test]
+    async fn averages_keep_the_deviation_of_large_prices() {
+        let pool = fixtures::memory_pool().await;
+        import_sde(&pool, &fixtures::sde()).await.unwrap();
+        for (days_ago, price) in [(1, 1e9 + 1.0), (2, 1e9 + 2.0), (3, 1e9 + 3.0), (40, 1.0)] {
+            sqlx::query("INSERT INTO market_history (date, item_id, region_id, low_price, high_price, average_price, order_count, volume) VALUES (date('now', ?), ?, 10000002, ?, ?, ?, 1, 1)")
+                .bind(format!("-{} days", days_ago))
+                .bind(TRITANIUM)
+                .bind(price)
+                .bind(price)
+                .bind(price)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let repository = MarketHistoryRepository::new(Arc::new(Mutex::new(pool)));
+        let averages = repository
+            .averages(RegionId(10000002), &[30])
+            .await
+            .unwrap();
+
+        let average = &averages[0][&TRITANIUM];
+        assert_eq!(average.days, 3);
+        assert!((average.stddev_price - (2.0f64 / 3.0).sqrt()).abs() < 1e-6);
     }
 }
@@ -1,13 +1,26 @@
-use crate::esi::models::MarketRegionOrdersItem;
-use chrono::format;
+use super::TrackedStructure;
+use crate::{
+    esi::models::MarketRegionOrdersItem,
+    ids::{OrderId, RegionId, StationId, SystemId, TypeId},
+};
+use chrono::{DateTime, Utc};
 use futures::TryStreamExt;
 use sqlx::SqlitePool;
-use std::{sync::Arc, collections::HashMap, borrow::BorrowMut};
+use std::{sync::Arc, collections::HashMap};
 use tokio::sync::Mutex;
 
 const CHUNK_SIZE: usize = 1000;
+/// The largest range in jumps a buy order can have.
+const MAX_ORDER_RANGE: i64 = 40;
 
 
+/// The order with the best price of an item.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BestOrder {
+    pub order_id: OrderId,
+    pub price: f64,
+}
+
 #[derive(Debug)]
 pub struct MarketOrderRepository(Arc<Mutex<SqlitePool>>);
 
@@ -24,11 +37,15 @@ impl Clone for MarketOrderRepository {
 }
 
 impl MarketOrderRepository {
+    /// Inserts the active orders of the region and deactivates the ones that
+    /// are gone. With `types` only orders of those types are deactivated, for
+    /// a snapshot of part of the market.
     pub async fn insert_active_items(
         &mut self,
         items: Vec<MarketRegionOrdersItem>,
-        region_id: usize,
-    ) -> Result<(), sqlx::Error> {
+        region_id: RegionId,
+        types: Option<&[TypeId]>,
+    ) -> Result<(usize, usize), sqlx::Error> {
         let lock = self.0.lock().await;
         let active_order_ids: String = items
             .iter()
@@ -36,51 +53,92 @@ impl MarketOrderRepository {
             .collect::<Vec<_>>()
             .join(",");
 
-        for batch in items.chunks(CHUNK_SIZE) {
-            let mut transaction = lock.begin().await?;
-            for order in batch {
-                let expiry = order.issued + chrono::Duration::days(order.duration as i64);
-                let order_id = order.order_id as i64;
-                let type_id = order.type_id as i64;
-                let system_id = order.system_id as i64;
-                let volume_remain = order.volume_remain as i64;
-                let volume_total = order.volume_total as i64;
-                let price = order.price as f32;
-
-                sqlx::query!("INSERT OR IGNORE INTO market_orders (buy_order, issued, expiry, order_id, item_id, system_id, volume_remain, volume_total, price) VALUES (?,?,?,?,?,?,?,?, ?)", 
-                    order.is_buy_order, order.issued, expiry, order_id, type_id, system_id, volume_remain, volume_total, price).execute(transaction.as_mut()).await
-                    .map_err(|e| {
-                        log::error!("Failed to insert order: {:?}. tid: {}, sid: {}", e.to_string(), type_id, system_id);
-                        e
-                    })?;
-            }
-            transaction.commit().await?;
-        }
+        let inserted = insert_orders(&lock, &items).await?;
 
         let mut connection = lock.acquire().await?;
 
-        sqlx::query(&format!(
-            "UPDATE market_orders SET active = 0 WHERE active = 1 AND order_id NOT IN({}) AND system_id IN (SELECT id FROM eve_system WHERE region_id = {})",
-            active_order_ids, region_id
+        let type_filter = match types {
+            Some(types) => format!(
+                " AND item_id IN ({})",
+                types.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(",")
+            ),
+            None => String::new(),
+        };
+
+        // Tracked structures have their own snapshots, the region only lists
+        // the orders of some of them.
+        let deactivated = sqlx::query(&format!(
+            "UPDATE market_orders SET active = 0 WHERE active = 1 AND order_id NOT IN({}) AND system_id IN (SELECT id FROM eve_system WHERE region_id = {}) AND COALESCE(location_id, 0) NOT IN (SELECT structure_id FROM tracked_structures WHERE paused = 0){}",
+            active_order_ids, region_id, type_filter
         ))        
         .execute(connection.as_mut())
-        .await?;
+        .await?
+        .rows_affected() as usize;
 
         drop(connection);
 
-        Ok(())
+        Ok((inserted, deactivated))
+    }
+
+    /// Tracked structures of the region that are not paused, their orders are
+    /// collected with the region.
+    pub async fn tracked_structures(
+        &self,
+        region_id: RegionId,
+    ) -> Result<Vec<TrackedStructure>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            TrackedStructure,
+            r#"SELECT t.structure_id as "structure_id: StationId", t.name, t.system_id as "system_id: SystemId", s.region_id as "region_id: RegionId", t.paused as "paused: bool", t.added as "added: DateTime<Utc>"
+            FROM tracked_structures t
+            JOIN eve_system s ON s.id = t.system_id
+            WHERE s.region_id = ? AND t.paused = 0
+            ORDER BY t.structure_id"#,
+            region_id
+        )
+        .fetch_all(connection.as_mut())
+        .await
+    }
+
+    /// Inserts the active orders of a player structure, already placed in its
+    /// system, and deactivates the ones that are gone.
+    pub async fn insert_structure_items(
+        &mut self,
+        items: Vec<MarketRegionOrdersItem>,
+        structure_id: StationId,
+    ) -> Result<(usize, usize), sqlx::Error> {
+        let lock = self.0.lock().await;
+        let active_order_ids: String = items
+            .iter()
+            .map(|o| o.order_id.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let inserted = insert_orders(&lock, &items).await?;
+
+        let mut connection = lock.acquire().await?;
+        let deactivated = sqlx::query(&format!(
+            "UPDATE market_orders SET active = 0 WHERE active = 1 AND order_id NOT IN({}) AND location_id = {}",
+            active_order_ids, structure_id
+        ))
+        .execute(connection.as_mut())
+        .await?
+        .rows_affected() as usize;
+
+        Ok((inserted, deactivated))
     }
 
-    pub async fn region_sell_prices(&self, region_id: usize) -> Result<HashMap<usize, f64>, sqlx::Error> {
+    pub async fn region_sell_prices(&self, region_id: RegionId) -> Result<HashMap<TypeId, f64>, sqlx::Error> {
         let lock = self.0.lock().await;
         let mut connection = lock.acquire().await?;
-        let region_id = region_id as i64;
         sqlx::query!(
             r#"SELECT item_id, MIN(price) as "sell_price! : f64" FROM market_orders WHERE system_id IN (select id from eve_system where region_id = ?) AND buy_order = 0 AND active = 1 GROUP BY item_id HAVING COUNT(price) > 0"#,
             region_id
         )
         .map(|row| {
-            let item_id = row.item_id as usize;
+            let item_id = TypeId(row.item_id);
             let price: f64 = row.sell_price;
     
             (item_id, price)
@@ -90,16 +148,18 @@ impl MarketOrderRepository {
     }
 
     
-    pub async fn region_buy_prices(&self, region_id: usize) -> Result<HashMap<usize, f64>, sqlx::Error> {
+    /// Highest buy price per item in the region, ignoring orders that require
+    /// selling more than `max_min_volume` units at once.
+    pub async fn region_buy_prices(&self, region_id: RegionId, max_min_volume: usize) -> Result<HashMap<TypeId, f64>, sqlx::Error> {
         let lock = self.0.lock().await;
         let mut connection = lock.acquire().await?;
-        let region_id = region_id as i64;
+        let max_min_volume = max_min_volume as i64;
         sqlx::query!(
-            r#"SELECT item_id, MAX(price) as "buy_price! : f64" FROM market_orders WHERE system_id IN (select id from eve_system where region_id = ?) AND buy_order = 1 AND active = 1 GROUP BY item_id HAVING COUNT(price) > 0"#,
-            region_id
+            r#"SELECT item_id, MAX(price) as "buy_price! : f64" FROM market_orders WHERE system_id IN (select id from eve_system where region_id = ?) AND buy_order = 1 AND active = 1 AND min_volume <= ? GROUP BY item_id HAVING COUNT(price) > 0"#,
+            region_id, max_min_volume
         )
         .map(|row| {
-            let item_id = row.item_id as usize;
+            let item_id = TypeId(row.item_id);
             let price: f64 = row.buy_price;
     
             (item_id, price)
@@ -108,10 +168,92 @@ impl MarketOrderRepository {
         .try_collect::<HashMap<_,_>>().await
     }
 
-    pub async fn region_buy_competition(&self, region_id: usize, last_hours: usize) -> Result<HashMap<usize, usize>, sqlx::Error> {
+    pub async fn station_sell_prices(&self, location_id: StationId) -> Result<HashMap<TypeId, f64>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+        sqlx::query!(
+            r#"SELECT item_id as "item_id!", MIN(price) as "sell_price! : f64" FROM market_orders WHERE location_id = ? AND buy_order = 0 AND active = 1 GROUP BY item_id HAVING COUNT(price) > 0"#,
+            location_id
+        )
+        .map(|row| {
+            let item_id = TypeId(row.item_id);
+            let price: f64 = row.sell_price;
+
+            (item_id, price)
+        })
+        .fetch(connection.as_mut())
+        .try_collect::<HashMap<_,_>>().await
+    }
+
+    /// Highest buy price per item among the buy orders whose range reaches the
+    /// station `location_id` in `system_id`. Jump ranges are resolved over the
+    /// stargate graph, only as far as the largest jump range of the buy orders
+    /// in the region, region ranges against the region of the station.
+    pub async fn station_buy_prices(&self, system_id: SystemId, location_id: StationId, max_min_volume: usize) -> Result<HashMap<TypeId, f64>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+        let max_min_volume = max_min_volume as i64;
+        sqlx::query!(
+            r#"WITH RECURSIVE max_range(jumps) AS (
+                SELECT MIN(COALESCE(MAX(CAST(range AS INTEGER)), 0), ?4) FROM market_orders
+                WHERE buy_order = 1 AND active = 1 AND range NOT IN ('station', 'solarsystem', 'region')
+            ),
+            jumps(system_id, distance) AS (
+                SELECT ?1, 0
+                UNION
+                SELECT g.target_system_id, j.distance + 1 FROM eve_stargates g JOIN jumps j ON g.source_system_id = j.system_id WHERE j.distance < (SELECT jumps FROM max_range)
+            ),
+            nearest AS (SELECT system_id, MIN(distance) as distance FROM jumps GROUP BY system_id)
+            SELECT o.item_id as "item_id!", MAX(o.price) as "buy_price! : f64" FROM market_orders o
+            LEFT JOIN nearest n ON n.system_id = o.system_id
+            WHERE o.buy_order = 1 AND o.active = 1 AND o.min_volume <= ?3
+            AND (
+                (o.range = 'station' AND o.location_id = ?2)
+                OR (o.range = 'solarsystem' AND n.distance = 0)
+                OR (o.range = 'region' AND o.system_id IN (SELECT id FROM eve_system WHERE region_id = (SELECT region_id FROM eve_system WHERE id = ?1)))
+                OR (o.range NOT IN ('station', 'solarsystem', 'region') AND n.distance <= CAST(o.range AS INTEGER))
+            )
+            GROUP BY o.item_id"#,
+            system_id, location_id, max_min_volume, MAX_ORDER_RANGE
+        )
+        .map(|row| {
+            let item_id = TypeId(row.item_id);
+            let price: f64 = row.buy_price;
+
+            (item_id, price)
+        })
+        .fetch(connection.as_mut())
+        .try_collect::<HashMap<_,_>>().await
+    }
+
+    /// Active order with the lowest sell price, or the highest buy price, per
+    /// item in the region.
+    pub async fn region_best_orders(&self, region_id: RegionId, buy_order: bool) -> Result<HashMap<TypeId, BestOrder>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+        // SQLite takes the bare order_id from the row with the MIN or MAX price.
+        if buy_order {
+            sqlx::query!(
+                r#"SELECT item_id, order_id as "order_id!: OrderId", MAX(price) as "price! : f64" FROM market_orders WHERE system_id IN (select id from eve_system where region_id = ?) AND buy_order = 1 AND active = 1 GROUP BY item_id"#,
+                region_id
+            )
+            .map(|row| (TypeId(row.item_id), BestOrder { order_id: row.order_id, price: row.price }))
+            .fetch(connection.as_mut())
+            .try_collect::<HashMap<_,_>>().await
+        } else {
+            sqlx::query!(
+                r#"SELECT item_id, order_id as "order_id!: OrderId", MIN(price) as "price! : f64" FROM market_orders WHERE system_id IN (select id from eve_system where region_id = ?) AND buy_order = 0 AND active = 1 GROUP BY item_id"#,
+                region_id
+            )
+            .map(|row| (TypeId(row.item_id), BestOrder { order_id: row.order_id, price: row.price }))
+            .fetch(connection.as_mut())
+            .try_collect::<HashMap<_,_>>().await
+        }
+    }
+
+    pub async fn region_buy_competition(&self, region_id: RegionId, last_hours: usize) -> Result<HashMap<TypeId, usize>, sqlx::Error> {
         let lock = self.0.lock().await;
         let mut connection = lock.acquire().await?;
-        let region_id = region_id as i64;
         let past_hours = format!("-{} hours", last_hours);
         sqlx::query!(
             r#"SELECT item_id, count(1) as "competition! : i64" FROM market_orders 
@@ -120,7 +262,7 @@ impl MarketOrderRepository {
             region_id, past_hours
         )
         .map(|row| {
-            let item_id = row.item_id as usize;
+            let item_id = TypeId(row.item_id);
             let price = row.competition as usize;
     
             (item_id, price)
@@ -129,10 +271,9 @@ impl MarketOrderRepository {
         .try_collect::<HashMap<_,_>>().await
     }
 
-    pub async fn region_sell_competition(&self, region_id: usize, last_hours: usize) -> Result<HashMap<usize, usize>, sqlx::Error> {
+    pub async fn region_sell_competition(&self, region_id: RegionId, last_hours: usize) -> Result<HashMap<TypeId, usize>, sqlx::Error> {
         let lock = self.0.lock().await;
         let mut connection = lock.acquire().await?;
-        let region_id = region_id as i64;
         let past_hours = format!("-{} hours", last_hours);
         sqlx::query!(
             r#"SELECT item_id, count(1) as "competition! : i64" FROM market_orders 
@@ -141,7 +282,7 @@ impl MarketOrderRepository {
             region_id, past_hours
         )
         .map(|row| {
-            let item_id = row.item_id as usize;
+            let item_id = TypeId(row.item_id);
             let price = row.competition as usize;
     
             (item_id, price)
@@ -149,50 +290,163 @@ impl MarketOrderRepository {
         .fetch(connection.as_mut())
         .try_collect::<HashMap<_,_>>().await
     }
+}
 
-    pub async fn region_confirmed_buy_volume(&self, region_id: usize, last_hours: usize) -> Result<HashMap<usize, Vec<(f64, usize)>>, sqlx::Error> {
-        let lock = self.0.lock().await;
-        let mut connection = lock.acquire().await?;        
-        let region_id = region_id as i64;
-        let last_hours = format!("-{} hours", last_hours);
-        let items = sqlx::query!(r#"SELECT order_id, item_id, price, MAX(volume_remain) - MIN(volume_remain) as "fulfilled! : i64" FROM market_orders WHERE system_id IN (select id from eve_system where region_id = ?) AND buy_order=1 AND datetime(created) > datetime('now', ?) GROUP BY order_id, price"#, region_id, last_hours)
-        .map(|row| {
-            let item_id = row.item_id as usize;
-            let price = row.price;
-            let volume = row.fulfilled as usize;
-            
-            (item_id, price, volume)
-        })
-        .fetch_all(connection.as_mut()).await?;
+/// Inserts orders in chunks, an order that did not change since the last
+/// snapshot is skipped. Returns the number of inserted rows.
+async fn insert_orders(
+    pool: &SqlitePool,
+    items: &[MarketRegionOrdersItem],
+) -> Result<usize, sqlx::Error> {
+    let mut inserted = 0;
 
-        let mut map: HashMap<usize, Vec<(f64, usize)>> = HashMap::new();
-        for (item_id, price, volume) in items {
-            map.entry(item_id).or_default().push((price, volume));
-        }
+    for batch in items.chunks(CHUNK_SIZE) {
+        let mut transaction = pool.begin().await?;
+        for order in batch {
+            let expiry = order.issued + chrono::Duration::days(order.duration as i64);
+            let order_id = order.order_id;
+            let type_id = order.type_id;
+            let system_id = order.system_id;
+            let volume_remain = order.volume_remain as i64;
+            let volume_total = order.volume_total as i64;
+            let price = order.price as f32;
+            let location_id = order.location_id;
+            let range = order.range.as_str();
+            let min_volume = order.min_volume as i64;
+            let duration = order.duration as i64;
 
-        Ok(map)
+            let result = sqlx::query!("INSERT OR IGNORE INTO market_orders (buy_order, issued, expiry, order_id, item_id, system_id, volume_remain, volume_total, price, location_id, range, min_volume, duration) VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?)", 
+                order.is_buy_order, order.issued, expiry, order_id, type_id, system_id, volume_remain, volume_total, price, location_id, range, min_volume, duration).execute(transaction.as_mut()).await
+                .inspect_err(|e| {
+                    log::error!("Failed to insert order: {:?}. tid: {}, sid: {}", e.to_string(), type_id, system_id);
+                })?;
+
+            inserted += result.rows_affected() as usize;
+        }
+        transaction.commit().await?;
     }
 
-    pub async fn region_confirmed_sell_volume(&self, region_id: usize, last_hours: usize) -> Result<HashMap<usize, Vec<(f64, usize)>>, sqlx::Error> {
-        let lock = self.0.lock().await;
-        let mut connection = lock.acquire().await?;        
-        let region_id = region_id as i64;
-        let last_hours = format!("-{} hours", last_hours);
-        let items = sqlx::query!(r#"SELECT order_id, item_id, price, MAX(volume_remain) - MIN(volume_remain) as "fulfilled! : i64" FROM market_orders WHERE system_id IN (select id from eve_system where region_id = ?) AND buy_order=0 AND datetime(created) > datetime('now', ?) GROUP BY order_id, price"#, region_id, last_hours)
-        .map(|row| {
-            let item_id = row.item_id as usize;
-            let price = row.price;
-            let volume = row.fulfilled as usize;
-            
-            (item_id, price, volume)
-        })
-        .fetch_all(connection.as_mut()).await?;
+    Ok(inserted)
+}
 
-        let mut map: HashMap<usize, Vec<(f64, usize)>> = HashMap::new();
-        for (item_id, price, volume) in items {
-            map.entry(item_id).or_default().push((price, volume));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::esi::models::MarketRegionOrderRange;
+    use crate::ids::ConstellationId;
+    use crate::sde::{
+        fixtures::{self, JITA, PERIMETER, TRITANIUM},
+        import_sde, SdeConstellation,
+    };
+
+    const BORDER: SystemId = SystemId(30002791);
+    const JITA_STATION: StationId = StationId(60003760);
+    const JITA_STRUCTURE: StationId = StationId(1035466617946);
+
+    /// A sell order of Tritanium in Jita, as ESI lists it.
+    fn sell_order(order_id: i64, location_id: StationId) -> MarketRegionOrdersItem {
+        MarketRegionOrdersItem {
+            duration: 90,
+            is_buy_order: false,
+            issued: Utc::now(),
+            location_id,
+            min_volume: 1,
+            order_id: OrderId(order_id),
+            price: 5.0,
+            range: MarketRegionOrderRange::Region,
+            system_id: JITA,
+            type_id: TRITANIUM,
+            volume_remain: 10,
+            volume_total: 10,
         }
+    }
+
+    async fn active_order_ids(pool: &SqlitePool) -> Vec<i64> {
+        sqlx::query_scalar("SELECT order_id FROM market_orders WHERE active = 1 ORDER BY order_id")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    /// A buy order of Tritanium, at `JITA_STATION` for the ones in Jita.
+    async fn insert_buy_order(pool: &SqlitePool, order_id: i64, system_id: SystemId, range: &str, price: f64) {
+        sqlx::query("INSERT INTO market_orders (buy_order, issued, expiry, order_id, item_id, system_id, volume_remain, volume_total, price, location_id, range) VALUES (1, datetime('now'), datetime('now', '+90 days'), ?, ?, ?, 10, 10, ?, ?, ?)")
+            .bind(order_id)
+            .bind(TRITANIUM)
+            .bind(system_id)
+            .bind(price)
+            .bind(if system_id == JITA { JITA_STATION.0 } else { 60000000 + order_id })
+            .bind(range)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn station_buy_prices_reach_orders_in_other_regions() {
+        let pool = fixtures::memory_pool().await;
+        let mut sde = fixtures::sde();
+        // Jita - Perimeter - Border, with Border in another region.
+        sde.regions.insert(RegionId(10000033), "The Citadel".to_string());
+        sde.constellations.insert(
+            ConstellationId(20000387),
+            SdeConstellation {
+                id: ConstellationId(20000387),
+                name: "Ahtila".to_string(),
+                region_id: RegionId(10000033),
+            },
+        );
+        sde.systems.get_mut(&PERIMETER).unwrap().stargates.push((50001250, 50001251));
+        let mut border = fixtures::system(BORDER, "Border", vec![(50001251, 50001250)]);
+        border.region_id = RegionId(10000033);
+        border.constellation_id = ConstellationId(20000387);
+        sde.systems.insert(BORDER, border);
+        import_sde(&pool, &sde).await.unwrap();
+
+        insert_buy_order(&pool, 1, JITA, "station", 4.0).await;
+        // Two jumps away, only the order with a range of two reaches Jita.
+        insert_buy_order(&pool, 2, BORDER, "2", 5.0).await;
+        insert_buy_order(&pool, 3, BORDER, "1", 6.0).await;
+
+        let repository = MarketOrderRepository::new(Arc::new(Mutex::new(pool)));
+        let prices = repository.station_buy_prices(JITA, JITA_STATION, 1).await.unwrap();
+
+        assert_eq!(prices.get(&TRITANIUM), Some(&5.0));
+    }
+
+    #[tokio::test]
+    async fn structures_and_regions_replace_their_own_orders() {
+        let pool = fixtures::memory_pool().await;
+        import_sde(&pool, &fixtures::sde()).await.unwrap();
+        sqlx::query("INSERT INTO tracked_structures (structure_id, name, system_id) VALUES (?, 'Jita - Keepstar', ?)")
+            .bind(JITA_STRUCTURE)
+            .bind(JITA)
+            .execute(&pool)
+            .await
+            .unwrap();
+        let mut repository = MarketOrderRepository::new(Arc::new(Mutex::new(pool.clone())));
+        let region_id = RegionId(10000002);
+
+        repository
+            .insert_active_items(vec![sell_order(1, JITA_STATION), sell_order(2, JITA_STRUCTURE)], region_id, None)
+            .await
+            .unwrap();
+        repository
+            .insert_structure_items(vec![sell_order(3, JITA_STRUCTURE)], JITA_STRUCTURE)
+            .await
+            .unwrap();
+        // The structure snapshot replaced the order the region listed.
+        assert_eq!(active_order_ids(&pool).await, vec![1, 3]);
+
+        let (_, deactivated) = repository
+            .insert_active_items(vec![sell_order(4, JITA_STATION)], region_id, None)
+            .await
+            .unwrap();
+        assert_eq!(deactivated, 1);
+        assert_eq!(active_order_ids(&pool).await, vec![3, 4]);
 
-        Ok(map)
+        let structures = repository.tracked_structures(region_id).await.unwrap();
+        assert_eq!(structures.len(), 1);
+        assert_eq!(structures[0].structure_id, JITA_STRUCTURE);
     }
 }
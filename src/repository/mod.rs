@@ -1,7 +1,21 @@
+mod industry;
 mod item;
 mod market_history;
 mod market_orders;
+mod station;
+mod system;
+mod tracked_region;
+mod tracked_structure;
+mod translation;
+mod update_log;
 
-pub use item::ItemRepository;
-pub use market_history::{MarketHistoryRepository, MarketHistoryAverage};
-pub use market_orders::MarketOrderRepository;
+pub use industry::{Blueprint, IndustryActivity, IndustryRepository, Reprocessing};
+pub use item::{ItemFilter, ItemRepository, MarketGroup};
+pub use market_history::{MarketHistoryAverage, MarketHistoryRepository};
+pub use market_orders::{BestOrder, MarketOrderRepository};
+pub use station::{Station, StationRepository};
+pub use system::{SolarSystem, SystemRepository};
+pub use tracked_region::{TrackedRegion, TrackedRegionRepository};
+pub use tracked_structure::{TrackedStructure, TrackedStructureRepository};
+pub use translation::{NameKind, TranslationRepository};
+pub use update_log::{LastRuns, RunKind, RunStats, UpdateLogRepository};
@@ -0,0 +1,119 @@
+use crate::ids::{RegionId, StationId, SystemId};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Serialize)]
+pub struct Station {
+    pub id: StationId,
+    pub name: String,
+    pub system_id: SystemId,
+    pub system_name: String,
+    pub region_id: RegionId,
+    pub corporation_id: i64,
+    pub reprocessing_efficiency: f64,
+    pub reprocessing_stations_take: f64,
+    pub services: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct StationRepository(Arc<Mutex<SqlitePool>>);
+
+impl Clone for StationRepository {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl StationRepository {
+    pub fn new(pool: Arc<Mutex<SqlitePool>>) -> Self {
+        Self(pool)
+    }
+
+    /// Stations with a name in any language containing `search`, optionally
+    /// limited to a region.
+    pub async fn search(
+        &self,
+        search: Option<String>,
+        region_id: Option<RegionId>,
+        limit: usize,
+    ) -> Result<Vec<Station>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let limit = limit as i64;
+        let search = search.map(|search| search.trim().to_lowercase());
+
+        let stations = sqlx::query!(
+            r#"SELECT st.id as "id: StationId", st.name, st.system_id as "system_id: SystemId", sy.name as system_name, sy.region_id as "region_id: RegionId",
+                st.corporation_id, st.reprocessing_efficiency, st.reprocessing_stations_take, group_concat(sv.service, '|') as services
+            FROM eve_station st
+            JOIN eve_system sy ON sy.id = st.system_id
+            LEFT JOIN eve_station_services sv ON sv.station_id = st.id
+            WHERE (?1 IS NULL OR st.name LIKE '%' || ?1 || '%'
+                    OR st.id IN (SELECT t.id FROM eve_translations t WHERE t.kind = 'station' AND t.name_lower LIKE '%' || ?1 || '%'))
+                AND (?2 IS NULL OR sy.region_id = ?2)
+            GROUP BY st.id
+            ORDER BY st.name
+            LIMIT ?3"#,
+            search,
+            region_id,
+            limit
+        )
+        .fetch_all(connection.as_mut())
+        .await?
+        .into_iter()
+        .map(|row| Station {
+            id: row.id,
+            name: row.name,
+            system_id: row.system_id,
+            system_name: row.system_name,
+            region_id: row.region_id,
+            corporation_id: row.corporation_id,
+            reprocessing_efficiency: row.reprocessing_efficiency,
+            reprocessing_stations_take: row.reprocessing_stations_take,
+            services: split_services(row.services),
+        })
+        .collect();
+
+        Ok(stations)
+    }
+
+    pub async fn station(&self, station_id: StationId) -> Result<Option<Station>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let station = sqlx::query!(
+            r#"SELECT st.id as "id: StationId", st.name, st.system_id as "system_id: SystemId", sy.name as system_name, sy.region_id as "region_id: RegionId",
+                st.corporation_id, st.reprocessing_efficiency, st.reprocessing_stations_take, group_concat(sv.service, '|') as services
+            FROM eve_station st
+            JOIN eve_system sy ON sy.id = st.system_id
+            LEFT JOIN eve_station_services sv ON sv.station_id = st.id
+            WHERE st.id = ?
+            GROUP BY st.id"#,
+            station_id
+        )
+        .fetch_optional(connection.as_mut())
+        .await?
+        .map(|row| Station {
+            id: row.id,
+            name: row.name,
+            system_id: row.system_id,
+            system_name: row.system_name,
+            region_id: row.region_id,
+            corporation_id: row.corporation_id,
+            reprocessing_efficiency: row.reprocessing_efficiency,
+            reprocessing_stations_take: row.reprocessing_stations_take,
+            services: split_services(row.services),
+        });
+
+        Ok(station)
+    }
+}
+
+fn split_services(services: Option<String>) -> Vec<String> {
+    services
+        .map(|services| services.split('|').map(|s| s.to_string()).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,100 @@
+use crate::ids::{ConstellationId, RegionId, SystemId};
+use futures::TryStreamExt;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+
+/// Meters in a light year, positions in the SDE are in meters.
+const LIGHT_YEAR: f64 = 9_460_730_472_580_800.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SolarSystem {
+    pub id: SystemId,
+    pub name: String,
+    pub region_id: RegionId,
+    pub constellation_id: Option<ConstellationId>,
+    pub security: Option<f64>,
+    pub security_rounded: Option<f64>,
+    pub security_class: Option<String>,
+    #[serde(skip)]
+    pub position: Option<[f64; 3]>,
+}
+
+impl SolarSystem {
+    /// High security space, where concord responds.
+    pub fn is_highsec(&self) -> bool {
+        self.security_rounded
+            .is_some_and(|security| security >= 0.5)
+    }
+
+    /// Straight line distance in light years.
+    pub fn distance_ly(&self, other: &SolarSystem) -> Option<f64> {
+        let [x1, y1, z1] = self.position?;
+        let [x2, y2, z2] = other.position?;
+
+        Some(((x1 - x2).powi(2) + (y1 - y2).powi(2) + (z1 - z2).powi(2)).sqrt() / LIGHT_YEAR)
+    }
+}
+
+#[derive(Debug)]
+pub struct SystemRepository(Arc<Mutex<SqlitePool>>);
+
+impl Clone for SystemRepository {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl SystemRepository {
+    pub fn new(pool: Arc<Mutex<SqlitePool>>) -> Self {
+        Self(pool)
+    }
+
+    pub async fn systems(&self) -> Result<HashMap<SystemId, SolarSystem>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query!(
+            r#"SELECT id as "id: SystemId", name, region_id as "region_id: RegionId", constellation_id as "constellation_id: ConstellationId",
+                security, security_rounded, security_class, x, y, z
+            FROM eve_system"#
+        )
+        .map(|row| {
+            let position = match (row.x, row.y, row.z) {
+                (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+                _ => None,
+            };
+
+            (
+                row.id,
+                SolarSystem {
+                    id: row.id,
+                    name: row.name,
+                    region_id: row.region_id,
+                    constellation_id: row.constellation_id,
+                    security: row.security,
+                    security_rounded: row.security_rounded,
+                    security_class: row.security_class,
+                    position,
+                },
+            )
+        })
+        .fetch(connection.as_mut())
+        .try_collect()
+        .await
+    }
+
+    /// Stargate connections as (source system, target system).
+    pub async fn stargates(&self) -> Result<Vec<(SystemId, SystemId)>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query!(
+            r#"SELECT source_system_id as "source_system_id: SystemId", target_system_id as "target_system_id: SystemId" FROM eve_stargates"#
+        )
+        .map(|row| (row.source_system_id, row.target_system_id))
+        .fetch_all(connection.as_mut())
+        .await
+    }
+}
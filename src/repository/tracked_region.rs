@@ -0,0 +1,167 @@
+use crate::ids::RegionId;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// A region the collectors update, managed through the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackedRegion {
+    pub region_id: RegionId,
+    pub name: String,
+    pub history: bool,
+    pub orders: bool,
+    pub paused: bool,
+    pub added: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct TrackedRegionRepository(Arc<Mutex<SqlitePool>>);
+
+impl TrackedRegionRepository {
+    pub fn new(pool: Arc<Mutex<SqlitePool>>) -> Self {
+        Self(pool)
+    }
+}
+
+impl Clone for TrackedRegionRepository {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl TrackedRegionRepository {
+    pub async fn all(&self) -> Result<Vec<TrackedRegion>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            TrackedRegion,
+            r#"SELECT t.region_id as "region_id: RegionId", r.name, t.history as "history: bool", t.orders as "orders: bool", t.paused as "paused: bool", t.added as "added: DateTime<Utc>"
+            FROM tracked_regions t
+            JOIN eve_region r ON r.id = t.region_id
+            ORDER BY t.region_id"#
+        )
+        .fetch_all(connection.as_mut())
+        .await
+    }
+
+    pub async fn get(&self, region_id: RegionId) -> Result<Option<TrackedRegion>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            TrackedRegion,
+            r#"SELECT t.region_id as "region_id: RegionId", r.name, t.history as "history: bool", t.orders as "orders: bool", t.paused as "paused: bool", t.added as "added: DateTime<Utc>"
+            FROM tracked_regions t
+            JOIN eve_region r ON r.id = t.region_id
+            WHERE t.region_id = ?"#,
+            region_id
+        )
+        .fetch_optional(connection.as_mut())
+        .await
+    }
+
+    /// Fills the table on the first start, after that it is only changed
+    /// through the admin API. Returns whether anything was inserted.
+    pub async fn seed(
+        &self,
+        history: &[RegionId],
+        orders: &[RegionId],
+    ) -> Result<bool, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut transaction = lock.begin().await?;
+
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM tracked_regions")
+            .fetch_one(transaction.as_mut())
+            .await?;
+        if count > 0 {
+            return Ok(false);
+        }
+
+        let mut regions = history.to_vec();
+        regions.extend(orders.iter().copied());
+        regions.sort();
+        regions.dedup();
+
+        for region_id in regions.iter() {
+            let with_history = history.contains(region_id);
+            let with_orders = orders.contains(region_id);
+
+            sqlx::query!(
+                "INSERT INTO tracked_regions (region_id, history, orders) VALUES (?, ?, ?)",
+                region_id,
+                with_history,
+                with_orders
+            )
+            .execute(transaction.as_mut())
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(!regions.is_empty())
+    }
+
+    /// Returns false when the region is already tracked.
+    pub async fn add(
+        &self,
+        region_id: RegionId,
+        history: bool,
+        orders: bool,
+    ) -> Result<bool, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let result = sqlx::query!(
+            "INSERT INTO tracked_regions (region_id, history, orders) VALUES (?, ?, ?) ON CONFLICT (region_id) DO NOTHING",
+            region_id,
+            history,
+            orders
+        )
+        .execute(connection.as_mut())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns false when the region is not tracked.
+    pub async fn set_paused(&self, region_id: RegionId, paused: bool) -> Result<bool, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let result = sqlx::query!(
+            "UPDATE tracked_regions SET paused = ? WHERE region_id = ?",
+            paused,
+            region_id
+        )
+        .execute(connection.as_mut())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns false when the region is not tracked.
+    pub async fn remove(&self, region_id: RegionId) -> Result<bool, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let result = sqlx::query!("DELETE FROM tracked_regions WHERE region_id = ?", region_id)
+            .execute(connection.as_mut())
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn region_exists(&self, region_id: RegionId) -> Result<bool, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let found = sqlx::query_scalar!("SELECT id FROM eve_region WHERE id = ?", region_id)
+            .fetch_optional(connection.as_mut())
+            .await?;
+
+        Ok(found.is_some())
+    }
+}
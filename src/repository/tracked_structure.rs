@@ -0,0 +1,137 @@
+use crate::ids::{RegionId, StationId, SystemId};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// A player structure whose market is collected with the orders of its
+/// region, managed through the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackedStructure {
+    pub structure_id: StationId,
+    pub name: String,
+    pub system_id: SystemId,
+    pub region_id: RegionId,
+    pub paused: bool,
+    pub added: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct TrackedStructureRepository(Arc<Mutex<SqlitePool>>);
+
+impl TrackedStructureRepository {
+    pub fn new(pool: Arc<Mutex<SqlitePool>>) -> Self {
+        Self(pool)
+    }
+}
+
+impl Clone for TrackedStructureRepository {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl TrackedStructureRepository {
+    pub async fn all(&self) -> Result<Vec<TrackedStructure>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            TrackedStructure,
+            r#"SELECT t.structure_id as "structure_id: StationId", t.name, t.system_id as "system_id: SystemId", s.region_id as "region_id: RegionId", t.paused as "paused: bool", t.added as "added: DateTime<Utc>"
+            FROM tracked_structures t
+            JOIN eve_system s ON s.id = t.system_id
+            ORDER BY t.structure_id"#
+        )
+        .fetch_all(connection.as_mut())
+        .await
+    }
+
+    pub async fn get(
+        &self,
+        structure_id: StationId,
+    ) -> Result<Option<TrackedStructure>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            TrackedStructure,
+            r#"SELECT t.structure_id as "structure_id: StationId", t.name, t.system_id as "system_id: SystemId", s.region_id as "region_id: RegionId", t.paused as "paused: bool", t.added as "added: DateTime<Utc>"
+            FROM tracked_structures t
+            JOIN eve_system s ON s.id = t.system_id
+            WHERE t.structure_id = ?"#,
+            structure_id
+        )
+        .fetch_optional(connection.as_mut())
+        .await
+    }
+
+    /// Returns false when the structure is already tracked.
+    pub async fn add(
+        &self,
+        structure_id: StationId,
+        name: &str,
+        system_id: SystemId,
+    ) -> Result<bool, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let result = sqlx::query!(
+            "INSERT INTO tracked_structures (structure_id, name, system_id) VALUES (?, ?, ?) ON CONFLICT (structure_id) DO NOTHING",
+            structure_id,
+            name,
+            system_id
+        )
+        .execute(connection.as_mut())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns false when the structure is not tracked.
+    pub async fn set_paused(
+        &self,
+        structure_id: StationId,
+        paused: bool,
+    ) -> Result<bool, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let result = sqlx::query!(
+            "UPDATE tracked_structures SET paused = ? WHERE structure_id = ?",
+            paused,
+            structure_id
+        )
+        .execute(connection.as_mut())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns false when the structure is not tracked.
+    pub async fn remove(&self, structure_id: StationId) -> Result<bool, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let result = sqlx::query!(
+            "DELETE FROM tracked_structures WHERE structure_id = ?",
+            structure_id
+        )
+        .execute(connection.as_mut())
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn system_exists(&self, system_id: SystemId) -> Result<bool, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let found = sqlx::query_scalar!("SELECT id FROM eve_system WHERE id = ?", system_id)
+            .fetch_optional(connection.as_mut())
+            .await?;
+
+        Ok(found.is_some())
+    }
+}
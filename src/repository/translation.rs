@@ -0,0 +1,150 @@
+use crate::ids::TypeId;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+
+/// What a row in `eve_translations` names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameKind {
+    Type,
+    Group,
+    MarketGroup,
+    Category,
+    MetaGroup,
+    Region,
+    Constellation,
+    System,
+    Station,
+}
+
+impl NameKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NameKind::Type => "type",
+            NameKind::Group => "group",
+            NameKind::MarketGroup => "market_group",
+            NameKind::Category => "category",
+            NameKind::MetaGroup => "meta_group",
+            NameKind::Region => "region",
+            NameKind::Constellation => "constellation",
+            NameKind::System => "system",
+            NameKind::Station => "station",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemMatch {
+    pub id: TypeId,
+    /// Name in the requested language, English when there is none.
+    pub name: String,
+    /// The name that matched the search and its language.
+    pub matched_name: String,
+    pub matched_language: String,
+}
+
+#[derive(Debug)]
+pub struct TranslationRepository(Arc<Mutex<SqlitePool>>);
+
+impl Clone for TranslationRepository {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl TranslationRepository {
+    pub fn new(pool: Arc<Mutex<SqlitePool>>) -> Self {
+        Self(pool)
+    }
+
+    /// Names of everything of `kind` in `language`, by id.
+    pub async fn names(
+        &self,
+        kind: NameKind,
+        language: &str,
+    ) -> Result<HashMap<i64, String>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let kind = kind.as_str();
+        let names = sqlx::query!(
+            "SELECT id, name FROM eve_translations WHERE kind = ? AND language = ?",
+            kind,
+            language
+        )
+        .fetch_all(connection.as_mut())
+        .await?
+        .into_iter()
+        .map(|row| (row.id, row.name))
+        .collect();
+
+        Ok(names)
+    }
+
+    pub async fn name(
+        &self,
+        kind: NameKind,
+        id: i64,
+        language: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let kind = kind.as_str();
+        let name = sqlx::query_scalar!(
+            "SELECT name FROM eve_translations WHERE kind = ? AND id = ? AND language = ?",
+            kind,
+            id,
+            language
+        )
+        .fetch_optional(connection.as_mut())
+        .await?;
+
+        Ok(name)
+    }
+
+    /// Published items with a name in any language containing `search`,
+    /// exact matches first and then the shortest names.
+    pub async fn search_items(
+        &self,
+        search: &str,
+        language: &str,
+        limit: i64,
+    ) -> Result<Vec<ItemMatch>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let search = search.trim().to_lowercase();
+        let pattern = format!(
+            "%{}%",
+            search
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        sqlx::query_as!(
+            ItemMatch,
+            r#"SELECT m.id as "id!: TypeId", COALESCE(l.name, i.name) as "name!: String", m.name as "matched_name!: String", m.language as "matched_language!: String"
+            FROM (
+                SELECT t.id, t.name, t.language, t.name_lower = ?2 as exact,
+                    ROW_NUMBER() OVER (PARTITION BY t.id ORDER BY t.name_lower = ?2 DESC, length(t.name), t.language = ?3 DESC, t.language = 'en' DESC) as rank
+                FROM eve_translations t
+                WHERE t.kind = 'type' AND t.name_lower LIKE ?1 ESCAPE '\'
+            ) m
+            JOIN eve_items i ON i.id = m.id
+            LEFT JOIN eve_translations l ON l.kind = 'type' AND l.id = m.id AND l.language = ?3
+            WHERE m.rank = 1 AND i.published = 1
+            ORDER BY m.exact DESC, length(m.name), m.id
+            LIMIT ?4"#,
+            pattern,
+            search,
+            language,
+            limit
+        )
+        .fetch_all(connection.as_mut())
+        .await
+    }
+}
@@ -0,0 +1,244 @@
+use crate::ids::RegionId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunKind {
+    Orders,
+    History,
+}
+
+impl RunKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunKind::Orders => "orders",
+            RunKind::History => "history",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RunStats {
+    pub pages_fetched: usize,
+    pub rows_inserted: usize,
+    pub rows_deactivated: usize,
+    /// Types whose history could not be fetched, the run finished without
+    /// them.
+    pub failed_types: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateLogEntry {
+    pub id: i64,
+    pub region_id: RegionId,
+    pub kind: String,
+    pub status: String,
+    pub started: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+    pub pages_fetched: i64,
+    pub rows_inserted: i64,
+    pub rows_deactivated: i64,
+    pub failed_types: i64,
+    pub error: Option<String>,
+}
+
+/// Outcome of the latest runs of a collector, so its status survives restarts.
+#[derive(Debug, Default)]
+pub struct LastRuns {
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct UpdateLogRepository(Arc<Mutex<SqlitePool>>);
+
+impl UpdateLogRepository {
+    pub fn new(pool: Arc<Mutex<SqlitePool>>) -> Self {
+        Self(pool)
+    }
+}
+
+impl Clone for UpdateLogRepository {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl UpdateLogRepository {
+    pub async fn start_run(
+        &mut self,
+        region_id: RegionId,
+        kind: RunKind,
+    ) -> Result<i64, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let kind = kind.as_str();
+        let started = Utc::now();
+
+        let run_id = sqlx::query!(
+            "INSERT INTO update_log (region_id, kind, status, started) VALUES (?, ?, 'running', ?)",
+            region_id,
+            kind,
+            started
+        )
+        .execute(connection.as_mut())
+        .await?
+        .last_insert_rowid();
+
+        Ok(run_id)
+    }
+
+    pub async fn finish_run(&mut self, run_id: i64, stats: RunStats) -> Result<(), sqlx::Error> {
+        self.end_run(run_id, "finished", stats, None).await
+    }
+
+    pub async fn fail_run(
+        &mut self,
+        run_id: i64,
+        stats: RunStats,
+        error: String,
+    ) -> Result<(), sqlx::Error> {
+        self.end_run(run_id, "failed", stats, Some(error)).await
+    }
+
+    /// The run stopped before it finished because the server shut down.
+    pub async fn interrupt_run(&mut self, run_id: i64, stats: RunStats) -> Result<(), sqlx::Error> {
+        self.end_run(run_id, "interrupted", stats, None).await
+    }
+
+    /// Marks runs that were still running when the server last stopped, e.g.
+    /// after a crash. Returns how many there were.
+    pub async fn interrupt_unfinished(&mut self) -> Result<u64, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let finished = Utc::now();
+
+        let result = sqlx::query!(
+            "UPDATE update_log SET status = 'interrupted', finished = ? WHERE status = 'running'",
+            finished
+        )
+        .execute(connection.as_mut())
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn end_run(
+        &mut self,
+        run_id: i64,
+        status: &str,
+        stats: RunStats,
+        error: Option<String>,
+    ) -> Result<(), sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let finished = Utc::now();
+        let pages_fetched = stats.pages_fetched as i64;
+        let rows_inserted = stats.rows_inserted as i64;
+        let rows_deactivated = stats.rows_deactivated as i64;
+        let failed_types = stats.failed_types as i64;
+
+        sqlx::query!(
+            "UPDATE update_log SET status = ?, finished = ?, pages_fetched = ?, rows_inserted = ?, rows_deactivated = ?, failed_types = ?, error = ? WHERE id = ?",
+            status,
+            finished,
+            pages_fetched,
+            rows_inserted,
+            rows_deactivated,
+            failed_types,
+            error,
+            run_id
+        )
+        .execute(connection.as_mut())
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn recent_runs(
+        &self,
+        region_id: Option<RegionId>,
+        kind: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<UpdateLogEntry>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let limit = limit as i64;
+
+        sqlx::query_as!(
+            UpdateLogEntry,
+            r#"SELECT id, region_id as "region_id: RegionId", kind, status, started as "started: DateTime<Utc>", finished as "finished: DateTime<Utc>", pages_fetched, rows_inserted, rows_deactivated, failed_types, error
+            FROM update_log
+            WHERE (?1 IS NULL OR region_id = ?1) AND (?2 IS NULL OR kind = ?2)
+            ORDER BY started DESC, id DESC
+            LIMIT ?3"#,
+            region_id,
+            kind,
+            limit
+        )
+        .fetch_all(connection.as_mut())
+        .await
+    }
+
+    pub async fn run(&self, run_id: i64) -> Result<Option<UpdateLogEntry>, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        sqlx::query_as!(
+            UpdateLogEntry,
+            r#"SELECT id, region_id as "region_id: RegionId", kind, status, started as "started: DateTime<Utc>", finished as "finished: DateTime<Utc>", pages_fetched, rows_inserted, rows_deactivated, failed_types, error
+            FROM update_log
+            WHERE id = ?"#,
+            run_id
+        )
+        .fetch_optional(connection.as_mut())
+        .await
+    }
+
+    pub async fn last_runs(
+        &self,
+        region_id: RegionId,
+        kind: RunKind,
+    ) -> Result<LastRuns, sqlx::Error> {
+        let lock = self.0.lock().await;
+        let mut connection = lock.acquire().await?;
+
+        let kind = kind.as_str();
+
+        let last_success = sqlx::query_scalar!(
+            r#"SELECT MAX(finished) as "finished: DateTime<Utc>" FROM update_log WHERE region_id = ? AND kind = ? AND status = 'finished'"#,
+            region_id,
+            kind
+        )
+        .fetch_one(connection.as_mut())
+        .await?;
+
+        let last_failure = sqlx::query!(
+            r#"SELECT finished as "finished: DateTime<Utc>", error FROM update_log
+            WHERE region_id = ? AND kind = ? AND status = 'failed'
+            ORDER BY started DESC, id DESC
+            LIMIT 1"#,
+            region_id,
+            kind
+        )
+        .fetch_optional(connection.as_mut())
+        .await?;
+
+        Ok(LastRuns {
+            last_success,
+            last_error: last_failure
+                .as_ref()
+                .and_then(|failure| failure.error.clone()),
+            last_error_at: last_failure.and_then(|failure| failure.finished),
+        })
+    }
+}
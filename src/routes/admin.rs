@@ -0,0 +1,467 @@
+use actix::Addr;
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    actors::{RefreshRegion, RegionManager, StartError, TrackRegion, UntrackRegion},
+    config::AppConfig,
+    esi::{errors::EsiError, get_structure, EsiClient},
+    ids::{RegionId, StationId, TypeId},
+    repository::{
+        ItemRepository, RunKind, TrackedRegion, TrackedRegionRepository, TrackedStructure,
+        TrackedStructureRepository,
+    },
+};
+
+/// Types that one refresh may ask for, orders are fetched once per type.
+const MAX_REFRESH_TYPES: usize = 100;
+
+#[derive(Deserialize)]
+struct AddRegion {
+    region_id: RegionId,
+    history: Option<bool>,
+    orders: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct AddStructure {
+    structure_id: StationId,
+}
+
+#[derive(Deserialize)]
+struct RefreshQuery {
+    /// Comma separated type ids, the whole region without.
+    types: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RefreshStarted {
+    run_id: i64,
+    /// Where the run can be polled.
+    status: String,
+}
+
+/// Checks the `Authorization: Bearer` header against `admin_token`.
+fn authorize(request: &HttpRequest, config: &AppConfig) -> Result<()> {
+    let Some(token) = config.admin_token.as_deref() else {
+        return Err(actix_web::error::ErrorForbidden(
+            "The admin API is disabled, set admin_token",
+        ));
+    };
+
+    let given = request
+        .headers()
+        .get("Authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "));
+
+    match given {
+        Some(given) if constant_time_eq(given.as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(actix_web::error::ErrorUnauthorized("Invalid admin token")),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn read_error(e: sqlx::Error) -> actix_web::Error {
+    log::error!("Could not read tracked regions: {}", e);
+    actix_web::error::ErrorInternalServerError("Could not read tracked regions")
+}
+
+fn write_error(e: sqlx::Error) -> actix_web::Error {
+    log::error!("Could not update tracked regions: {}", e);
+    actix_web::error::ErrorInternalServerError("Could not update tracked regions")
+}
+
+fn structure_read_error(e: sqlx::Error) -> actix_web::Error {
+    log::error!("Could not read tracked structures: {}", e);
+    actix_web::error::ErrorInternalServerError("Could not read tracked structures")
+}
+
+fn structure_write_error(e: sqlx::Error) -> actix_web::Error {
+    log::error!("Could not update tracked structures: {}", e);
+    actix_web::error::ErrorInternalServerError("Could not update tracked structures")
+}
+
+async fn tracked_region(
+    repository: &TrackedRegionRepository,
+    region_id: RegionId,
+) -> Result<TrackedRegion> {
+    repository
+        .get(region_id)
+        .await
+        .map_err(read_error)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Region is not tracked"))
+}
+
+/// Starts the collectors and runs them right away, so a new or resumed region
+/// does not wait for the next scheduled run.
+fn track(region_manager: &Addr<RegionManager>, region: &TrackedRegion) {
+    region_manager.do_send(TrackRegion {
+        region_id: region.region_id,
+        history: region.history,
+        orders: region.orders,
+        start_history: true,
+        start_orders: true,
+    });
+}
+
+#[get("/admin/regions")]
+pub async fn admin_regions(
+    request: HttpRequest,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedRegionRepository>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let regions = repository.all().await.map_err(read_error)?;
+
+    Ok(HttpResponse::Ok().json(regions))
+}
+
+#[post("/admin/regions")]
+pub async fn add_region(
+    request: HttpRequest,
+    body: web::Json<AddRegion>,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedRegionRepository>,
+    region_manager: web::Data<Addr<RegionManager>>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let body = body.into_inner();
+    let history = body.history.unwrap_or(true);
+    let orders = body.orders.unwrap_or(true);
+
+    if !history && !orders {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Enable at least one of history and orders",
+        ));
+    }
+    if !repository
+        .region_exists(body.region_id)
+        .await
+        .map_err(read_error)?
+    {
+        return Err(actix_web::error::ErrorNotFound("Unknown region"));
+    }
+    if !repository
+        .add(body.region_id, history, orders)
+        .await
+        .map_err(write_error)?
+    {
+        return Err(actix_web::error::ErrorConflict("Region is already tracked"));
+    }
+
+    let region = tracked_region(&repository, body.region_id).await?;
+    track(&region_manager, &region);
+
+    Ok(HttpResponse::Created().json(region))
+}
+
+#[post("/admin/regions/{region_id}/pause")]
+pub async fn pause_region(
+    request: HttpRequest,
+    path: web::Path<RegionId>,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedRegionRepository>,
+    region_manager: web::Data<Addr<RegionManager>>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let region_id = path.into_inner();
+    if !repository
+        .set_paused(region_id, true)
+        .await
+        .map_err(write_error)?
+    {
+        return Err(actix_web::error::ErrorNotFound("Region is not tracked"));
+    }
+
+    region_manager.do_send(UntrackRegion(region_id));
+
+    Ok(HttpResponse::Ok().json(tracked_region(&repository, region_id).await?))
+}
+
+#[post("/admin/regions/{region_id}/resume")]
+pub async fn resume_region(
+    request: HttpRequest,
+    path: web::Path<RegionId>,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedRegionRepository>,
+    region_manager: web::Data<Addr<RegionManager>>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let region_id = path.into_inner();
+    let region = tracked_region(&repository, region_id).await?;
+    if !region.paused {
+        return Ok(HttpResponse::Ok().json(region));
+    }
+
+    repository
+        .set_paused(region_id, false)
+        .await
+        .map_err(write_error)?;
+
+    let region = tracked_region(&repository, region_id).await?;
+    track(&region_manager, &region);
+
+    Ok(HttpResponse::Ok().json(region))
+}
+
+#[delete("/admin/regions/{region_id}")]
+pub async fn remove_region(
+    request: HttpRequest,
+    path: web::Path<RegionId>,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedRegionRepository>,
+    region_manager: web::Data<Addr<RegionManager>>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let region_id = path.into_inner();
+    if !repository.remove(region_id).await.map_err(write_error)? {
+        return Err(actix_web::error::ErrorNotFound("Region is not tracked"));
+    }
+
+    region_manager.do_send(UntrackRegion(region_id));
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Runs the history or order collector of a region now, e.g. after downtime,
+/// and returns the run to poll at `/status/runs/{run_id}`.
+#[post("/admin/regions/{region_id}/refresh/{kind}")]
+pub async fn refresh_region(
+    request: HttpRequest,
+    path: web::Path<(RegionId, RunKind)>,
+    query: web::Query<RefreshQuery>,
+    config: web::Data<AppConfig>,
+    item_repository: web::Data<ItemRepository>,
+    region_manager: web::Data<Addr<RegionManager>>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let (region_id, kind) = path.into_inner();
+    let types = match query.into_inner().types {
+        Some(types) => Some(parse_types(&types, item_repository.get_ref().clone()).await?),
+        None => None,
+    };
+
+    let started = region_manager
+        .send(RefreshRegion {
+            region_id,
+            kind,
+            types,
+        })
+        .await
+        .map_err(StartError::Mailbox)
+        .and_then(|result| result);
+
+    match started {
+        Ok(run_id) => Ok(HttpResponse::Accepted().json(RefreshStarted {
+            run_id,
+            status: format!("/status/runs/{}", run_id),
+        })),
+        Err(StartError::AlreadyRunning(run_id)) => Ok(HttpResponse::Conflict().json(json!({
+            "error": "Collector is already running",
+            "run_id": run_id,
+        }))),
+        Err(StartError::NotTracked) => Err(actix_web::error::ErrorNotFound(format!(
+            "Region has no running {} collector",
+            kind.as_str()
+        ))),
+        Err(e) => {
+            log::error!("Could not start {} run: {}", kind.as_str(), e);
+            Err(actix_web::error::ErrorInternalServerError(
+                "Could not start run",
+            ))
+        }
+    }
+}
+
+async fn parse_types(types: &str, mut item_repository: ItemRepository) -> Result<Vec<TypeId>> {
+    let mut types = types
+        .split(',')
+        .map(str::trim)
+        .filter(|type_id| !type_id.is_empty())
+        .map(|type_id| type_id.parse::<TypeId>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| actix_web::error::ErrorBadRequest("types must be comma separated type ids"))?;
+    types.sort();
+    types.dedup();
+
+    if types.is_empty() || types.len() > MAX_REFRESH_TYPES {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "types must list 1 to {} type ids",
+            MAX_REFRESH_TYPES
+        )));
+    }
+
+    let tradeable = item_repository.tradeable_item_ids().await.map_err(|e| {
+        log::error!("Could not read items: {}", e);
+        actix_web::error::ErrorInternalServerError("Could not read items")
+    })?;
+    let unknown = types
+        .iter()
+        .filter(|type_id| !tradeable.contains(type_id))
+        .map(|type_id| type_id.to_string())
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        return Err(actix_web::error::ErrorBadRequest(format!(
+            "Not tradeable: {}",
+            unknown.join(", ")
+        )));
+    }
+
+    Ok(types)
+}
+
+async fn tracked_structure(
+    repository: &TrackedStructureRepository,
+    structure_id: StationId,
+) -> Result<TrackedStructure> {
+    repository
+        .get(structure_id)
+        .await
+        .map_err(structure_read_error)?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Structure is not tracked"))
+}
+
+#[get("/admin/structures")]
+pub async fn admin_structures(
+    request: HttpRequest,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedStructureRepository>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let structures = repository.all().await.map_err(structure_read_error)?;
+
+    Ok(HttpResponse::Ok().json(structures))
+}
+
+/// Tracks a structure the configured character can dock in. Its name and
+/// system come from ESI, its orders are collected with the orders of its
+/// region.
+#[post("/admin/structures")]
+pub async fn add_structure(
+    request: HttpRequest,
+    body: web::Json<AddStructure>,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedStructureRepository>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let client = EsiClient::current();
+    if !client.is_authenticated() {
+        return Err(actix_web::error::ErrorForbidden(
+            "Structure markets are disabled, set the sso credentials",
+        ));
+    }
+
+    let structure_id = body.into_inner().structure_id;
+    let structure = match get_structure(client, structure_id).await {
+        Ok(structure) => structure,
+        Err(EsiError::ErrorResponse) => {
+            return Err(actix_web::error::ErrorNotFound(
+                "Unknown structure, or the character has no access to it",
+            ))
+        }
+        Err(e) => {
+            log::error!("Could not read structure {}: {}", structure_id, e);
+            return Err(actix_web::error::ErrorBadGateway(
+                "Could not read structure",
+            ));
+        }
+    };
+
+    if !repository
+        .system_exists(structure.solar_system_id)
+        .await
+        .map_err(structure_read_error)?
+    {
+        return Err(actix_web::error::ErrorNotFound(
+            "Structure is in an unknown system, import the SDE",
+        ));
+    }
+    if !repository
+        .add(structure_id, &structure.name, structure.solar_system_id)
+        .await
+        .map_err(structure_write_error)?
+    {
+        return Err(actix_web::error::ErrorConflict(
+            "Structure is already tracked",
+        ));
+    }
+
+    Ok(HttpResponse::Created().json(tracked_structure(&repository, structure_id).await?))
+}
+
+/// Skips the structure in order runs, its orders are left to the region.
+#[post("/admin/structures/{structure_id}/pause")]
+pub async fn pause_structure(
+    request: HttpRequest,
+    path: web::Path<StationId>,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedStructureRepository>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let structure_id = path.into_inner();
+    if !repository
+        .set_paused(structure_id, true)
+        .await
+        .map_err(structure_write_error)?
+    {
+        return Err(actix_web::error::ErrorNotFound("Structure is not tracked"));
+    }
+
+    Ok(HttpResponse::Ok().json(tracked_structure(&repository, structure_id).await?))
+}
+
+#[post("/admin/structures/{structure_id}/resume")]
+pub async fn resume_structure(
+    request: HttpRequest,
+    path: web::Path<StationId>,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedStructureRepository>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let structure_id = path.into_inner();
+    if !repository
+        .set_paused(structure_id, false)
+        .await
+        .map_err(structure_write_error)?
+    {
+        return Err(actix_web::error::ErrorNotFound("Structure is not tracked"));
+    }
+
+    Ok(HttpResponse::Ok().json(tracked_structure(&repository, structure_id).await?))
+}
+
+#[delete("/admin/structures/{structure_id}")]
+pub async fn remove_structure(
+    request: HttpRequest,
+    path: web::Path<StationId>,
+    config: web::Data<AppConfig>,
+    repository: web::Data<TrackedStructureRepository>,
+) -> Result<impl Responder> {
+    authorize(&request, &config)?;
+
+    let structure_id = path.into_inner();
+    if !repository
+        .remove(structure_id)
+        .await
+        .map_err(structure_write_error)?
+    {
+        return Err(actix_web::error::ErrorNotFound("Structure is not tracked"));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,217 @@
+use std::{collections::HashSet, hash::Hash, str::FromStr, time::Duration};
+
+use actix_web::{get, http::header, web, HttpRequest, HttpResponse, Responder, Result};
+use actix_ws::AggregatedMessage;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::{
+    sync::broadcast::{error::RecvError, Receiver},
+    time::Instant,
+};
+
+use crate::{
+    events::{EventBus, EventFilter, MarketEvent},
+    ids::{RegionId, TypeId},
+};
+
+/// Sent when there were no events for a while, so proxies keep the connection
+/// open and closed clients are noticed.
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize)]
+pub struct EventQuery {
+    /// Comma separated region ids, all regions when missing.
+    regions: Option<String>,
+    /// Comma separated type ids, all types when missing.
+    types: Option<String>,
+}
+
+impl EventQuery {
+    fn filter(&self) -> Result<EventFilter> {
+        Ok(EventFilter {
+            regions: self
+                .regions
+                .as_deref()
+                .map(|regions| parse_ids::<RegionId>(regions, "regions"))
+                .transpose()?,
+            types: self
+                .types
+                .as_deref()
+                .map(|types| parse_ids::<TypeId>(types, "types"))
+                .transpose()?,
+        })
+    }
+}
+
+fn parse_ids<T: FromStr + Eq + Hash>(ids: &str, name: &str) -> Result<HashSet<T>> {
+    ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse::<T>())
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(|_| {
+            actix_web::error::ErrorBadRequest(format!("{} must be comma separated ids", name))
+        })
+}
+
+enum Delivery {
+    Event(MarketEvent),
+    /// The client was too slow and missed this many events.
+    Lagged(u64),
+    KeepAlive,
+}
+
+/// Waits for the next event that passes the filter, `None` once the bus is
+/// gone.
+async fn next_delivery(
+    receiver: &mut Receiver<MarketEvent>,
+    filter: &EventFilter,
+) -> Option<Delivery> {
+    let deadline = Instant::now() + KEEP_ALIVE;
+    loop {
+        match tokio::time::timeout_at(deadline, receiver.recv()).await {
+            Err(_) => return Some(Delivery::KeepAlive),
+            Ok(Ok(event)) if filter.matches(&event) => return Some(Delivery::Event(event)),
+            Ok(Ok(_)) => continue,
+            Ok(Err(RecvError::Lagged(missed))) => return Some(Delivery::Lagged(missed)),
+            Ok(Err(RecvError::Closed)) => return None,
+        }
+    }
+}
+
+/// Market events as Server-Sent Events, `?regions=10000002&types=34,35`
+/// limits them to some regions and items.
+#[get("/events")]
+pub async fn event_stream(
+    query: web::Query<EventQuery>,
+    events: web::Data<EventBus>,
+) -> Result<impl Responder> {
+    let filter = query.filter()?;
+    let receiver = events.subscribe();
+
+    let stream = futures::stream::unfold((receiver, filter), |(mut receiver, filter)| async move {
+        let chunk = match next_delivery(&mut receiver, &filter).await? {
+            Delivery::Event(event) => format!(
+                "event: {}\ndata: {}\n\n",
+                event.name(),
+                serde_json::to_string(&event).ok()?
+            ),
+            Delivery::Lagged(missed) => format!(
+                "event: lagged\ndata: {}\n\n",
+                json!({ "event": "lagged", "missed": missed })
+            ),
+            Delivery::KeepAlive => ": keep-alive\n\n".to_string(),
+        };
+        Some((
+            Ok::<_, actix_web::Error>(web::Bytes::from(chunk)),
+            (receiver, filter),
+        ))
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        .streaming(stream))
+}
+
+/// Market events over a WebSocket, filtered like `/events`. The client changes
+/// its filter by sending `{"regions": [10000002], "types": [34]}`.
+#[get("/events/ws")]
+pub async fn event_socket(
+    request: HttpRequest,
+    body: web::Payload,
+    query: web::Query<EventQuery>,
+    events: web::Data<EventBus>,
+) -> Result<impl Responder> {
+    let mut filter = query.filter()?;
+    let mut receiver = events.subscribe();
+    let (response, mut session, messages) = actix_ws::handle(&request, body)?;
+    let mut messages = messages.aggregate_continuations();
+
+    actix_web::rt::spawn(async move {
+        loop {
+            let sent = tokio::select! {
+                delivery = next_delivery(&mut receiver, &filter) => match delivery {
+                    Some(Delivery::Event(event)) => match serde_json::to_string(&event) {
+                        Ok(event) => session.text(event).await,
+                        Err(e) => {
+                            log::error!("Could not serialize event: {:?}", e);
+                            Ok(())
+                        }
+                    },
+                    Some(Delivery::Lagged(missed)) => {
+                        session
+                            .text(json!({ "event": "lagged", "missed": missed }).to_string())
+                            .await
+                    }
+                    Some(Delivery::KeepAlive) => session.ping(b"").await,
+                    None => break,
+                },
+                message = messages.recv() => match message {
+                    Some(Ok(AggregatedMessage::Text(text))) => {
+                        match serde_json::from_str::<EventFilter>(&text) {
+                            Ok(subscription) => {
+                                filter = subscription;
+                                let mut subscribed = json!(filter);
+                                subscribed["event"] = json!("subscribed");
+                                session.text(subscribed.to_string()).await
+                            }
+                            Err(e) => {
+                                session
+                                    .text(json!({ "event": "error", "error": e.to_string() }).to_string())
+                                    .await
+                            }
+                        }
+                    }
+                    Some(Ok(AggregatedMessage::Ping(bytes))) => session.pong(&bytes).await,
+                    Some(Ok(AggregatedMessage::Binary(_) | AggregatedMessage::Pong(_))) => Ok(()),
+                    Some(Ok(AggregatedMessage::Close(_)) | Err(_)) | None => break,
+                },
+            };
+
+            if sent.is_err() {
+                return;
+            }
+        }
+
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_comma_separated_ids() {
+        let types = parse_ids::<TypeId>(" 34, 35,,34 ", "types").unwrap();
+
+        assert_eq!(types, HashSet::from([TypeId(34), TypeId(35)]));
+        assert!(parse_ids::<TypeId>("", "types").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_ids() {
+        let error = parse_ids::<RegionId>("10000002,forge", "regions").unwrap_err();
+
+        assert_eq!(error.to_string(), "regions must be comma separated ids");
+        assert_eq!(
+            error.as_response_error().status_code(),
+            actix_web::http::StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn missing_parameters_let_everything_through() {
+        let query = EventQuery {
+            regions: None,
+            types: Some("34".to_string()),
+        };
+        let filter = query.filter().unwrap();
+
+        assert!(filter.regions.is_none());
+        assert_eq!(filter.types, Some(HashSet::from([TypeId(34)])));
+    }
+}
@@ -0,0 +1,100 @@
+use actix_web::{get, web, HttpResponse, Responder, Result};
+use chrono::NaiveDate;
+use futures::{channel::mpsc, SinkExt, StreamExt};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+use crate::{
+    export::{self, ExportFilter, ExportFormat, ExportTable, ExportWriter},
+    ids::{RegionId, TypeId},
+};
+
+/// Rows between handing buffered output to the response.
+const BATCH_SIZE: usize = 1000;
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: Option<ExportFormat>,
+    region: Option<RegionId>,
+    item: Option<TypeId>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+#[get("/export/{table}")]
+pub async fn export_table(
+    path: web::Path<ExportTable>,
+    query: web::Query<ExportQuery>,
+    sqlx_pool: web::Data<SqlitePool>,
+) -> Result<impl Responder> {
+    let table = path.into_inner();
+    let query = query.into_inner();
+    let format = query.format.unwrap_or(ExportFormat::Csv);
+    let filter = ExportFilter {
+        region: query.region,
+        item: query.item,
+        from: query.from,
+        to: query.to,
+    };
+    let pool = sqlx_pool.get_ref().clone();
+
+    let writer =
+        ExportWriter::new(format, table.name(), table.columns(), Vec::new()).map_err(|e| {
+            log::error!("Could not start export: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not start export")
+        })?;
+
+    let (mut sender, receiver) = mpsc::channel::<Result<web::Bytes, actix_web::Error>>(4);
+
+    actix::spawn(async move {
+        if let Err(e) = stream_export(&pool, table, &filter, writer, &mut sender).await {
+            log::error!("Export of {} failed: {}", table.name(), e);
+            let _ = sender
+                .send(Err(actix_web::error::ErrorInternalServerError(
+                    "Export failed",
+                )))
+                .await;
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"{}.{}\"",
+                table.name(),
+                format.extension()
+            ),
+        ))
+        .streaming(receiver))
+}
+
+async fn stream_export(
+    pool: &SqlitePool,
+    table: ExportTable,
+    filter: &ExportFilter,
+    mut writer: ExportWriter<Vec<u8>>,
+    sender: &mut mpsc::Sender<Result<web::Bytes, actix_web::Error>>,
+) -> Result<(), export::ExportError> {
+    let mut rows = export::rows(pool, table, filter);
+    let mut count = 0;
+
+    while let Some(row) = rows.next().await {
+        writer.write_row(row.map_err(export::ExportError::Sql)?)?;
+        count += 1;
+
+        if count % BATCH_SIZE == 0 {
+            let buffered = writer.take_buffered()?;
+            if !buffered.is_empty() && sender.send(Ok(buffered.into())).await.is_err() {
+                log::debug!("Export of {} cancelled by client", table.name());
+                return Ok(());
+            }
+        }
+    }
+
+    let remaining = writer.finish()?;
+    let _ = sender.send(Ok(remaining.into())).await;
+
+    Ok(())
+}
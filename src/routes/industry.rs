@@ -0,0 +1,394 @@
+use actix_web::{get, web, HttpResponse, Responder, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::localize::{LanguageQuery, LocalizedNames};
+use crate::{
+    ids::{RegionId, TypeId},
+    repository::{
+        Blueprint, IndustryActivity, IndustryRepository, MarketOrderRepository, NameKind,
+        Reprocessing, TranslationRepository,
+    },
+};
+
+#[get("/blueprints/{blueprint_id}")]
+pub async fn blueprint_details(
+    path: web::Path<TypeId>,
+    query: web::Query<LanguageQuery>,
+    industry_repository: web::Data<IndustryRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let blueprint = industry_repository
+        .blueprint(path.into_inner())
+        .await
+        .map_err(|e| {
+            log::error!("Could not read blueprint: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read blueprint")
+        })?;
+
+    let mut blueprint =
+        blueprint.ok_or_else(|| actix_web::error::ErrorNotFound("Unknown blueprint"))?;
+
+    let localized_names = LocalizedNames::load(
+        &translation_repository,
+        NameKind::Type,
+        query.lang.as_deref(),
+    )
+    .await?;
+    localize_blueprint(&localized_names, &mut blueprint);
+
+    Ok(HttpResponse::Ok().json(blueprint))
+}
+
+fn localize_blueprint(localized_names: &LocalizedNames, blueprint: &mut Blueprint) {
+    localized_names.localize_optional(blueprint.id.0, &mut blueprint.name);
+
+    for activity in blueprint.activities.iter_mut() {
+        for material in activity.materials.iter_mut() {
+            localized_names.localize_optional(material.type_id.0, &mut material.name);
+        }
+        for product in activity.products.iter_mut() {
+            localized_names.localize_optional(product.type_id.0, &mut product.name);
+        }
+        for skill in activity.skills.iter_mut() {
+            localized_names.localize_optional(skill.type_id.0, &mut skill.name);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ManufacturingQuery {
+    region: Option<RegionId>,
+    lang: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MaterialCost {
+    type_id: TypeId,
+    name: Option<String>,
+    quantity: i64,
+    unit_price: Option<f64>,
+    total_price: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct ManufacturingCost {
+    blueprint_id: TypeId,
+    product_id: TypeId,
+    product_quantity: i64,
+    /// Time for one run in seconds.
+    time: i64,
+    materials: Vec<MaterialCost>,
+    /// Cost of the materials that have a sell order in the region.
+    material_cost: f64,
+    /// False when some materials have no sell order in the region.
+    complete: bool,
+    product_price: Option<f64>,
+    product_value: Option<f64>,
+    profit: Option<f64>,
+    margin: Option<f64>,
+}
+
+/// Cost of one manufacturing run at base material efficiency, with materials
+/// bought and the product sold at the lowest sell price in the region.
+#[get("/items/{type_id}/manufacturing")]
+pub async fn item_manufacturing(
+    path: web::Path<TypeId>,
+    query: web::Query<ManufacturingQuery>,
+    industry_repository: web::Data<IndustryRepository>,
+    order_repository: web::Data<MarketOrderRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let product_id = path.into_inner();
+    let region_id = query.region.unwrap_or(RegionId(10000002));
+
+    let blueprint_id = industry_repository
+        .product_blueprint(product_id, IndustryActivity::Manufacturing)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read blueprint: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read blueprint")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Item is not manufactured"))?;
+
+    let mut blueprint = industry_repository
+        .blueprint(blueprint_id)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read blueprint: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read blueprint")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown blueprint"))?;
+
+    let localized_names = LocalizedNames::load(
+        &translation_repository,
+        NameKind::Type,
+        query.lang.as_deref(),
+    )
+    .await?;
+    localize_blueprint(&localized_names, &mut blueprint);
+
+    let activity = blueprint
+        .activity(IndustryActivity::Manufacturing)
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Item is not manufactured"))?;
+
+    let sell_prices = order_repository
+        .region_sell_prices(region_id)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read sell order prices: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read sell order prices")
+        })?;
+
+    let materials = activity
+        .materials
+        .iter()
+        .map(|material| {
+            let unit_price = sell_prices.get(&material.type_id).copied();
+
+            MaterialCost {
+                type_id: material.type_id,
+                name: material.name.clone(),
+                quantity: material.quantity,
+                unit_price,
+                total_price: unit_price.map(|price| price * material.quantity as f64),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let material_cost = materials.iter().filter_map(|m| m.total_price).sum::<f64>();
+    let complete = materials.iter().all(|m| m.total_price.is_some());
+
+    let product_quantity = activity
+        .products
+        .iter()
+        .find(|product| product.type_id == product_id)
+        .map(|product| product.quantity)
+        .unwrap_or(1);
+    let product_price = sell_prices.get(&product_id).copied();
+    let product_value = product_price.map(|price| price * product_quantity as f64);
+    let profit = product_value
+        .filter(|_| complete)
+        .map(|value| value - material_cost);
+
+    Ok(HttpResponse::Ok().json(ManufacturingCost {
+        blueprint_id,
+        product_id,
+        product_quantity,
+        time: activity.time,
+        materials,
+        material_cost,
+        complete,
+        product_price,
+        product_value,
+        profit,
+        margin: profit
+            .zip(product_value)
+            .filter(|(_, value)| *value > 0.0)
+            .map(|(profit, value)| profit / value),
+    }))
+}
+
+#[derive(Deserialize)]
+struct ReprocessingQuery {
+    region: Option<RegionId>,
+    /// Share of the materials that is recovered, 0.5 at a basic NPC station.
+    efficiency: Option<f64>,
+    max_min_volume: Option<usize>,
+    limit: Option<usize>,
+    lang: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ReprocessedMaterial {
+    type_id: TypeId,
+    name: Option<String>,
+    quantity: i64,
+    buy_price: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct ReprocessingValue {
+    type_id: TypeId,
+    name: String,
+    portion_size: i64,
+    /// Materials recovered from one portion after efficiency.
+    materials: Vec<ReprocessedMaterial>,
+    /// False when some materials have no buy order in the region.
+    complete: bool,
+    /// Value of the recovered materials per unit, sold to buy orders.
+    reprocess_value: f64,
+    /// Lowest sell price per unit.
+    sell_price: Option<f64>,
+    /// Reprocess value minus sell price, positive when buying and
+    /// reprocessing the item is worth it.
+    difference: Option<f64>,
+}
+
+fn reprocessing_value(
+    type_id: TypeId,
+    mut reprocessing: Reprocessing,
+    localized_names: &LocalizedNames,
+    efficiency: f64,
+    sell_prices: &HashMap<TypeId, f64>,
+    buy_prices: &HashMap<TypeId, f64>,
+) -> ReprocessingValue {
+    localized_names.localize(type_id.0, &mut reprocessing.name);
+
+    let materials = reprocessing
+        .materials
+        .into_iter()
+        .map(|mut material| {
+            localized_names.localize_optional(material.type_id.0, &mut material.name);
+
+            ReprocessedMaterial {
+                type_id: material.type_id,
+                name: material.name,
+                quantity: (material.quantity as f64 * efficiency).floor() as i64,
+                buy_price: buy_prices.get(&material.type_id).copied(),
+            }
+        })
+        .filter(|material| material.quantity > 0)
+        .collect::<Vec<_>>();
+
+    let complete = materials.iter().all(|m| m.buy_price.is_some());
+    let portion_value = materials
+        .iter()
+        .filter_map(|m| m.buy_price.map(|price| price * m.quantity as f64))
+        .sum::<f64>();
+    let reprocess_value = portion_value / reprocessing.portion_size.max(1) as f64;
+    let sell_price = sell_prices.get(&type_id).copied();
+
+    ReprocessingValue {
+        type_id,
+        name: reprocessing.name,
+        portion_size: reprocessing.portion_size,
+        materials,
+        complete,
+        reprocess_value,
+        sell_price,
+        difference: sell_price.map(|price| reprocess_value - price),
+    }
+}
+
+async fn reprocessing_prices(
+    order_repository: &MarketOrderRepository,
+    region_id: RegionId,
+    max_min_volume: usize,
+) -> Result<(HashMap<TypeId, f64>, HashMap<TypeId, f64>)> {
+    let sell_prices = order_repository
+        .region_sell_prices(region_id)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read sell order prices: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read sell order prices")
+        })?;
+
+    let buy_prices = order_repository
+        .region_buy_prices(region_id, max_min_volume)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read buy order prices: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read buy order prices")
+        })?;
+
+    Ok((sell_prices, buy_prices))
+}
+
+/// Value of the minerals an item reprocesses into, at the highest buy price in
+/// the region, against the lowest price the item itself sells for.
+#[get("/items/{type_id}/reprocessing")]
+pub async fn item_reprocessing(
+    path: web::Path<TypeId>,
+    query: web::Query<ReprocessingQuery>,
+    industry_repository: web::Data<IndustryRepository>,
+    order_repository: web::Data<MarketOrderRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let type_id = path.into_inner();
+    let region_id = query.region.unwrap_or(RegionId(10000002));
+    let efficiency = query.efficiency.unwrap_or(0.5).clamp(0.0, 1.0);
+
+    let reprocessing = industry_repository
+        .reprocessing(Some(type_id))
+        .await
+        .map_err(|e| {
+            log::error!("Could not read reprocessing materials: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read reprocessing materials")
+        })?
+        .remove(&type_id)
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Item can not be reprocessed"))?;
+
+    let (sell_prices, buy_prices) = reprocessing_prices(
+        &order_repository,
+        region_id,
+        query.max_min_volume.unwrap_or(1),
+    )
+    .await?;
+    let localized_names = LocalizedNames::load(
+        &translation_repository,
+        NameKind::Type,
+        query.lang.as_deref(),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(reprocessing_value(
+        type_id,
+        reprocessing,
+        &localized_names,
+        efficiency,
+        &sell_prices,
+        &buy_prices,
+    )))
+}
+
+/// Items that are worth more reprocessed than they sell for, best first.
+#[get("/reprocessing")]
+pub async fn reprocessing_table(
+    query: web::Query<ReprocessingQuery>,
+    industry_repository: web::Data<IndustryRepository>,
+    order_repository: web::Data<MarketOrderRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let region_id = query.region.unwrap_or(RegionId(10000002));
+    let efficiency = query.efficiency.unwrap_or(0.5).clamp(0.0, 1.0);
+    let limit = query.limit.unwrap_or(100).min(1000);
+
+    let reprocessing = industry_repository.reprocessing(None).await.map_err(|e| {
+        log::error!("Could not read reprocessing materials: {}", e);
+        actix_web::error::ErrorInternalServerError("Could not read reprocessing materials")
+    })?;
+
+    let (sell_prices, buy_prices) = reprocessing_prices(
+        &order_repository,
+        region_id,
+        query.max_min_volume.unwrap_or(1),
+    )
+    .await?;
+    let localized_names = LocalizedNames::load(
+        &translation_repository,
+        NameKind::Type,
+        query.lang.as_deref(),
+    )
+    .await?;
+
+    let mut values = reprocessing
+        .into_iter()
+        .map(|(type_id, reprocessing)| {
+            reprocessing_value(
+                type_id,
+                reprocessing,
+                &localized_names,
+                efficiency,
+                &sell_prices,
+                &buy_prices,
+            )
+        })
+        .filter(|value| value.difference.is_some_and(|difference| difference > 0.0))
+        .collect::<Vec<_>>();
+
+    values.sort_by(|a, b| b.difference.partial_cmp(&a.difference).unwrap());
+    values.truncate(limit);
+
+    Ok(HttpResponse::Ok().json(values))
+}
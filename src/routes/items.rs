@@ -0,0 +1,99 @@
+use actix_web::{get, web, HttpResponse, Responder, Result};
+use chrono::NaiveDate;
+use serde::Deserialize;
+
+use super::localize::LanguageQuery;
+use crate::{
+    ids::{RegionId, TypeId},
+    repository::{ItemRepository, MarketHistoryRepository, NameKind, TranslationRepository},
+};
+
+#[derive(Deserialize)]
+struct ItemSearchQuery {
+    search: String,
+    lang: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ItemHistoryQuery {
+    region: Option<RegionId>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+/// Items with a name containing `search` in any language, so names pasted
+/// from a German or Russian client are found as well.
+#[get("/items")]
+pub async fn item_search(
+    query: web::Query<ItemSearchQuery>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let lang = query.lang.as_deref().unwrap_or("en");
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    let items = translation_repository
+        .search_items(&query.search, lang, limit)
+        .await
+        .map_err(|e| {
+            log::error!("Could not search items: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not search items")
+        })?;
+
+    Ok(HttpResponse::Ok().json(items))
+}
+
+#[get("/items/{type_id}")]
+pub async fn item_details(
+    path: web::Path<TypeId>,
+    query: web::Query<LanguageQuery>,
+    item_repository: web::Data<ItemRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let type_id = path.into_inner();
+
+    let mut item = item_repository
+        .item(type_id)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read item: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read item")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown item"))?;
+
+    if let Some(lang) = query.lang.as_deref().filter(|lang| *lang != "en") {
+        let name = translation_repository
+            .name(NameKind::Type, type_id.0, lang)
+            .await
+            .map_err(|e| {
+                log::error!("Could not read translations: {}", e);
+                actix_web::error::ErrorInternalServerError("Could not read translations")
+            })?;
+
+        if let Some(name) = name {
+            item.name = name;
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(item))
+}
+
+#[get("/items/{type_id}/history")]
+pub async fn item_history(
+    path: web::Path<TypeId>,
+    query: web::Query<ItemHistoryQuery>,
+    history_repository: web::Data<MarketHistoryRepository>,
+) -> Result<impl Responder> {
+    let type_id = path.into_inner();
+    let region_id = query.region.unwrap_or(RegionId(10000002));
+
+    let history = history_repository
+        .item_history(type_id, region_id, query.from, query.to)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read item history: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read item history")
+        })?;
+
+    Ok(HttpResponse::Ok().json(history))
+}
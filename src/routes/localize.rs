@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use actix_web::Result;
+use serde::Deserialize;
+
+use crate::repository::{NameKind, TranslationRepository};
+
+#[derive(Deserialize)]
+pub(super) struct LanguageQuery {
+    pub lang: Option<String>,
+}
+
+/// Names in the requested language, by id. Empty for English since the SDE
+/// tables already hold the English names.
+pub(super) struct LocalizedNames(HashMap<i64, String>);
+
+impl LocalizedNames {
+    pub async fn load(
+        translation_repository: &TranslationRepository,
+        kind: NameKind,
+        lang: Option<&str>,
+    ) -> Result<Self> {
+        let lang = match lang {
+            Some(lang) if lang != "en" => lang,
+            _ => return Ok(Self(HashMap::new())),
+        };
+
+        let names = translation_repository
+            .names(kind, lang)
+            .await
+            .map_err(|e| {
+                log::error!("Could not read translations: {}", e);
+                actix_web::error::ErrorInternalServerError("Could not read translations")
+            })?;
+
+        Ok(Self(names))
+    }
+
+    /// Replaces `name` when there is a translation for `id`.
+    pub fn localize(&self, id: i64, name: &mut String) {
+        if let Some(localized) = self.0.get(&id) {
+            name.clone_from(localized);
+        }
+    }
+
+    pub fn localize_optional(&self, id: i64, name: &mut Option<String>) {
+        if let Some(localized) = self.0.get(&id) {
+            *name = Some(localized.clone());
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use actix_web::{get, web, HttpResponse, Responder, Result};
+use serde::{Deserialize, Serialize};
+
+use super::localize::{LanguageQuery, LocalizedNames};
+use crate::repository::{ItemRepository, MarketGroup, NameKind, TranslationRepository};
+
+#[derive(Deserialize)]
+struct MarketGroupsQuery {
+    /// Only count items in this category and drop branches without any.
+    category: Option<i64>,
+    /// Return the subtree below this market group instead of the whole tree.
+    root: Option<i64>,
+    lang: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MarketGroupNode {
+    #[serde(flatten)]
+    group: MarketGroup,
+    /// Published items in this group and all groups below it.
+    total_item_count: i64,
+    children: Vec<MarketGroupNode>,
+}
+
+#[get("/market-groups")]
+pub async fn market_group_tree(
+    query: web::Query<MarketGroupsQuery>,
+    item_repository: web::Data<ItemRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let mut groups = item_repository
+        .market_groups(query.category)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read market groups: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read market groups")
+        })?;
+
+    let localized_names = LocalizedNames::load(
+        &translation_repository,
+        NameKind::MarketGroup,
+        query.lang.as_deref(),
+    )
+    .await?;
+    groups
+        .iter_mut()
+        .for_each(|group| localized_names.localize(group.id, &mut group.name));
+
+    if let Some(root) = query.root {
+        if !groups.iter().any(|group| group.id == root) {
+            return Err(actix_web::error::ErrorNotFound("Unknown market group"));
+        }
+    }
+
+    let mut children = HashMap::<Option<i64>, Vec<MarketGroup>>::new();
+    for group in groups {
+        children.entry(group.parent_id).or_default().push(group);
+    }
+
+    let mut tree = build_tree(&mut children, None);
+    if let Some(root) = query.root {
+        tree = find_subtree(tree, root)
+            .map(|node| node.children)
+            .unwrap_or_default();
+    }
+    if query.category.is_some() {
+        prune_empty(&mut tree);
+    }
+
+    Ok(HttpResponse::Ok().json(tree))
+}
+
+#[get("/categories")]
+pub async fn categories(
+    query: web::Query<LanguageQuery>,
+    item_repository: web::Data<ItemRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let mut categories = item_repository.categories().await.map_err(|e| {
+        log::error!("Could not read categories: {}", e);
+        actix_web::error::ErrorInternalServerError("Could not read categories")
+    })?;
+
+    let localized_names = LocalizedNames::load(
+        &translation_repository,
+        NameKind::Category,
+        query.lang.as_deref(),
+    )
+    .await?;
+    categories
+        .iter_mut()
+        .for_each(|category| localized_names.localize(category.id, &mut category.name));
+
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+#[get("/meta-groups")]
+pub async fn meta_groups(
+    query: web::Query<LanguageQuery>,
+    item_repository: web::Data<ItemRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let mut meta_groups = item_repository.meta_groups().await.map_err(|e| {
+        log::error!("Could not read meta groups: {}", e);
+        actix_web::error::ErrorInternalServerError("Could not read meta groups")
+    })?;
+
+    let localized_names = LocalizedNames::load(
+        &translation_repository,
+        NameKind::MetaGroup,
+        query.lang.as_deref(),
+    )
+    .await?;
+    meta_groups
+        .iter_mut()
+        .for_each(|group| localized_names.localize(group.id, &mut group.name));
+
+    Ok(HttpResponse::Ok().json(meta_groups))
+}
+
+/// Builds the nodes below `parent`, groups are already sorted by name.
+fn build_tree(
+    children: &mut HashMap<Option<i64>, Vec<MarketGroup>>,
+    parent: Option<i64>,
+) -> Vec<MarketGroupNode> {
+    children
+        .remove(&parent)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|group| {
+            let nodes = build_tree(children, Some(group.id));
+            let total_item_count =
+                group.item_count + nodes.iter().map(|node| node.total_item_count).sum::<i64>();
+
+            MarketGroupNode {
+                group,
+                total_item_count,
+                children: nodes,
+            }
+        })
+        .collect()
+}
+
+fn find_subtree(nodes: Vec<MarketGroupNode>, id: i64) -> Option<MarketGroupNode> {
+    nodes.into_iter().find_map(|node| {
+        if node.group.id == id {
+            Some(node)
+        } else {
+            find_subtree(node.children, id)
+        }
+    })
+}
+
+fn prune_empty(nodes: &mut Vec<MarketGroupNode>) {
+    nodes.retain(|node| node.total_item_count > 0);
+    nodes
+        .iter_mut()
+        .for_each(|node| prune_empty(&mut node.children));
+}
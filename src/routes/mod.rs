@@ -5,30 +5,72 @@ use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 
+pub use admin::{
+    add_region, add_structure, admin_regions, admin_structures, pause_region, pause_structure,
+    refresh_region, remove_region, remove_structure, resume_region, resume_structure,
+};
+pub use events::{event_socket, event_stream};
+pub use export::export_table;
+pub use industry::{blueprint_details, item_manufacturing, item_reprocessing, reprocessing_table};
+pub use items::{item_details, item_history, item_search};
+pub use market::{categories, market_group_tree, meta_groups};
+pub use route::system_route;
+pub use stations::{station_details, station_prices, station_search};
+pub use status::{status_page, status_report, status_run, status_runs};
+
+mod admin;
+mod events;
+mod export;
+mod industry;
+mod items;
+mod localize;
+mod market;
+mod route;
+mod stations;
+mod status;
+
+use localize::LocalizedNames;
+
 use crate::{
-    cache::Cache,
-    repository::{MarketHistoryAverage, MarketHistoryRepository, MarketOrderRepository},
+    ids::{RegionId, TypeId},
+    repository::{
+        ItemFilter, ItemRepository, MarketHistoryAverage, MarketHistoryRepository,
+        MarketOrderRepository, NameKind, TranslationRepository,
+    },
 };
 
 #[derive(Deserialize)]
 struct MarginTableQuery {
-    region: Option<usize>,
+    region: Option<RegionId>,
     page: Option<usize>,
     margin: Option<f64>,
     min_volume: Option<usize>,
+    days: Option<usize>,
+    /// Only items in this market group or below it.
+    market_group: Option<i64>,
+    category: Option<i64>,
+    /// Tech I, Tech II, Faction, ... as in `/meta-groups`.
+    meta_group: Option<i64>,
+    tech_level: Option<i64>,
+    min_meta_level: Option<i64>,
+    max_meta_level: Option<i64>,
+    lang: Option<String>,
 }
 
 #[get("/")]
-async fn margin_table(
+pub async fn margin_table(
     query: web::Query<MarginTableQuery>,
     order_repository: web::Data<MarketOrderRepository>,
     history_repository: web::Data<MarketHistoryRepository>,
+    item_repository: web::Data<ItemRepository>,
+    translation_repository: web::Data<TranslationRepository>,
     sqlx_pool: web::Data<SqlitePool>,
 ) -> Result<impl Responder> {
-    let region_id = query.region.unwrap_or(10000002);
+    let region_id = query.region.unwrap_or(RegionId(10000002));
     let page = query.page.unwrap_or(0);
     let min_margin = query.margin.unwrap_or(0.2);
     let min_volume = query.min_volume.unwrap_or(3);
+    let days = query.days.unwrap_or(30);
 
     let page_size = 100;
     let start = page * page_size;
@@ -68,13 +110,18 @@ async fn margin_table(
     //         actix_web::error::ErrorInternalServerError("Could not read sell order prices")
     //     })?;
 
-    let averages = history_repository.averages().await.map_err(|e| {
-        log::error!("Could not read averages: {}", e);
-        actix_web::error::ErrorInternalServerError("Could not read averages")
-    })?;
+    let mut averages = history_repository
+        .averages(region_id, &[days, 7])
+        .await
+        .map_err(|e| {
+            log::error!("Could not read averages: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read averages")
+        })?;
+    let weekly_averages = averages.pop().unwrap_or_default();
+    let averages = averages.pop().unwrap_or_default();
 
-    let item_names = sqlx::query!("SELECT id, name FROM eve_items")
-        .map(|row| (row.id as usize, row.name))
+    let mut item_names = sqlx::query!("SELECT id, name FROM eve_items")
+        .map(|row| (TypeId(row.id), row.name))
         .fetch(sqlx_pool.get_ref())
         .try_collect::<HashMap<_, _>>()
         .await
@@ -83,6 +130,16 @@ async fn margin_table(
             actix_web::error::ErrorInternalServerError("Could not read item names")
         })?;
 
+    let localized_names = LocalizedNames::load(
+        &translation_repository,
+        NameKind::Type,
+        query.lang.as_deref(),
+    )
+    .await?;
+    item_names
+        .iter_mut()
+        .for_each(|(item_id, name)| localized_names.localize(item_id.0, name));
+
     let avg_days = 3;
 
     let buy_competition = order_repository
@@ -116,9 +173,37 @@ async fn margin_table(
     //         actix_web::error::ErrorInternalServerError("Could not read sell volume")
     //     })?;
 
+    let item_filter = ItemFilter {
+        market_group: query.market_group,
+        category: query.category,
+        meta_group: query.meta_group,
+        tech_level: query.tech_level,
+        min_meta_level: query.min_meta_level,
+        max_meta_level: query.max_meta_level,
+    };
+    let filter = if !item_filter.is_empty() {
+        let item_ids = item_repository
+            .filtered_item_ids(&item_filter)
+            .await
+            .map_err(|e| {
+                log::error!("Could not read filtered items: {}", e);
+                actix_web::error::ErrorInternalServerError("Could not read filtered items")
+            })?;
+        Some(item_ids)
+    } else {
+        None
+    };
+
     let mut items = Vec::new();
 
     for (item_id, average) in averages {
+        if filter
+            .as_ref()
+            .is_some_and(|filter| !filter.contains(&item_id))
+        {
+            continue;
+        }
+
         let name = item_names.get(&item_id).unwrap().clone();
 
         let buy_competition = *buy_competition.get(&item_id).unwrap_or(&0) / avg_days;
@@ -133,6 +218,7 @@ async fn margin_table(
             item_id,
             name,
             average,
+            weekly_averages.get(&item_id),
             buy_competition,
             sell_competition,
         ));
@@ -173,7 +259,7 @@ async fn margin_table(
 
     let end = if end > items.len() { items.len() } else { end };
     let start = if start >= items.len() {
-        items.len() - 1
+        items.len().saturating_sub(1)
     } else {
         start
     };
@@ -183,7 +269,7 @@ async fn margin_table(
 
 #[derive(Serialize)]
 struct MarginRowItem {
-    item_id: usize,
+    item_id: TypeId,
     name: String,
     buy_price: f64,
     sell_price: f64,
@@ -195,20 +281,27 @@ struct MarginRowItem {
     traded: f64,
     sell_competition: usize,
     buy_competition: usize,
+    weighted_price: f64,
+    median_price: f64,
+    stddev_price: f64,
+    history_days: i64,
+    weighted_price_7d: Option<f64>,
+    volume_7d: Option<usize>,
 }
 
 impl MarginRowItem {
     fn new(
-        item_id: usize,
+        item_id: TypeId,
         name: String,
         average: MarketHistoryAverage,
+        weekly_average: Option<&MarketHistoryAverage>,
         buy_competition: usize,
         sell_competition: usize,
     ) -> Self {
         let profit_per_item = (average.avg_high * 1.09) - (average.avg_low * 0.91);
         let spread = average.avg_high - average.avg_low;
 
-        let traded = average.avg_price as f64 * average.avg_volume as f64;
+        let traded = average.avg_price * average.avg_volume;
 
         let captureable = 0.3;
 
@@ -224,6 +317,12 @@ impl MarginRowItem {
             sell_competition,
             spread,
             traded,
+            weighted_price: average.weighted_price,
+            median_price: average.median_price,
+            stddev_price: average.stddev_price,
+            history_days: average.days,
+            weighted_price_7d: weekly_average.map(|a| a.weighted_price),
+            volume_7d: weekly_average.map(|a| a.avg_volume as usize),
             score: average.avg_volume / 2.0 * captureable * profit_per_item
                 / (buy_competition + sell_competition + 1) as f64,
         }
@@ -0,0 +1,114 @@
+use actix_web::{get, web, HttpResponse, Responder, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use super::localize::LocalizedNames;
+use crate::{
+    ids::SystemId,
+    repository::{NameKind, SolarSystem, SystemRepository, TranslationRepository},
+};
+
+#[derive(Deserialize)]
+struct RouteQuery {
+    from: SystemId,
+    to: SystemId,
+    /// Only route through high security systems.
+    safe: Option<bool>,
+    lang: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Route {
+    jumps: usize,
+    /// Straight line distance between the end points.
+    distance_ly: Option<f64>,
+    systems: Vec<SolarSystem>,
+}
+
+/// Shortest route over stargates between two systems.
+#[get("/route")]
+pub async fn system_route(
+    query: web::Query<RouteQuery>,
+    system_repository: web::Data<SystemRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let systems = system_repository.systems().await.map_err(|e| {
+        log::error!("Could not read systems: {}", e);
+        actix_web::error::ErrorInternalServerError("Could not read systems")
+    })?;
+
+    let stargates = system_repository.stargates().await.map_err(|e| {
+        log::error!("Could not read stargates: {}", e);
+        actix_web::error::ErrorInternalServerError("Could not read stargates")
+    })?;
+
+    let (from, to) = match (systems.get(&query.from), systems.get(&query.to)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Err(actix_web::error::ErrorNotFound("Unknown system")),
+    };
+
+    let safe = query.safe.unwrap_or(false);
+    let path = shortest_path(&stargates, from.id, to.id, |system_id| {
+        !safe || system_id == to.id || systems.get(&system_id).is_some_and(|s| s.is_highsec())
+    })
+    .ok_or_else(|| actix_web::error::ErrorNotFound("No route"))?;
+
+    let system_names = LocalizedNames::load(
+        &translation_repository,
+        NameKind::System,
+        query.lang.as_deref(),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(Route {
+        jumps: path.len() - 1,
+        distance_ly: from.distance_ly(to),
+        systems: path
+            .iter()
+            .filter_map(|system_id| systems.get(system_id).cloned())
+            .map(|mut system| {
+                system_names.localize(system.id.0, &mut system.name);
+                system
+            })
+            .collect(),
+    }))
+}
+
+/// Breadth first search over the stargate graph, only entering systems for
+/// which `allowed` returns true.
+fn shortest_path(
+    stargates: &[(SystemId, SystemId)],
+    from: SystemId,
+    to: SystemId,
+    allowed: impl Fn(SystemId) -> bool,
+) -> Option<Vec<SystemId>> {
+    let mut neighbours = HashMap::<SystemId, Vec<SystemId>>::new();
+    for (source, target) in stargates {
+        neighbours.entry(*source).or_default().push(*target);
+    }
+
+    let mut previous = HashMap::from([(from, from)]);
+    let mut queue = VecDeque::from([from]);
+
+    while let Some(system_id) = queue.pop_front() {
+        if system_id == to {
+            let mut path = vec![to];
+            let mut current = to;
+            while current != from {
+                current = previous[&current];
+                path.push(current);
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for next in neighbours.get(&system_id).into_iter().flatten() {
+            if !previous.contains_key(next) && allowed(*next) {
+                previous.insert(*next, system_id);
+                queue.push_back(*next);
+            }
+        }
+    }
+
+    None
+}
@@ -0,0 +1,153 @@
+use actix_web::{get, web, HttpResponse, Responder, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::localize::{LanguageQuery, LocalizedNames};
+use crate::{
+    ids::{RegionId, StationId, TypeId},
+    repository::{
+        MarketOrderRepository, NameKind, Station, StationRepository, TranslationRepository,
+    },
+};
+
+#[derive(Deserialize)]
+struct StationsQuery {
+    search: Option<String>,
+    region: Option<RegionId>,
+    lang: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Station and system names in the requested language, English when there is
+/// no translation.
+async fn localize_stations(
+    stations: &mut [Station],
+    lang: Option<&str>,
+    translation_repository: &TranslationRepository,
+) -> Result<()> {
+    let station_names =
+        LocalizedNames::load(translation_repository, NameKind::Station, lang).await?;
+    let system_names = LocalizedNames::load(translation_repository, NameKind::System, lang).await?;
+
+    for station in stations.iter_mut() {
+        station_names.localize(station.id.0, &mut station.name);
+        system_names.localize(station.system_id.0, &mut station.system_name);
+    }
+
+    Ok(())
+}
+
+/// Stations with a name containing `search` in any language.
+#[get("/stations")]
+pub async fn station_search(
+    query: web::Query<StationsQuery>,
+    station_repository: web::Data<StationRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let query = query.into_inner();
+    let limit = query.limit.unwrap_or(50).min(500);
+
+    let mut stations = station_repository
+        .search(query.search, query.region, limit)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read stations: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read stations")
+        })?;
+
+    localize_stations(
+        &mut stations,
+        query.lang.as_deref(),
+        &translation_repository,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(stations))
+}
+
+#[get("/stations/{station_id}")]
+pub async fn station_details(
+    path: web::Path<StationId>,
+    query: web::Query<LanguageQuery>,
+    station_repository: web::Data<StationRepository>,
+    translation_repository: web::Data<TranslationRepository>,
+) -> Result<impl Responder> {
+    let mut station = station_repository
+        .station(path.into_inner())
+        .await
+        .map_err(|e| {
+            log::error!("Could not read station: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read station")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown station"))?;
+
+    localize_stations(
+        std::slice::from_mut(&mut station),
+        query.lang.as_deref(),
+        &translation_repository,
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(station))
+}
+
+#[derive(Deserialize)]
+struct StationPricesQuery {
+    max_min_volume: Option<usize>,
+}
+
+#[derive(Serialize, Default)]
+struct StationPrice {
+    sell_price: Option<f64>,
+    buy_price: Option<f64>,
+}
+
+/// Lowest sell and highest buy price per item for a single station, e.g. Jita
+/// 4-4. Buy orders count when their range reaches the station.
+#[get("/stations/{station_id}/prices")]
+pub async fn station_prices(
+    path: web::Path<StationId>,
+    query: web::Query<StationPricesQuery>,
+    station_repository: web::Data<StationRepository>,
+    order_repository: web::Data<MarketOrderRepository>,
+) -> Result<impl Responder> {
+    let station_id = path.into_inner();
+    let max_min_volume = query.max_min_volume.unwrap_or(1);
+
+    let station = station_repository
+        .station(station_id)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read station: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read station")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown station"))?;
+
+    let sell_prices = order_repository
+        .station_sell_prices(station_id)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read station sell prices: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read station sell prices")
+        })?;
+
+    let buy_prices = order_repository
+        .station_buy_prices(station.system_id, station_id, max_min_volume)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read station buy prices: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read station buy prices")
+        })?;
+
+    let mut prices = BTreeMap::<TypeId, StationPrice>::new();
+
+    for (item_id, price) in sell_prices {
+        prices.entry(item_id).or_default().sell_price = Some(price);
+    }
+
+    for (item_id, price) in buy_prices {
+        prices.entry(item_id).or_default().buy_price = Some(price);
+    }
+
+    Ok(HttpResponse::Ok().json(prices))
+}
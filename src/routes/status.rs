@@ -0,0 +1,239 @@
+use std::{collections::HashMap, fmt::Write};
+
+use actix::Addr;
+use actix_web::{get, web, HttpResponse, Responder, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actors::{CircuitState, CollectorState, GetStatusReport, RegionManager, StatusReport},
+    config::AppConfig,
+    ids::RegionId,
+    repository::{TrackedRegion, TrackedRegionRepository, UpdateLogRepository},
+};
+
+#[derive(Serialize)]
+struct Status {
+    regions: Vec<TrackedRegion>,
+    /// Configured regions that are not tracked. The configuration only fills
+    /// the tracked regions on the first start, later they come from the
+    /// admin API.
+    ignored_config_regions: Vec<RegionId>,
+    #[serde(flatten)]
+    report: StatusReport,
+}
+
+#[derive(Deserialize)]
+struct RunsQuery {
+    region: Option<RegionId>,
+    kind: Option<String>,
+    limit: Option<usize>,
+}
+
+#[get("/status/runs")]
+pub async fn status_runs(
+    query: web::Query<RunsQuery>,
+    update_log_repository: web::Data<UpdateLogRepository>,
+) -> Result<impl Responder> {
+    let query = query.into_inner();
+    let limit = query.limit.unwrap_or(50).min(500);
+
+    let runs = update_log_repository
+        .recent_runs(query.region, query.kind, limit)
+        .await
+        .map_err(|e| {
+            log::error!("Could not read update log: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read update log")
+        })?;
+
+    Ok(HttpResponse::Ok().json(runs))
+}
+
+#[get("/status/runs/{run_id}")]
+pub async fn status_run(
+    path: web::Path<i64>,
+    update_log_repository: web::Data<UpdateLogRepository>,
+) -> Result<impl Responder> {
+    let run = update_log_repository
+        .run(path.into_inner())
+        .await
+        .map_err(|e| {
+            log::error!("Could not read update log: {}", e);
+            actix_web::error::ErrorInternalServerError("Could not read update log")
+        })?
+        .ok_or_else(|| actix_web::error::ErrorNotFound("Unknown run"))?;
+
+    Ok(HttpResponse::Ok().json(run))
+}
+
+async fn load_status(
+    region_manager: &Addr<RegionManager>,
+    tracked_region_repository: &TrackedRegionRepository,
+    config: &AppConfig,
+) -> Result<Status> {
+    let regions = tracked_region_repository.all().await.map_err(|e| {
+        log::error!("Could not read tracked regions: {}", e);
+        actix_web::error::ErrorInternalServerError("Could not read tracked regions")
+    })?;
+
+    let report = region_manager.send(GetStatusReport).await.map_err(|e| {
+        log::error!("Could not read collector status: {}", e);
+        actix_web::error::ErrorInternalServerError("Could not read collector status")
+    })?;
+
+    let ignored_config_regions = config
+        .regions()
+        .into_iter()
+        .filter(|region_id| !regions.iter().any(|region| region.region_id == *region_id))
+        .collect();
+
+    Ok(Status {
+        regions,
+        ignored_config_regions,
+        report,
+    })
+}
+
+/// Tracked regions, schedulers and what every collector is doing.
+#[get("/status")]
+pub async fn status_report(
+    region_manager: web::Data<Addr<RegionManager>>,
+    tracked_region_repository: web::Data<TrackedRegionRepository>,
+    config: web::Data<AppConfig>,
+) -> Result<impl Responder> {
+    let status = load_status(&region_manager, &tracked_region_repository, &config).await?;
+
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// `/status` as a page that refreshes itself.
+#[get("/status/page")]
+pub async fn status_page(
+    region_manager: web::Data<Addr<RegionManager>>,
+    tracked_region_repository: web::Data<TrackedRegionRepository>,
+    config: web::Data<AppConfig>,
+) -> Result<impl Responder> {
+    let status = load_status(&region_manager, &tracked_region_repository, &config).await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(render_status(&status)))
+}
+
+fn render_status(status: &Status) -> String {
+    let names = status
+        .regions
+        .iter()
+        .map(|region| (region.region_id, region.name.as_str()))
+        .collect::<HashMap<_, _>>();
+    let region = |region_id: RegionId| match names.get(&region_id) {
+        Some(name) => format!("{} ({})", escape(name), region_id),
+        None => region_id.to_string(),
+    };
+    let time = |time: Option<DateTime<Utc>>| match time {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => "-".to_string(),
+    };
+
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta http-equiv=\"refresh\" content=\"10\">\n<title>Noice status</title>\n<style>body { font-family: sans-serif; } table { border-collapse: collapse; } th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; } .running { color: #06c; } .error { color: #c00; }</style>\n</head>\n<body>\n",
+    );
+
+    html.push_str("<h1>Collectors</h1>\n<table>\n<tr><th>Region</th><th>Kind</th><th>State</th><th>Progress</th><th>Started</th><th>Last success</th><th>Last error</th><th>Retry</th><th>Circuit</th></tr>\n");
+    for collector in status.report.collectors.iter() {
+        let (class, state) = match collector.state {
+            CollectorState::Idle => ("idle", "idle"),
+            CollectorState::Running => ("running", "running"),
+        };
+        let error = match &collector.last_error {
+            Some(error) => format!(
+                "<span class=\"error\">{}</span> {}",
+                time(collector.last_error_at),
+                escape(error)
+            ),
+            None => "-".to_string(),
+        };
+        let supervision = &collector.supervision;
+        let retry = match supervision.retry_at {
+            Some(at) => format!("{} at {}", supervision.retries, time(Some(at))),
+            None => "-".to_string(),
+        };
+        let circuit = match supervision.circuit {
+            CircuitState::Closed => "closed".to_string(),
+            CircuitState::Open => format!(
+                "<span class=\"error\">open</span> until {}",
+                time(supervision.circuit_open_until)
+            ),
+            CircuitState::HalfOpen => "half-open".to_string(),
+        };
+
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            region(collector.region_id),
+            collector.kind.as_str(),
+            class,
+            state,
+            escape(collector.progress.as_deref().unwrap_or("-")),
+            time(collector.started),
+            time(collector.last_success),
+            error,
+            retry,
+            circuit
+        );
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h1>Schedulers</h1>\n<table>\n<tr><th>Kind</th><th>Schedule</th><th>Next run</th><th>Regions</th></tr>\n");
+    for scheduler in status.report.schedulers.iter() {
+        let regions = scheduler
+            .regions
+            .iter()
+            .map(|region_id| region(*region_id))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            scheduler.kind.as_str(),
+            escape(&scheduler.schedule),
+            time(scheduler.next_run),
+            regions
+        );
+    }
+    html.push_str("</table>\n");
+
+    let paused = status
+        .regions
+        .iter()
+        .filter(|tracked| tracked.paused)
+        .map(|tracked| region(tracked.region_id))
+        .collect::<Vec<_>>();
+    if !paused.is_empty() {
+        let _ = writeln!(html, "<p>Paused: {}</p>", paused.join(", "));
+    }
+
+    if !status.ignored_config_regions.is_empty() {
+        let ignored = status
+            .ignored_config_regions
+            .iter()
+            .map(|region_id| region_id.to_string())
+            .collect::<Vec<_>>();
+        let _ = writeln!(
+            html,
+            "<p>Configured but not tracked: {}. The configured regions are only used on the first start, track them through the admin API.</p>",
+            ignored.join(", ")
+        );
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
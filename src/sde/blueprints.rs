@@ -0,0 +1,59 @@
+use crate::ids::TypeId;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// A blueprint from `fsd/blueprints.yaml`. Activities are keyed by their SDE
+/// name: `manufacturing`, `copying`, `invention`, `research_material`,
+/// `research_time` and `reaction`.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SdeBlueprint {
+    #[serde(default)]
+    pub max_production_limit: i64,
+    pub activities: BTreeMap<String, SdeActivity>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct SdeActivity {
+    #[serde(default)]
+    pub time: i64,
+    #[serde(default)]
+    pub materials: Vec<SdeMaterial>,
+    #[serde(default)]
+    pub products: Vec<SdeProduct>,
+    #[serde(default)]
+    pub skills: Vec<SdeSkill>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct SdeMaterial {
+    #[serde(rename = "typeID")]
+    pub type_id: TypeId,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct SdeProduct {
+    #[serde(rename = "typeID")]
+    pub type_id: TypeId,
+    pub quantity: i64,
+    /// Chance of success, only set for invention.
+    pub probability: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct SdeSkill {
+    #[serde(rename = "typeID")]
+    pub type_id: TypeId,
+    pub level: i64,
+}
+
+impl SdeActivity {
+    /// Sorts the lists so activities compare equal to the ones read back from
+    /// the database.
+    pub fn normalize(&mut self) {
+        self.materials.sort_by_key(|material| material.type_id);
+        self.products.sort_by_key(|product| product.type_id);
+        self.skills.sort_by_key(|skill| skill.type_id);
+    }
+}
@@ -0,0 +1,548 @@
+use super::{Sde, SdeError};
+use crate::ids::{SystemId, TypeId};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    hash::Hash,
+};
+
+/// What changes between the imported tables and a new SDE, computed before
+/// importing it.
+#[derive(Debug, Default, Serialize)]
+pub struct SdeDiff {
+    pub types: TypeChanges,
+    pub market_groups: MarketGroupChanges,
+    pub systems: NamedChanges<SystemId>,
+    pub stargates: StargateChanges,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Named<K> {
+    pub id: K,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Renamed<K> {
+    pub id: K,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// A type that changed market group or a market group that changed parent.
+#[derive(Debug, Serialize)]
+pub struct Moved<K> {
+    pub id: K,
+    pub name: String,
+    pub from: Option<Named<i64>>,
+    pub to: Option<Named<i64>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NamedChanges<K> {
+    pub added: Vec<Named<K>>,
+    pub removed: Vec<Named<K>>,
+    pub renamed: Vec<Renamed<K>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct TypeChanges {
+    #[serde(flatten)]
+    pub names: NamedChanges<TypeId>,
+    pub published: Vec<Named<TypeId>>,
+    pub unpublished: Vec<Named<TypeId>>,
+    pub moved: Vec<Moved<TypeId>>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MarketGroupChanges {
+    #[serde(flatten)]
+    pub names: NamedChanges<i64>,
+    pub moved: Vec<Moved<i64>>,
+}
+
+/// Stargates are listed once per connection, from the system with the lower
+/// id.
+#[derive(Debug, Default, Serialize)]
+pub struct StargateChanges {
+    pub added: Vec<Stargate>,
+    pub removed: Vec<Stargate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Stargate {
+    pub from: Named<SystemId>,
+    pub to: Named<SystemId>,
+}
+
+impl<K> Default for NamedChanges<K> {
+    fn default() -> Self {
+        Self {
+            added: Vec::new(),
+            removed: Vec::new(),
+            renamed: Vec::new(),
+        }
+    }
+}
+
+impl<K> NamedChanges<K> {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+}
+
+impl SdeDiff {
+    pub fn is_empty(&self) -> bool {
+        self.types.names.is_empty()
+            && self.types.published.is_empty()
+            && self.types.unpublished.is_empty()
+            && self.types.moved.is_empty()
+            && self.market_groups.names.is_empty()
+            && self.market_groups.moved.is_empty()
+            && self.systems.is_empty()
+            && self.stargates.added.is_empty()
+            && self.stargates.removed.is_empty()
+    }
+}
+
+/// Compares the SDE tables in the database with `sde`.
+pub async fn diff_sde(pool: &SqlitePool, sde: &Sde) -> Result<SdeDiff, SdeError> {
+    let market_groups = sqlx::query!("SELECT id, name, parent_id FROM eve_market_groups")
+        .fetch_all(pool)
+        .await
+        .map_err(SdeError::Sql)?;
+    let types = sqlx::query!(
+        r#"SELECT id as "id: TypeId", name, published as "published: bool", market_group_id FROM eve_items"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(SdeError::Sql)?;
+    let systems = sqlx::query!(r#"SELECT id as "id: SystemId", name FROM eve_system"#)
+        .fetch_all(pool)
+        .await
+        .map_err(SdeError::Sql)?;
+    let stargates = sqlx::query!(
+        r#"SELECT source_system_id as "source: SystemId", target_system_id as "target: SystemId" FROM eve_stargates"#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(SdeError::Sql)?;
+
+    let old_market_group_names = market_groups
+        .iter()
+        .map(|group| (group.id, group.name.clone()))
+        .collect::<HashMap<_, _>>();
+    let new_market_group_names = sde
+        .market_groups
+        .iter()
+        .map(|(id, group)| (*id, group.name.en.clone()))
+        .collect::<HashMap<_, _>>();
+    let market_group = |id: Option<i64>| {
+        id.map(|id| Named {
+            id,
+            name: new_market_group_names
+                .get(&id)
+                .or_else(|| old_market_group_names.get(&id))
+                .cloned()
+                .unwrap_or_default(),
+        })
+    };
+
+    let mut diff = SdeDiff::default();
+
+    diff.market_groups.names = named_changes(&old_market_group_names, &new_market_group_names);
+    for old in market_groups.iter() {
+        if let Some(new) = sde.market_groups.get(&old.id) {
+            if old.parent_id != new.parent_group_id {
+                diff.market_groups.moved.push(Moved {
+                    id: old.id,
+                    name: new.name.en.clone(),
+                    from: market_group(old.parent_id),
+                    to: market_group(new.parent_group_id),
+                });
+            }
+        }
+    }
+    diff.market_groups.moved.sort_by_key(|moved| moved.id);
+
+    let old_type_names = types
+        .iter()
+        .map(|eve_type| (eve_type.id, eve_type.name.clone()))
+        .collect::<HashMap<_, _>>();
+    let new_type_names = sde
+        .types
+        .iter()
+        .map(|(id, eve_type)| (*id, eve_type.name.en.clone()))
+        .collect::<HashMap<_, _>>();
+
+    diff.types.names = named_changes(&old_type_names, &new_type_names);
+    for old in types.iter() {
+        let Some(new) = sde.types.get(&old.id) else {
+            continue;
+        };
+        let named = || Named {
+            id: old.id,
+            name: new.name.en.clone(),
+        };
+
+        match (old.published, new.published) {
+            (false, true) => diff.types.published.push(named()),
+            (true, false) => diff.types.unpublished.push(named()),
+            _ => {}
+        }
+
+        if old.market_group_id != new.market_group_id {
+            diff.types.moved.push(Moved {
+                id: old.id,
+                name: new.name.en.clone(),
+                from: market_group(old.market_group_id),
+                to: market_group(new.market_group_id),
+            });
+        }
+    }
+    diff.types.published.sort_by_key(|named| named.id);
+    diff.types.unpublished.sort_by_key(|named| named.id);
+    diff.types.moved.sort_by_key(|moved| moved.id);
+
+    let old_system_names = systems
+        .into_iter()
+        .map(|system| (system.id, system.name))
+        .collect::<HashMap<_, _>>();
+    let new_system_names = sde
+        .systems
+        .values()
+        .map(|system| (system.id, system.name.clone()))
+        .collect::<HashMap<_, _>>();
+    diff.systems = named_changes(&old_system_names, &new_system_names);
+
+    let system = |id: SystemId| Named {
+        id,
+        name: new_system_names
+            .get(&id)
+            .or_else(|| old_system_names.get(&id))
+            .cloned()
+            .unwrap_or_default(),
+    };
+    let old_stargates = stargates
+        .into_iter()
+        .map(|stargate| connection(stargate.source, stargate.target))
+        .collect::<HashSet<_>>();
+    let new_stargates = sde
+        .stargates()
+        .into_iter()
+        .map(|(source, target)| connection(source, target))
+        .collect::<HashSet<_>>();
+
+    let mut added = new_stargates.difference(&old_stargates).collect::<Vec<_>>();
+    added.sort();
+    diff.stargates.added = added
+        .into_iter()
+        .map(|(from, to)| Stargate {
+            from: system(*from),
+            to: system(*to),
+        })
+        .collect();
+
+    let mut removed = old_stargates.difference(&new_stargates).collect::<Vec<_>>();
+    removed.sort();
+    diff.stargates.removed = removed
+        .into_iter()
+        .map(|(from, to)| Stargate {
+            from: system(*from),
+            to: system(*to),
+        })
+        .collect();
+
+    Ok(diff)
+}
+
+fn connection(a: SystemId, b: SystemId) -> (SystemId, SystemId) {
+    if a < b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn named_changes<K: Copy + Eq + Hash + Ord>(
+    old: &HashMap<K, String>,
+    new: &HashMap<K, String>,
+) -> NamedChanges<K> {
+    let mut changes = NamedChanges::default();
+
+    for (id, name) in new.iter() {
+        match old.get(id) {
+            None => changes.added.push(Named {
+                id: *id,
+                name: name.clone(),
+            }),
+            Some(old_name) if old_name != name => changes.renamed.push(Renamed {
+                id: *id,
+                old_name: old_name.clone(),
+                new_name: name.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for (id, name) in old.iter() {
+        if !new.contains_key(id) {
+            changes.removed.push(Named {
+                id: *id,
+                name: name.clone(),
+            });
+        }
+    }
+
+    changes.added.sort_by_key(|named| named.id);
+    changes.removed.sort_by_key(|named| named.id);
+    changes.renamed.sort_by_key(|renamed| renamed.id);
+
+    changes
+}
+
+impl<K: Display> Display for Named<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.id)
+    }
+}
+
+impl<K: Display> Display for Moved<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let group = |group: &Option<Named<i64>>| match group {
+            Some(group) => group.to_string(),
+            None => "none".to_string(),
+        };
+
+        write!(
+            f,
+            "{} ({}): {} -> {}",
+            self.name,
+            self.id,
+            group(&self.from),
+            group(&self.to)
+        )
+    }
+}
+
+impl<K: Display> NamedChanges<K> {
+    fn write(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for named in self.added.iter() {
+            writeln!(f, "  + {}", named)?;
+        }
+        for named in self.removed.iter() {
+            writeln!(f, "  - {}", named)?;
+        }
+        for renamed in self.renamed.iter() {
+            writeln!(
+                f,
+                "  ~ {} -> {} ({})",
+                renamed.old_name, renamed.new_name, renamed.id
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} added, {} removed, {} renamed",
+            self.added.len(),
+            self.removed.len(),
+            self.renamed.len()
+        )
+    }
+}
+
+impl Display for SdeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+
+        writeln!(
+            f,
+            "Types: {}, {} published, {} unpublished, {} moved",
+            self.types.names.summary(),
+            self.types.published.len(),
+            self.types.unpublished.len(),
+            self.types.moved.len()
+        )?;
+        self.types.names.write(f)?;
+        for named in self.types.published.iter() {
+            writeln!(f, "  published {}", named)?;
+        }
+        for named in self.types.unpublished.iter() {
+            writeln!(f, "  unpublished {}", named)?;
+        }
+        for moved in self.types.moved.iter() {
+            writeln!(f, "  moved {}", moved)?;
+        }
+
+        writeln!(
+            f,
+            "Market groups: {}, {} moved",
+            self.market_groups.names.summary(),
+            self.market_groups.moved.len()
+        )?;
+        self.market_groups.names.write(f)?;
+        for moved in self.market_groups.moved.iter() {
+            writeln!(f, "  moved {}", moved)?;
+        }
+
+        writeln!(f, "Systems: {}", self.systems.summary())?;
+        self.systems.write(f)?;
+
+        writeln!(
+            f,
+            "Stargates: {} added, {} removed",
+            self.stargates.added.len(),
+            self.stargates.removed.len()
+        )?;
+        for stargate in self.stargates.added.iter() {
+            writeln!(f, "  + {} <-> {}", stargate.from, stargate.to)?;
+        }
+        for stargate in self.stargates.removed.iter() {
+            writeln!(f, "  - {} <-> {}", stargate.from, stargate.to)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sde::{
+        fixtures::{self, JITA, NEW_CALDARI, PERIMETER, RIFTER, TRITANIUM},
+        import_sde,
+    };
+
+    #[tokio::test]
+    async fn same_sde_has_no_changes() {
+        let pool = fixtures::memory_pool().await;
+        let sde = fixtures::sde();
+        import_sde(&pool, &sde).await.unwrap();
+
+        let diff = diff_sde(&pool, &sde).await.unwrap();
+
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "No changes\n");
+    }
+
+    #[tokio::test]
+    async fn lists_type_changes() {
+        let pool = fixtures::memory_pool().await;
+        let mut sde = fixtures::sde();
+        import_sde(&pool, &sde).await.unwrap();
+
+        sde.types.get_mut(&TRITANIUM).unwrap().name = fixtures::translation("Tritanium Ore");
+        sde.types.get_mut(&TRITANIUM).unwrap().published = false;
+        sde.types.get_mut(&RIFTER).unwrap().market_group_id = Some(1857);
+        sde.types.insert(
+            TypeId(35),
+            fixtures::eve_type("Pyerite", 18, Some(1857), Some(0.01)),
+        );
+
+        let diff = diff_sde(&pool, &sde).await.unwrap();
+
+        let added = diff.types.names.added.iter().map(|named| named.id);
+        assert_eq!(added.collect::<Vec<_>>(), vec![TypeId(35)]);
+        assert!(diff.types.names.removed.is_empty());
+        assert_eq!(diff.types.names.renamed.len(), 1);
+        assert_eq!(diff.types.names.renamed[0].old_name, "Tritanium");
+        assert_eq!(diff.types.names.renamed[0].new_name, "Tritanium Ore");
+        assert!(diff.types.published.is_empty());
+        assert_eq!(diff.types.unpublished.len(), 1);
+        assert_eq!(diff.types.unpublished[0].id, TRITANIUM);
+
+        assert_eq!(diff.types.moved.len(), 1);
+        let moved = &diff.types.moved[0];
+        assert_eq!(moved.id, RIFTER);
+        assert_eq!(moved.from.as_ref().map(|group| group.id), Some(61));
+        assert_eq!(
+            moved.to.as_ref().map(|group| group.name.as_str()),
+            Some("Minerals")
+        );
+    }
+
+    #[tokio::test]
+    async fn lists_market_group_changes() {
+        let pool = fixtures::memory_pool().await;
+        let mut sde = fixtures::sde();
+        import_sde(&pool, &sde).await.unwrap();
+
+        sde.market_groups.get_mut(&61).unwrap().parent_group_id = None;
+        sde.market_groups.get_mut(&4).unwrap().name = fixtures::translation("Spaceships");
+
+        let diff = diff_sde(&pool, &sde).await.unwrap();
+
+        assert_eq!(diff.market_groups.names.renamed.len(), 1);
+        assert_eq!(diff.market_groups.names.renamed[0].id, 4);
+        assert_eq!(diff.market_groups.moved.len(), 1);
+        let moved = &diff.market_groups.moved[0];
+        assert_eq!(moved.id, 61);
+        assert_eq!(
+            moved.from.as_ref().map(|group| group.name.as_str()),
+            Some("Spaceships")
+        );
+        assert!(moved.to.is_none());
+        assert!(diff.types.moved.is_empty());
+    }
+
+    #[tokio::test]
+    async fn lists_systems_and_stargates_once_per_connection() {
+        let pool = fixtures::memory_pool().await;
+        let mut sde = fixtures::sde();
+        import_sde(&pool, &sde).await.unwrap();
+
+        // Perimeter is now only connected to New Caldari.
+        sde.systems.get_mut(&JITA).unwrap().stargates.clear();
+        sde.systems.get_mut(&PERIMETER).unwrap().stargates = vec![(50001250, 50001251)];
+        sde.systems.insert(
+            NEW_CALDARI,
+            fixtures::system(NEW_CALDARI, "New Caldari", vec![(50001251, 50001250)]),
+        );
+
+        let diff = diff_sde(&pool, &sde).await.unwrap();
+
+        let added = diff.systems.added.iter().map(|named| named.id);
+        assert_eq!(added.collect::<Vec<_>>(), vec![NEW_CALDARI]);
+        assert!(diff.systems.removed.is_empty());
+
+        let connections = |stargates: &[Stargate]| {
+            stargates
+                .iter()
+                .map(|stargate| (stargate.from.id, stargate.to.id))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            connections(&diff.stargates.added),
+            vec![(PERIMETER, NEW_CALDARI)]
+        );
+        assert_eq!(
+            connections(&diff.stargates.removed),
+            vec![(JITA, PERIMETER)]
+        );
+        assert_eq!(diff.stargates.removed[0].from.name, "Jita");
+    }
+
+    #[test]
+    fn named_changes_are_sorted_by_id() {
+        let old = HashMap::from([(3, "c".to_string()), (1, "a".to_string())]);
+        let new = HashMap::from([
+            (2, "b".to_string()),
+            (3, "C".to_string()),
+            (4, "d".to_string()),
+        ]);
+
+        let changes = named_changes(&old, &new);
+
+        let ids = |named: &[Named<i64>]| named.iter().map(|named| named.id).collect::<Vec<_>>();
+        assert_eq!(ids(&changes.added), vec![2, 4]);
+        assert_eq!(ids(&changes.removed), vec![1]);
+        assert_eq!(changes.renamed[0].new_name, "C");
+    }
+}
@@ -0,0 +1,157 @@
+//! A small SDE and an in-memory database for the tests of the importer and
+//! the diff.
+
+use super::{
+    EveCategory, EveGroup, EveMarketGroup, EveMetaGroup, EveType, Sde, SdeConstellation, SdeSystem,
+    Translation,
+};
+use crate::ids::{ConstellationId, RegionId, SystemId, TypeId};
+use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+use std::collections::{BTreeMap, HashMap};
+
+pub const JITA: SystemId = SystemId(30000142);
+pub const PERIMETER: SystemId = SystemId(30000144);
+pub const NEW_CALDARI: SystemId = SystemId(30000145);
+pub const TRITANIUM: TypeId = TypeId(34);
+pub const RIFTER: TypeId = TypeId(587);
+
+/// A database with every migration applied. A single connection that is
+/// never closed, every connection to `sqlite::memory:` is its own database.
+pub async fn memory_pool() -> SqlitePool {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!().run(&pool).await.unwrap();
+    pool
+}
+
+pub fn translation(en: &str) -> Translation {
+    Translation {
+        en: en.to_string(),
+        other: BTreeMap::new(),
+    }
+}
+
+pub fn eve_type(
+    name: &str,
+    group_id: i64,
+    market_group_id: Option<i64>,
+    volume: Option<f64>,
+) -> EveType {
+    EveType {
+        name: translation(name),
+        group_id,
+        published: true,
+        market_group_id,
+        volume,
+        portion_size: 1,
+        base_price: None,
+        mass: None,
+        meta_group_id: None,
+        meta_level: None,
+        tech_level: None,
+    }
+}
+
+pub fn market_group(name: &str, parent_group_id: Option<i64>) -> EveMarketGroup {
+    EveMarketGroup {
+        name: translation(name),
+        parent_group_id,
+        description: None,
+        has_types: parent_group_id.is_some(),
+        icon_id: None,
+    }
+}
+
+/// `stargates` as (gate, destination gate).
+pub fn system(id: SystemId, name: &str, stargates: Vec<(i64, i64)>) -> SdeSystem {
+    SdeSystem {
+        id,
+        name: name.to_string(),
+        region_id: RegionId(10000002),
+        constellation_id: ConstellationId(20000020),
+        security: 0.9459,
+        security_class: None,
+        position: [0.0, 0.0, 0.0],
+        stargates,
+    }
+}
+
+/// Tritanium and a Rifter, and Jita connected to Perimeter.
+pub fn sde() -> Sde {
+    Sde {
+        categories: HashMap::from([
+            (
+                4,
+                EveCategory {
+                    name: translation("Material"),
+                    published: true,
+                },
+            ),
+            (
+                6,
+                EveCategory {
+                    name: translation("Ship"),
+                    published: true,
+                },
+            ),
+        ]),
+        market_groups: HashMap::from([
+            (4, market_group("Ships", None)),
+            (61, market_group("Frigates", Some(4))),
+            (475, market_group("Manufacture & Research", None)),
+            (1857, market_group("Minerals", Some(475))),
+        ]),
+        meta_groups: HashMap::from([(
+            1,
+            EveMetaGroup {
+                name: translation("Tech I"),
+            },
+        )]),
+        groups: HashMap::from([
+            (
+                18,
+                EveGroup {
+                    name: translation("Mineral"),
+                    category_id: 4,
+                    published: true,
+                },
+            ),
+            (
+                25,
+                EveGroup {
+                    name: translation("Frigate"),
+                    category_id: 6,
+                    published: true,
+                },
+            ),
+        ]),
+        types: HashMap::from([
+            (TRITANIUM, eve_type("Tritanium", 18, Some(1857), Some(0.01))),
+            (RIFTER, eve_type("Rifter", 25, Some(61), Some(27_289.0))),
+        ]),
+        regions: HashMap::from([(RegionId(10000002), "The Forge".to_string())]),
+        constellations: HashMap::from([(
+            ConstellationId(20000020),
+            SdeConstellation {
+                id: ConstellationId(20000020),
+                name: "Kimotoro".to_string(),
+                region_id: RegionId(10000002),
+            },
+        )]),
+        systems: HashMap::from([
+            (JITA, system(JITA, "Jita", vec![(50001248, 50001249)])),
+            (
+                PERIMETER,
+                system(PERIMETER, "Perimeter", vec![(50001249, 50001248)]),
+            ),
+        ]),
+        stations: HashMap::new(),
+        blueprints: HashMap::new(),
+        type_materials: HashMap::new(),
+    }
+}
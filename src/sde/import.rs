@@ -0,0 +1,1213 @@
+use super::{
+    blueprints::{SdeActivity, SdeMaterial, SdeProduct, SdeSkill},
+    Sde, SdeBlueprint, SdeError,
+};
+use crate::{
+    ids::{ConstellationId, RegionId, StationId, SystemId, TypeId},
+    repository::NameKind,
+};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+#[derive(Debug, Default)]
+pub struct TableChanges {
+    pub table: &'static str,
+    pub added: usize,
+    pub updated: usize,
+    /// Rows deleted because they are no longer in the SDE.
+    pub removed: usize,
+    /// Rows no longer in the SDE that are kept because market data may still
+    /// reference them.
+    pub stale: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub tables: Vec<TableChanges>,
+}
+
+impl ImportReport {
+    pub fn has_changes(&self) -> bool {
+        self.tables
+            .iter()
+            .any(|table| table.added + table.updated + table.removed > 0)
+    }
+}
+
+/// Upserts the SDE into the database in a single transaction.
+pub async fn import_sde(pool: &SqlitePool, sde: &Sde) -> Result<ImportReport, SdeError> {
+    let mut tx = pool.begin().await.map_err(SdeError::Sql)?;
+
+    // Market groups reference their parent and are not written in tree order,
+    // check foreign keys on commit instead.
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await
+        .map_err(SdeError::Sql)?;
+
+    let tables = vec![
+        import_regions(&mut tx, sde).await?,
+        import_constellations(&mut tx, sde).await?,
+        import_systems(&mut tx, sde).await?,
+        import_stargates(&mut tx, sde).await?,
+        import_stations(&mut tx, sde).await?,
+        import_categories(&mut tx, sde).await?,
+        import_groups(&mut tx, sde).await?,
+        import_market_groups(&mut tx, sde).await?,
+        import_meta_groups(&mut tx, sde).await?,
+        import_types(&mut tx, sde).await?,
+        import_blueprints(&mut tx, sde).await?,
+        import_type_materials(&mut tx, sde).await?,
+        import_translations(&mut tx, sde).await?,
+    ];
+
+    tx.commit().await.map_err(SdeError::Sql)?;
+
+    Ok(ImportReport { tables })
+}
+
+/// Compares the rows in the database with the rows in the SDE and returns the
+/// rows that have to be written.
+fn diff<K: Eq + Hash + Clone, V: PartialEq>(
+    table: &'static str,
+    existing: HashMap<K, V>,
+    incoming: impl Iterator<Item = (K, V)>,
+) -> (TableChanges, Vec<(K, V)>) {
+    let mut changes = TableChanges {
+        table,
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+    let mut writes = Vec::new();
+
+    for (key, value) in incoming {
+        seen.insert(key.clone());
+
+        match existing.get(&key) {
+            Some(current) if *current == value => {}
+            Some(_) => {
+                changes.updated += 1;
+                writes.push((key, value));
+            }
+            None => {
+                changes.added += 1;
+                writes.push((key, value));
+            }
+        }
+    }
+
+    changes.stale = existing.keys().filter(|key| !seen.contains(key)).count();
+
+    (changes, writes)
+}
+
+async fn import_regions(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing = sqlx::query!(r#"SELECT id as "id: RegionId", name FROM eve_region"#)
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?
+        .into_iter()
+        .map(|row| (row.id, row.name))
+        .collect::<HashMap<_, _>>();
+
+    let (changes, writes) = diff(
+        "eve_region",
+        existing,
+        sde.regions.iter().map(|(id, name)| (*id, name.clone())),
+    );
+
+    for (id, name) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_region (id, name) VALUES (?, ?) ON CONFLICT(id) DO UPDATE SET name = excluded.name",
+            id,
+            name
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+    }
+
+    Ok(changes)
+}
+
+async fn import_constellations(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing = sqlx::query!(
+        r#"SELECT id as "id: ConstellationId", name, region_id as "region_id: RegionId" FROM eve_constellation"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    .into_iter()
+    .map(|row| (row.id, (row.name, row.region_id)))
+    .collect::<HashMap<_, _>>();
+
+    let (changes, writes) = diff(
+        "eve_constellation",
+        existing,
+        sde.constellations.values().map(|constellation| {
+            (
+                constellation.id,
+                (constellation.name.clone(), constellation.region_id),
+            )
+        }),
+    );
+
+    for (id, (name, region_id)) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_constellation (id, name, region_id) VALUES (?, ?, ?) ON CONFLICT(id) DO UPDATE SET name = excluded.name, region_id = excluded.region_id",
+            id,
+            name,
+            region_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+    }
+
+    Ok(changes)
+}
+
+#[derive(PartialEq)]
+struct SystemRow {
+    name: String,
+    region_id: RegionId,
+    constellation_id: Option<ConstellationId>,
+    security: Option<f64>,
+    security_rounded: Option<f64>,
+    security_class: Option<String>,
+    x: Option<f64>,
+    y: Option<f64>,
+    z: Option<f64>,
+}
+
+async fn import_systems(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing = sqlx::query!(
+        r#"SELECT id as "id: SystemId", name, region_id as "region_id: RegionId", constellation_id as "constellation_id: ConstellationId",
+            security, security_rounded, security_class, x, y, z
+        FROM eve_system"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    .into_iter()
+    .map(|row| {
+        (
+            row.id,
+            SystemRow {
+                name: row.name,
+                region_id: row.region_id,
+                constellation_id: row.constellation_id,
+                security: row.security,
+                security_rounded: row.security_rounded,
+                security_class: row.security_class,
+                x: row.x,
+                y: row.y,
+                z: row.z,
+            },
+        )
+    })
+    .collect::<HashMap<_, _>>();
+
+    let (changes, writes) = diff(
+        "eve_system",
+        existing,
+        sde.systems.values().map(|system| {
+            let [x, y, z] = system.position;
+
+            (
+                system.id,
+                SystemRow {
+                    name: system.name.clone(),
+                    region_id: system.region_id,
+                    constellation_id: Some(system.constellation_id),
+                    security: Some(system.security),
+                    security_rounded: Some(system.security_rounded()),
+                    security_class: system.security_class.clone(),
+                    x: Some(x),
+                    y: Some(y),
+                    z: Some(z),
+                },
+            )
+        }),
+    );
+
+    for (id, system) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_system (id, name, region_id, constellation_id, security, security_rounded, security_class, x, y, z) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET name = excluded.name, region_id = excluded.region_id, constellation_id = excluded.constellation_id, security = excluded.security,
+                security_rounded = excluded.security_rounded, security_class = excluded.security_class, x = excluded.x, y = excluded.y, z = excluded.z",
+            id,
+            system.name,
+            system.region_id,
+            system.constellation_id,
+            system.security,
+            system.security_rounded,
+            system.security_class,
+            system.x,
+            system.y,
+            system.z
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+    }
+
+    Ok(changes)
+}
+
+/// Stargates have no SDE id in the database, so connections are matched on
+/// their systems and removed connections are deleted.
+async fn import_stargates(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing = sqlx::query!(
+        r#"SELECT source_system_id as "source_system_id: SystemId", target_system_id as "target_system_id: SystemId" FROM eve_stargates"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    .into_iter()
+    .map(|row| (row.source_system_id, row.target_system_id))
+    .collect::<HashSet<_>>();
+
+    let incoming = sde.stargates().into_iter().collect::<HashSet<_>>();
+
+    let mut changes = TableChanges {
+        table: "eve_stargates",
+        ..Default::default()
+    };
+
+    for (source, target) in incoming.difference(&existing) {
+        sqlx::query!(
+            "INSERT INTO eve_stargates (source_system_id, target_system_id) VALUES (?, ?)",
+            source,
+            target
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+
+        changes.added += 1;
+    }
+
+    for (source, target) in existing.difference(&incoming) {
+        sqlx::query!(
+            "DELETE FROM eve_stargates WHERE source_system_id = ? AND target_system_id = ?",
+            source,
+            target
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+
+        changes.removed += 1;
+    }
+
+    Ok(changes)
+}
+
+#[derive(PartialEq)]
+struct StationRow {
+    name: String,
+    system_id: SystemId,
+    corporation_id: i64,
+    type_id: TypeId,
+    reprocessing_efficiency: f64,
+    reprocessing_stations_take: f64,
+    services: Vec<String>,
+}
+
+/// Services are part of the station row, a changed station gets its services
+/// replaced.
+async fn import_stations(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let mut services = HashMap::<StationId, Vec<String>>::new();
+
+    for row in sqlx::query!(
+        r#"SELECT station_id as "station_id: StationId", service FROM eve_station_services ORDER BY service"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    {
+        services.entry(row.station_id).or_default().push(row.service);
+    }
+
+    let existing = sqlx::query!(
+        r#"SELECT id as "id: StationId", name, system_id as "system_id: SystemId", corporation_id, type_id as "type_id: TypeId", reprocessing_efficiency, reprocessing_stations_take FROM eve_station"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    .into_iter()
+    .map(|row| {
+        (
+            row.id,
+            StationRow {
+                name: row.name,
+                system_id: row.system_id,
+                corporation_id: row.corporation_id,
+                type_id: row.type_id,
+                reprocessing_efficiency: row.reprocessing_efficiency,
+                reprocessing_stations_take: row.reprocessing_stations_take,
+                services: services.remove(&row.id).unwrap_or_default(),
+            },
+        )
+    })
+    .collect::<HashMap<_, _>>();
+
+    let (changes, writes) = diff(
+        "eve_station",
+        existing,
+        sde.stations.values().map(|station| {
+            (
+                station.id,
+                StationRow {
+                    name: station.name.clone(),
+                    system_id: station.system_id,
+                    corporation_id: station.corporation_id,
+                    type_id: station.type_id,
+                    reprocessing_efficiency: station.reprocessing_efficiency,
+                    reprocessing_stations_take: station.reprocessing_stations_take,
+                    services: station.services.clone(),
+                },
+            )
+        }),
+    );
+
+    for (id, station) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_station (id, name, system_id, corporation_id, type_id, reprocessing_efficiency, reprocessing_stations_take) VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET name = excluded.name, system_id = excluded.system_id, corporation_id = excluded.corporation_id, type_id = excluded.type_id,
+                reprocessing_efficiency = excluded.reprocessing_efficiency, reprocessing_stations_take = excluded.reprocessing_stations_take",
+            id,
+            station.name,
+            station.system_id,
+            station.corporation_id,
+            station.type_id,
+            station.reprocessing_efficiency,
+            station.reprocessing_stations_take
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+
+        sqlx::query!("DELETE FROM eve_station_services WHERE station_id = ?", id)
+            .execute(&mut **tx)
+            .await
+            .map_err(SdeError::Sql)?;
+
+        for service in station.services {
+            sqlx::query!(
+                "INSERT INTO eve_station_services (station_id, service) VALUES (?, ?)",
+                id,
+                service
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(SdeError::Sql)?;
+        }
+    }
+
+    Ok(changes)
+}
+
+async fn import_categories(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing =
+        sqlx::query!(r#"SELECT id, name, published as "published: bool" FROM eve_categories"#)
+            .fetch_all(&mut **tx)
+            .await
+            .map_err(SdeError::Sql)?
+            .into_iter()
+            .map(|row| (row.id, (row.name, row.published)))
+            .collect::<HashMap<_, _>>();
+
+    let (changes, writes) = diff(
+        "eve_categories",
+        existing,
+        sde.categories
+            .iter()
+            .map(|(id, category)| (*id, (category.name.en.clone(), category.published))),
+    );
+
+    for (id, (name, published)) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_categories (id, name, published) VALUES (?, ?, ?) ON CONFLICT(id) DO UPDATE SET name = excluded.name, published = excluded.published",
+            id,
+            name,
+            published
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+    }
+
+    Ok(changes)
+}
+
+async fn import_groups(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing = sqlx::query!(
+        r#"SELECT id, name, category_id, published as "published: bool" FROM eve_groups"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    .into_iter()
+    .map(|row| {
+        (
+            row.id,
+            GroupRow {
+                name: row.name,
+                category_id: row.category_id,
+                published: row.published,
+            },
+        )
+    })
+    .collect::<HashMap<_, _>>();
+
+    let (changes, writes) = diff(
+        "eve_groups",
+        existing,
+        sde.groups.iter().map(|(id, group)| {
+            (
+                *id,
+                GroupRow {
+                    name: group.name.en.clone(),
+                    category_id: Some(group.category_id),
+                    published: group.published,
+                },
+            )
+        }),
+    );
+
+    for (id, group) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_groups (id, name, category_id, published) VALUES (?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET name = excluded.name, category_id = excluded.category_id, published = excluded.published",
+            id,
+            group.name,
+            group.category_id,
+            group.published
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+    }
+
+    Ok(changes)
+}
+
+#[derive(PartialEq)]
+struct GroupRow {
+    name: String,
+    category_id: Option<i64>,
+    published: bool,
+}
+
+async fn import_market_groups(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing = sqlx::query!(
+        r#"SELECT id, name, parent_id, description, has_types as "has_types: bool", icon_id FROM eve_market_groups"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    .into_iter()
+    .map(|row| {
+        (
+            row.id,
+            MarketGroupRow {
+                name: row.name,
+                parent_id: row.parent_id,
+                description: row.description,
+                has_types: row.has_types,
+                icon_id: row.icon_id,
+            },
+        )
+    })
+    .collect::<HashMap<_, _>>();
+
+    let (changes, writes) = diff(
+        "eve_market_groups",
+        existing,
+        sde.market_groups.iter().map(|(id, group)| {
+            (
+                *id,
+                MarketGroupRow {
+                    name: group.name.en.clone(),
+                    parent_id: group.parent_group_id,
+                    description: group
+                        .description
+                        .as_ref()
+                        .map(|description| description.en.clone()),
+                    has_types: group.has_types,
+                    icon_id: group.icon_id,
+                },
+            )
+        }),
+    );
+
+    for (id, group) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_market_groups (id, name, parent_id, description, has_types, icon_id) VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET name = excluded.name, parent_id = excluded.parent_id, description = excluded.description,
+                has_types = excluded.has_types, icon_id = excluded.icon_id",
+            id,
+            group.name,
+            group.parent_id,
+            group.description,
+            group.has_types,
+            group.icon_id
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+    }
+
+    Ok(changes)
+}
+
+#[derive(PartialEq)]
+struct MarketGroupRow {
+    name: String,
+    parent_id: Option<i64>,
+    description: Option<String>,
+    has_types: bool,
+    icon_id: Option<i64>,
+}
+
+async fn import_meta_groups(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing = sqlx::query!("SELECT id, name FROM eve_meta_groups")
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?
+        .into_iter()
+        .map(|row| (row.id, row.name))
+        .collect::<HashMap<_, _>>();
+
+    let (changes, writes) = diff(
+        "eve_meta_groups",
+        existing,
+        sde.meta_groups
+            .iter()
+            .map(|(id, group)| (*id, group.name.en.clone())),
+    );
+
+    for (id, name) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_meta_groups (id, name) VALUES (?, ?) ON CONFLICT(id) DO UPDATE SET name = excluded.name",
+            id,
+            name
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+    }
+
+    Ok(changes)
+}
+
+async fn import_types(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing = sqlx::query!(
+        r#"SELECT id as "id: TypeId", name, published as "published: bool", group_id, market_group_id, volume, packaged_volume, portion_size, base_price, mass,
+            meta_group_id, meta_level, tech_level
+        FROM eve_items"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    .into_iter()
+    .map(|row| {
+        (
+            row.id,
+            ItemRow {
+                name: row.name,
+                published: row.published,
+                group_id: row.group_id,
+                market_group_id: row.market_group_id,
+                volume: row.volume,
+                packaged_volume: row.packaged_volume,
+                portion_size: row.portion_size,
+                base_price: row.base_price,
+                mass: row.mass,
+                meta_group_id: row.meta_group_id,
+                meta_level: row.meta_level,
+                tech_level: row.tech_level,
+            },
+        )
+    })
+    .collect::<HashMap<_, _>>();
+
+    let (changes, writes) = diff(
+        "eve_items",
+        existing,
+        sde.types.iter().map(|(id, eve_type)| {
+            (
+                *id,
+                ItemRow {
+                    name: eve_type.name.en.clone(),
+                    published: eve_type.published,
+                    group_id: eve_type.group_id,
+                    market_group_id: eve_type.market_group_id,
+                    volume: eve_type.volume,
+                    packaged_volume: eve_type.packaged_volume(*id),
+                    portion_size: eve_type.portion_size,
+                    base_price: eve_type.base_price,
+                    mass: eve_type.mass,
+                    meta_group_id: eve_type.meta_group_id,
+                    meta_level: eve_type.meta_level,
+                    tech_level: eve_type.tech_level,
+                },
+            )
+        }),
+    );
+
+    for (id, item) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_items (id, name, published, group_id, market_group_id, volume, packaged_volume, portion_size, base_price, mass, meta_group_id, meta_level, tech_level)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET name = excluded.name, published = excluded.published, group_id = excluded.group_id, market_group_id = excluded.market_group_id,
+                volume = excluded.volume, packaged_volume = excluded.packaged_volume, portion_size = excluded.portion_size, base_price = excluded.base_price, mass = excluded.mass,
+                meta_group_id = excluded.meta_group_id, meta_level = excluded.meta_level, tech_level = excluded.tech_level",
+            id,
+            item.name,
+            item.published,
+            item.group_id,
+            item.market_group_id,
+            item.volume,
+            item.packaged_volume,
+            item.portion_size,
+            item.base_price,
+            item.mass,
+            item.meta_group_id,
+            item.meta_level,
+            item.tech_level
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+    }
+
+    Ok(changes)
+}
+
+#[derive(PartialEq)]
+struct ItemRow {
+    name: String,
+    published: bool,
+    group_id: i64,
+    market_group_id: Option<i64>,
+    volume: Option<f64>,
+    packaged_volume: Option<f64>,
+    portion_size: i64,
+    base_price: Option<f64>,
+    mass: Option<f64>,
+    meta_group_id: Option<i64>,
+    meta_level: Option<i64>,
+    tech_level: Option<i64>,
+}
+
+/// Blueprints are only referenced by other SDE data, so blueprints that are no
+/// longer in the SDE are deleted. A changed blueprint gets all its activities
+/// replaced.
+async fn import_blueprints(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let mut existing =
+        sqlx::query!(r#"SELECT id as "id: TypeId", max_production_limit FROM eve_blueprint"#)
+            .fetch_all(&mut **tx)
+            .await
+            .map_err(SdeError::Sql)?
+            .into_iter()
+            .map(|row| {
+                (
+                    row.id,
+                    SdeBlueprint {
+                        max_production_limit: row.max_production_limit,
+                        activities: Default::default(),
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+    for row in sqlx::query!(
+        r#"SELECT blueprint_id as "blueprint_id: TypeId", activity, time FROM eve_blueprint_activity"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    {
+        if let Some(blueprint) = existing.get_mut(&row.blueprint_id) {
+            blueprint.activities.insert(
+                row.activity,
+                SdeActivity {
+                    time: row.time,
+                    materials: Vec::new(),
+                    products: Vec::new(),
+                    skills: Vec::new(),
+                },
+            );
+        }
+    }
+
+    for row in sqlx::query!(
+        r#"SELECT blueprint_id as "blueprint_id: TypeId", activity, type_id as "type_id: TypeId", quantity FROM eve_blueprint_material ORDER BY type_id"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    {
+        if let Some(activity) = activity_mut(&mut existing, &row.blueprint_id, &row.activity) {
+            activity.materials.push(SdeMaterial {
+                type_id: row.type_id,
+                quantity: row.quantity,
+            });
+        }
+    }
+
+    for row in sqlx::query!(
+        r#"SELECT blueprint_id as "blueprint_id: TypeId", activity, type_id as "type_id: TypeId", quantity, probability FROM eve_blueprint_product ORDER BY type_id"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    {
+        if let Some(activity) = activity_mut(&mut existing, &row.blueprint_id, &row.activity) {
+            activity.products.push(SdeProduct {
+                type_id: row.type_id,
+                quantity: row.quantity,
+                probability: row.probability,
+            });
+        }
+    }
+
+    for row in sqlx::query!(
+        r#"SELECT blueprint_id as "blueprint_id: TypeId", activity, skill_id as "skill_id: TypeId", level FROM eve_blueprint_skill ORDER BY skill_id"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    {
+        if let Some(activity) = activity_mut(&mut existing, &row.blueprint_id, &row.activity) {
+            activity.skills.push(SdeSkill {
+                type_id: row.skill_id,
+                level: row.level,
+            });
+        }
+    }
+
+    let removed = existing
+        .keys()
+        .filter(|id| !sde.blueprints.contains_key(id))
+        .copied()
+        .collect::<Vec<_>>();
+
+    let (mut changes, writes) = diff(
+        "eve_blueprint",
+        existing
+            .iter()
+            .map(|(id, blueprint)| (*id, blueprint))
+            .collect(),
+        sde.blueprints
+            .iter()
+            .map(|(id, blueprint)| (*id, blueprint)),
+    );
+
+    for id in removed {
+        sqlx::query!("DELETE FROM eve_blueprint WHERE id = ?", id)
+            .execute(&mut **tx)
+            .await
+            .map_err(SdeError::Sql)?;
+
+        changes.removed += 1;
+    }
+    changes.stale = 0;
+
+    for (id, blueprint) in writes {
+        sqlx::query!(
+            "INSERT INTO eve_blueprint (id, max_production_limit) VALUES (?, ?) ON CONFLICT(id) DO UPDATE SET max_production_limit = excluded.max_production_limit",
+            id,
+            blueprint.max_production_limit
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+
+        for table in [
+            "eve_blueprint_activity",
+            "eve_blueprint_material",
+            "eve_blueprint_product",
+            "eve_blueprint_skill",
+        ] {
+            sqlx::query(&format!("DELETE FROM {} WHERE blueprint_id = ?", table))
+                .bind(id)
+                .execute(&mut **tx)
+                .await
+                .map_err(SdeError::Sql)?;
+        }
+
+        for (name, activity) in blueprint.activities.iter() {
+            sqlx::query!(
+                "INSERT INTO eve_blueprint_activity (blueprint_id, activity, time) VALUES (?, ?, ?)",
+                id,
+                name,
+                activity.time
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(SdeError::Sql)?;
+
+            for material in activity.materials.iter() {
+                sqlx::query!(
+                    "INSERT INTO eve_blueprint_material (blueprint_id, activity, type_id, quantity) VALUES (?, ?, ?, ?)",
+                    id,
+                    name,
+                    material.type_id,
+                    material.quantity
+                )
+                .execute(&mut **tx)
+                .await
+                .map_err(SdeError::Sql)?;
+            }
+
+            for product in activity.products.iter() {
+                sqlx::query!(
+                    "INSERT INTO eve_blueprint_product (blueprint_id, activity, type_id, quantity, probability) VALUES (?, ?, ?, ?, ?)",
+                    id,
+                    name,
+                    product.type_id,
+                    product.quantity,
+                    product.probability
+                )
+                .execute(&mut **tx)
+                .await
+                .map_err(SdeError::Sql)?;
+            }
+
+            for skill in activity.skills.iter() {
+                sqlx::query!(
+                    "INSERT INTO eve_blueprint_skill (blueprint_id, activity, skill_id, level) VALUES (?, ?, ?, ?)",
+                    id,
+                    name,
+                    skill.type_id,
+                    skill.level
+                )
+                .execute(&mut **tx)
+                .await
+                .map_err(SdeError::Sql)?;
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Like blueprints, reprocessing materials of types no longer in the SDE are
+/// deleted.
+async fn import_type_materials(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let mut existing = HashMap::<TypeId, Vec<(TypeId, i64)>>::new();
+
+    for row in sqlx::query!(
+        r#"SELECT type_id as "type_id: TypeId", material_type_id as "material_type_id: TypeId", quantity FROM type_materials ORDER BY material_type_id"#
+    )
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(SdeError::Sql)?
+    {
+        existing
+            .entry(row.type_id)
+            .or_default()
+            .push((row.material_type_id, row.quantity));
+    }
+
+    let removed = existing
+        .keys()
+        .filter(|id| !sde.type_materials.contains_key(id))
+        .copied()
+        .collect::<Vec<_>>();
+
+    let (mut changes, writes) = diff(
+        "type_materials",
+        existing,
+        sde.type_materials
+            .iter()
+            .map(|(id, materials)| (*id, materials.clone())),
+    );
+
+    for type_id in removed {
+        sqlx::query!("DELETE FROM type_materials WHERE type_id = ?", type_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(SdeError::Sql)?;
+
+        changes.removed += 1;
+    }
+    changes.stale = 0;
+
+    for (type_id, materials) in writes {
+        sqlx::query!("DELETE FROM type_materials WHERE type_id = ?", type_id)
+            .execute(&mut **tx)
+            .await
+            .map_err(SdeError::Sql)?;
+
+        for (material_type_id, quantity) in materials {
+            sqlx::query!(
+                "INSERT INTO type_materials (type_id, material_type_id, quantity) VALUES (?, ?, ?)",
+                type_id,
+                material_type_id,
+                quantity
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(SdeError::Sql)?;
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Regions, constellations, systems and stations only have English names in
+/// the SDE, they are stored with the other names so they are searched and
+/// looked up the same way.
+fn location_names(sde: &Sde) -> impl Iterator<Item = (NameKind, i64, &str, &str)> {
+    sde.regions
+        .iter()
+        .map(|(id, name)| (NameKind::Region, id.0, "en", name.as_str()))
+        .chain(sde.constellations.values().map(|constellation| {
+            (
+                NameKind::Constellation,
+                constellation.id.0,
+                "en",
+                constellation.name.as_str(),
+            )
+        }))
+        .chain(
+            sde.systems
+                .values()
+                .map(|system| (NameKind::System, system.id.0, "en", system.name.as_str())),
+        )
+        .chain(
+            sde.stations
+                .values()
+                .map(|station| (NameKind::Station, station.id.0, "en", station.name.as_str())),
+        )
+}
+
+/// Names in every language the SDE has them in. Translations are only looked
+/// up by name, so the ones no longer in the SDE are deleted.
+async fn import_translations(
+    tx: &mut Transaction<'_, Sqlite>,
+    sde: &Sde,
+) -> Result<TableChanges, SdeError> {
+    let existing = sqlx::query!("SELECT kind, id, language, name FROM eve_translations")
+        .fetch_all(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?
+        .into_iter()
+        .map(|row| ((row.kind, row.id, row.language), row.name))
+        .collect::<HashMap<_, _>>();
+
+    let incoming = sde
+        .types
+        .iter()
+        .map(|(id, eve_type)| (NameKind::Type, id.0, &eve_type.name))
+        .chain(
+            sde.groups
+                .iter()
+                .map(|(id, group)| (NameKind::Group, *id, &group.name)),
+        )
+        .chain(
+            sde.market_groups
+                .iter()
+                .map(|(id, group)| (NameKind::MarketGroup, *id, &group.name)),
+        )
+        .chain(
+            sde.categories
+                .iter()
+                .map(|(id, category)| (NameKind::Category, *id, &category.name)),
+        )
+        .chain(
+            sde.meta_groups
+                .iter()
+                .map(|(id, group)| (NameKind::MetaGroup, *id, &group.name)),
+        )
+        .flat_map(|(kind, id, name)| {
+            name.languages()
+                .map(move |(language, text)| (kind, id, language, text))
+        })
+        .chain(location_names(sde))
+        .map(|(kind, id, language, text)| {
+            (
+                (kind.as_str().to_string(), id, language.to_string()),
+                text.to_string(),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+    let removed = existing
+        .keys()
+        .filter(|key| !incoming.contains_key(key))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let (mut changes, writes) = diff("eve_translations", existing, incoming.into_iter());
+
+    for (kind, id, language) in removed {
+        sqlx::query!(
+            "DELETE FROM eve_translations WHERE kind = ? AND id = ? AND language = ?",
+            kind,
+            id,
+            language
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+
+        changes.removed += 1;
+    }
+    changes.stale = 0;
+
+    for ((kind, id, language), name) in writes {
+        let name_lower = name.to_lowercase();
+
+        sqlx::query!(
+            "INSERT INTO eve_translations (kind, id, language, name, name_lower) VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(kind, id, language) DO UPDATE SET name = excluded.name, name_lower = excluded.name_lower",
+            kind,
+            id,
+            language,
+            name,
+            name_lower
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(SdeError::Sql)?;
+    }
+
+    Ok(changes)
+}
+
+fn activity_mut<'a>(
+    blueprints: &'a mut HashMap<TypeId, SdeBlueprint>,
+    blueprint_id: &TypeId,
+    activity: &str,
+) -> Option<&'a mut SdeActivity> {
+    blueprints
+        .get_mut(blueprint_id)
+        .and_then(|blueprint| blueprint.activities.get_mut(activity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sde::fixtures::{self, JITA, PERIMETER, RIFTER, TRITANIUM};
+
+    fn table<'a>(report: &'a ImportReport, table: &str) -> &'a TableChanges {
+        report
+            .tables
+            .iter()
+            .find(|changes| changes.table == table)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn importing_again_changes_nothing() {
+        let pool = fixtures::memory_pool().await;
+        let sde = fixtures::sde();
+
+        let first = import_sde(&pool, &sde).await.unwrap();
+        assert!(first.has_changes());
+        assert_eq!(table(&first, "eve_items").added, 2);
+        assert_eq!(table(&first, "eve_stargates").added, 2);
+
+        let second = import_sde(&pool, &sde).await.unwrap();
+        assert!(!second.has_changes());
+    }
+
+    #[tokio::test]
+    async fn updates_changed_rows_and_keeps_stale_ones() {
+        let pool = fixtures::memory_pool().await;
+        let mut sde = fixtures::sde();
+        import_sde(&pool, &sde).await.unwrap();
+
+        sde.types.get_mut(&TRITANIUM).unwrap().name.en = "Tritanium Ore".to_string();
+        sde.types.remove(&RIFTER);
+        sde.systems.get_mut(&JITA).unwrap().stargates.clear();
+        sde.systems.get_mut(&PERIMETER).unwrap().stargates.clear();
+
+        let report = import_sde(&pool, &sde).await.unwrap();
+
+        let items = table(&report, "eve_items");
+        assert_eq!((items.added, items.updated, items.removed), (0, 1, 0));
+        assert_eq!(items.stale, 1);
+        assert_eq!(table(&report, "eve_stargates").removed, 2);
+
+        let names = sqlx::query_scalar!("SELECT name FROM eve_items ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(names, vec!["Tritanium Ore", "Rifter"]);
+
+        let stargates = sqlx::query_scalar!("SELECT COUNT(*) FROM eve_stargates")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stargates, 0);
+
+        // Translations are only looked up, the removed type's are deleted.
+        let translations = sqlx::query_scalar!(
+            "SELECT name FROM eve_translations WHERE kind = 'type' ORDER BY id"
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(translations, vec!["Tritanium Ore"]);
+    }
+
+    #[tokio::test]
+    async fn location_names_are_stored_as_translations() {
+        let pool = fixtures::memory_pool().await;
+        import_sde(&pool, &fixtures::sde()).await.unwrap();
+
+        let systems = sqlx::query_scalar!(
+            "SELECT name_lower FROM eve_translations WHERE kind = 'system' AND language = 'en' ORDER BY id"
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(systems, vec!["jita", "perimeter"]);
+    }
+}
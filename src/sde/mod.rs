@@ -0,0 +1,732 @@
+use crate::ids::{ConstellationId, RegionId, StationId, SystemId, TypeId};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    path::PathBuf,
+};
+
+pub use blueprints::SdeBlueprint;
+pub use diff::diff_sde;
+pub use import::import_sde;
+pub use source::{verify_checksum, SdeSource};
+
+mod blueprints;
+mod diff;
+#[cfg(test)]
+pub(crate) mod fixtures;
+mod import;
+mod source;
+
+#[derive(Debug)]
+pub enum SdeError {
+    Io(std::io::Error, PathBuf),
+    Yaml(serde_yaml::Error, PathBuf),
+    Zip(zip::result::ZipError, PathBuf),
+    MissingFile(String),
+    /// Expected and actual MD5 of the archive.
+    ChecksumMismatch(String, String),
+    MissingName(i64),
+    MissingGroup(TypeId, i64),
+    MissingMarketGroup(TypeId, i64),
+    MissingMetaGroup(TypeId, i64),
+    MissingCategory(i64, i64),
+    MissingParentMarketGroup(i64, i64),
+    Sql(sqlx::Error),
+}
+
+impl Display for SdeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SdeError::Io(e, path) => write!(f, "Could not read {:?}: {}", path, e),
+            SdeError::Yaml(e, path) => write!(f, "Could not parse {:?}: {}", path, e),
+            SdeError::Zip(e, path) => write!(f, "Could not open {:?}: {}", path, e),
+            SdeError::MissingFile(file) => write!(f, "{} is missing", file),
+            SdeError::ChecksumMismatch(expected, actual) => {
+                write!(
+                    f,
+                    "expected MD5 {} but the archive has {}",
+                    expected, actual
+                )
+            }
+            SdeError::MissingName(id) => write!(f, "item {} has no name", id),
+            SdeError::MissingGroup(type_id, group_id) => {
+                write!(f, "type {} has unknown group {}", type_id, group_id)
+            }
+            SdeError::MissingMarketGroup(type_id, market_group_id) => write!(
+                f,
+                "type {} has unknown market group {}",
+                type_id, market_group_id
+            ),
+            SdeError::MissingMetaGroup(type_id, meta_group_id) => write!(
+                f,
+                "type {} has unknown meta group {}",
+                type_id, meta_group_id
+            ),
+            SdeError::MissingCategory(group_id, category_id) => {
+                write!(f, "group {} has unknown category {}", group_id, category_id)
+            }
+            SdeError::MissingParentMarketGroup(market_group_id, parent_group_id) => write!(
+                f,
+                "market group {} has unknown parent {}",
+                market_group_id, parent_group_id
+            ),
+            SdeError::Sql(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// The parts of the static data export that are imported into the database.
+pub struct Sde {
+    pub categories: HashMap<i64, EveCategory>,
+    pub market_groups: HashMap<i64, EveMarketGroup>,
+    pub meta_groups: HashMap<i64, EveMetaGroup>,
+    pub groups: HashMap<i64, EveGroup>,
+    pub types: HashMap<TypeId, EveType>,
+    pub regions: HashMap<RegionId, String>,
+    pub constellations: HashMap<ConstellationId, SdeConstellation>,
+    pub systems: HashMap<SystemId, SdeSystem>,
+    pub stations: HashMap<StationId, SdeStation>,
+    pub blueprints: HashMap<TypeId, SdeBlueprint>,
+    /// Reprocessing yield per type as (material, quantity) for one portion.
+    pub type_materials: HashMap<TypeId, Vec<(TypeId, i64)>>,
+}
+
+impl Sde {
+    /// Stargate connections as (source system, target system), resolved from
+    /// the destination gate of every stargate.
+    pub fn stargates(&self) -> Vec<(SystemId, SystemId)> {
+        let stargate_system = self
+            .systems
+            .values()
+            .flat_map(|system| system.stargates.iter().map(|(gate, _)| (*gate, system.id)))
+            .collect::<HashMap<_, _>>();
+
+        let mut stargates = self
+            .systems
+            .values()
+            .flat_map(|system| {
+                let stargate_system = &stargate_system;
+                system
+                    .stargates
+                    .iter()
+                    .filter_map(
+                        move |(gate, destination)| match stargate_system.get(destination) {
+                            Some(target) => Some((system.id, *target)),
+                            None => {
+                                log::warn!(
+                                    "Stargate {} in system {} leads to unknown gate {}",
+                                    gate,
+                                    system.id,
+                                    destination
+                                );
+                                None
+                            }
+                        },
+                    )
+            })
+            .collect::<Vec<_>>();
+
+        stargates.sort();
+        stargates.dedup();
+        stargates
+    }
+}
+
+pub fn read_sde(source: &mut SdeSource) -> Result<Sde, SdeError> {
+    log::info!("Loading categories");
+    let categories: HashMap<i64, EveCategory> = source.load_yaml("fsd/categoryIDs.yaml")?;
+
+    log::info!("Loading market groups");
+    let market_groups: HashMap<i64, EveMarketGroup> = source.load_yaml("fsd/marketGroups.yaml")?;
+
+    for (market_group_id, market_group) in market_groups.iter() {
+        if let Some(parent_group_id) = market_group.parent_group_id {
+            if !market_groups.contains_key(&parent_group_id) {
+                return Err(SdeError::MissingParentMarketGroup(
+                    *market_group_id,
+                    parent_group_id,
+                ));
+            }
+        }
+    }
+
+    log::info!("Loading groups");
+    let groups: HashMap<i64, EveGroup> = source.load_yaml("fsd/groupIDs.yaml")?;
+
+    for (group_id, group) in groups.iter() {
+        if !categories.contains_key(&group.category_id) {
+            return Err(SdeError::MissingCategory(*group_id, group.category_id));
+        }
+    }
+
+    log::info!("Loading meta groups");
+    let meta_groups: HashMap<i64, EveMetaGroup> = source.load_yaml("fsd/metaGroups.yaml")?;
+
+    log::info!("Loading types");
+    let mut types: HashMap<TypeId, EveType> = source.load_yaml("fsd/typeIDs.yaml")?;
+
+    for (type_id, eve_type) in types.iter() {
+        if !groups.contains_key(&eve_type.group_id) {
+            return Err(SdeError::MissingGroup(*type_id, eve_type.group_id));
+        }
+
+        if let Some(market_group_id) = eve_type.market_group_id {
+            if !market_groups.contains_key(&market_group_id) {
+                return Err(SdeError::MissingMarketGroup(*type_id, market_group_id));
+            }
+        }
+
+        if let Some(meta_group_id) = eve_type.meta_group_id {
+            if !meta_groups.contains_key(&meta_group_id) {
+                return Err(SdeError::MissingMetaGroup(*type_id, meta_group_id));
+            }
+        }
+    }
+
+    log::info!("Loading type attributes");
+    let type_dogma: HashMap<TypeId, TypeDogma> = source.load_yaml("fsd/typeDogma.yaml")?;
+    for (type_id, dogma) in type_dogma {
+        if let Some(eve_type) = types.get_mut(&type_id) {
+            eve_type.meta_level = dogma.attribute(ATTRIBUTE_META_LEVEL);
+            eve_type.tech_level = dogma.attribute(ATTRIBUTE_TECH_LEVEL);
+        }
+    }
+
+    log::info!("Loading blueprints");
+    let mut blueprints: HashMap<TypeId, SdeBlueprint> = source.load_yaml("fsd/blueprints.yaml")?;
+    blueprints
+        .values_mut()
+        .flat_map(|blueprint| blueprint.activities.values_mut())
+        .for_each(|activity| activity.normalize());
+
+    log::info!("Loading type materials");
+    let type_materials = source
+        .load_yaml::<HashMap<TypeId, TypeMaterials>>("fsd/typeMaterials.yaml")?
+        .into_iter()
+        .map(|(type_id, materials)| {
+            let mut materials = materials
+                .materials
+                .into_iter()
+                .map(|material| (material.material_type_id, material.quantity))
+                .collect::<Vec<_>>();
+            materials.sort();
+
+            (type_id, materials)
+        })
+        .collect();
+
+    log::info!("Loading regions");
+    let universe = load_universe(source)?;
+
+    log::info!("Loading stations");
+    let stations = load_stations(source)?;
+
+    Ok(Sde {
+        categories,
+        market_groups,
+        meta_groups,
+        groups,
+        types,
+        regions: universe.regions,
+        constellations: universe.constellations,
+        systems: universe.systems,
+        stations,
+        blueprints,
+        type_materials,
+    })
+}
+
+struct Universe {
+    regions: HashMap<RegionId, String>,
+    constellations: HashMap<ConstellationId, SdeConstellation>,
+    systems: HashMap<SystemId, SdeSystem>,
+}
+
+/// Regions, constellations and systems live in
+/// `fsd/universe/eve/<region>/<constellation>/<system>/`, so the region and
+/// constellation of a system are taken from the folders it is in.
+fn load_universe(source: &mut SdeSource) -> Result<Universe, SdeError> {
+    let inv_names = source
+        .load_yaml::<Vec<InvItem>>("bsd/invNames.yaml")?
+        .into_iter()
+        .map(|x| (x.item_id, x.item_name))
+        .collect::<HashMap<_, _>>();
+
+    let name = |id: i64| {
+        inv_names
+            .get(&id)
+            .map(|name| name.trim().to_string())
+            .ok_or(SdeError::MissingName(id))
+    };
+
+    let files = source.files("fsd/universe/eve/")?;
+    let folder = |file: &str, depth: usize| {
+        file.split('/')
+            .skip(3)
+            .take(depth)
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+
+    let mut region_folders = HashMap::new();
+    let mut constellation_folders = HashMap::new();
+    let mut universe = Universe {
+        regions: HashMap::new(),
+        constellations: HashMap::new(),
+        systems: HashMap::new(),
+    };
+
+    for file in files
+        .iter()
+        .filter(|file| file.ends_with("/region.staticdata"))
+    {
+        let static_data: RegionStaticData = source.load_yaml(file)?;
+
+        region_folders.insert(folder(file, 1), static_data.region_id);
+        universe
+            .regions
+            .insert(static_data.region_id, name(static_data.region_id.0)?);
+    }
+
+    for file in files
+        .iter()
+        .filter(|file| file.ends_with("/constellation.staticdata"))
+    {
+        let static_data: ConstellationStaticData = source.load_yaml(file)?;
+        let region_id = *region_folders
+            .get(&folder(file, 1))
+            .ok_or_else(|| SdeError::MissingFile(format!("{} region.staticdata", file)))?;
+
+        constellation_folders.insert(folder(file, 2), static_data.constellation_id);
+        universe.constellations.insert(
+            static_data.constellation_id,
+            SdeConstellation {
+                id: static_data.constellation_id,
+                name: name(static_data.constellation_id.0)?,
+                region_id,
+            },
+        );
+    }
+
+    for file in files
+        .iter()
+        .filter(|file| file.ends_with("/solarsystem.staticdata"))
+    {
+        let static_data: SolarSystemStaticData = source.load_yaml(file)?;
+        let region_id = *region_folders
+            .get(&folder(file, 1))
+            .ok_or_else(|| SdeError::MissingFile(format!("{} region.staticdata", file)))?;
+        let constellation_id = *constellation_folders
+            .get(&folder(file, 2))
+            .ok_or_else(|| SdeError::MissingFile(format!("{} constellation.staticdata", file)))?;
+
+        universe.systems.insert(
+            static_data.solar_system_id,
+            SdeSystem {
+                id: static_data.solar_system_id,
+                name: name(static_data.solar_system_id.0)?,
+                region_id,
+                constellation_id,
+                security: static_data.security,
+                security_class: static_data.security_class,
+                position: static_data.center,
+                stargates: static_data
+                    .stargates
+                    .iter()
+                    .map(|(gate_id, gate)| (*gate_id, gate.destination_gate))
+                    .collect(),
+            },
+        );
+    }
+
+    Ok(universe)
+}
+
+impl SdeSystem {
+    /// Security status as shown in game: rounded to one decimal, except that
+    /// anything above 0.0 shows as at least 0.1.
+    pub fn security_rounded(&self) -> f64 {
+        if self.security > 0.0 && self.security < 0.05 {
+            0.1
+        } else {
+            (self.security * 10.0).round() / 10.0
+        }
+    }
+}
+
+/// NPC stations with the names of the services their operation offers.
+fn load_stations(source: &mut SdeSource) -> Result<HashMap<StationId, SdeStation>, SdeError> {
+    let services: HashMap<i64, StationService> = source.load_yaml("fsd/stationServices.yaml")?;
+    let operations: HashMap<i64, StationOperation> =
+        source.load_yaml("fsd/stationOperations.yaml")?;
+    let stations: Vec<StaStation> = source.load_yaml("bsd/staStations.yaml")?;
+
+    Ok(stations
+        .into_iter()
+        .map(|station| {
+            let mut station_services = operations
+                .get(&station.operation_id)
+                .map(|operation| {
+                    operation
+                        .services
+                        .iter()
+                        .filter_map(|service_id| services.get(service_id))
+                        .map(|service| service.name.en.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            station_services.sort();
+            station_services.dedup();
+
+            (
+                station.station_id,
+                SdeStation {
+                    id: station.station_id,
+                    name: station.station_name,
+                    system_id: station.solar_system_id,
+                    corporation_id: station.corporation_id,
+                    type_id: station.station_type_id,
+                    reprocessing_efficiency: station.reprocessing_efficiency,
+                    reprocessing_stations_take: station.reprocessing_stations_take,
+                    services: station_services,
+                },
+            )
+        })
+        .collect())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InvItem {
+    #[serde(rename = "itemID")]
+    item_id: i64,
+    item_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EveType {
+    pub name: Translation,
+    #[serde(rename = "groupID")]
+    pub group_id: i64,
+    pub published: bool,
+    #[serde(rename = "marketGroupID")]
+    pub market_group_id: Option<i64>,
+    pub volume: Option<f64>,
+    #[serde(default = "default_portion_size")]
+    pub portion_size: i64,
+    pub base_price: Option<f64>,
+    pub mass: Option<f64>,
+    #[serde(rename = "metaGroupID")]
+    pub meta_group_id: Option<i64>,
+    /// From the dogma attributes in `typeDogma.yaml`.
+    #[serde(skip)]
+    pub meta_level: Option<i64>,
+    #[serde(skip)]
+    pub tech_level: Option<i64>,
+}
+
+fn default_portion_size() -> i64 {
+    1
+}
+
+const ATTRIBUTE_TECH_LEVEL: i64 = 422;
+const ATTRIBUTE_META_LEVEL: i64 = 633;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TypeDogma {
+    #[serde(default)]
+    dogma_attributes: Vec<DogmaAttribute>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DogmaAttribute {
+    #[serde(rename = "attributeID")]
+    attribute_id: i64,
+    value: f64,
+}
+
+impl TypeDogma {
+    /// Value of an attribute that only holds whole numbers, like levels.
+    fn attribute(&self, attribute_id: i64) -> Option<i64> {
+        self.dogma_attributes
+            .iter()
+            .find(|attribute| attribute.attribute_id == attribute_id)
+            .map(|attribute| attribute.value.round() as i64)
+    }
+}
+
+/// Volume of ships when repackaged, per group. The SDE only contains the
+/// assembled volume, these are the fixed sizes the game uses per hull class.
+const PACKAGED_VOLUMES: [(i64, f64); 42] = [
+    (25, 2_500.0),       // Frigate
+    (237, 2_500.0),      // Corvette
+    (324, 2_500.0),      // Assault Frigate
+    (830, 2_500.0),      // Covert Ops
+    (831, 2_500.0),      // Interceptor
+    (834, 2_500.0),      // Stealth Bomber
+    (893, 2_500.0),      // Electronic Attack Ship
+    (1283, 2_500.0),     // Expedition Frigate
+    (1527, 2_500.0),     // Logistics Frigate
+    (420, 5_000.0),      // Destroyer
+    (541, 5_000.0),      // Interdictor
+    (1305, 5_000.0),     // Tactical Destroyer
+    (1534, 5_000.0),     // Command Destroyer
+    (963, 5_000.0),      // Strategic Cruiser
+    (26, 10_000.0),      // Cruiser
+    (358, 10_000.0),     // Heavy Assault Cruiser
+    (832, 10_000.0),     // Logistics
+    (833, 10_000.0),     // Force Recon Ship
+    (894, 10_000.0),     // Heavy Interdiction Cruiser
+    (906, 10_000.0),     // Combat Recon Ship
+    (1972, 10_000.0),    // Flag Cruiser
+    (419, 15_000.0),     // Combat Battlecruiser
+    (540, 15_000.0),     // Command Ship
+    (1201, 15_000.0),    // Attack Battlecruiser
+    (27, 50_000.0),      // Battleship
+    (898, 50_000.0),     // Black Ops
+    (900, 50_000.0),     // Marauder
+    (28, 20_000.0),      // Hauler
+    (380, 20_000.0),     // Deep Space Transport
+    (1202, 20_000.0),    // Blockade Runner
+    (463, 3_750.0),      // Mining Barge
+    (543, 3_750.0),      // Exhumer
+    (31, 500.0),         // Shuttle
+    (883, 1_000_000.0),  // Capital Industrial Ship
+    (513, 1_000_000.0),  // Freighter
+    (902, 1_000_000.0),  // Jump Freighter
+    (547, 1_000_000.0),  // Carrier
+    (485, 1_000_000.0),  // Dreadnought
+    (4594, 1_000_000.0), // Lancer Dreadnought
+    (1538, 1_000_000.0), // Force Auxiliary
+    (659, 10_000_000.0), // Supercarrier
+    (30, 10_000_000.0),  // Titan
+];
+
+/// Packaged volume of hulls in classes without a single size, these take
+/// precedence over the group.
+const PACKAGED_VOLUME_OVERRIDES: [(TypeId, f64); 2] = [
+    (TypeId(42244), 50_000.0),  // Porpoise
+    (TypeId(28606), 250_000.0), // Orca
+];
+
+impl EveType {
+    /// Volume of the type `type_id` when packaged, falls back to the assembled
+    /// volume for everything that does not shrink when repackaged.
+    pub fn packaged_volume(&self, type_id: TypeId) -> Option<f64> {
+        let override_volume = PACKAGED_VOLUME_OVERRIDES
+            .iter()
+            .find(|(id, _)| *id == type_id)
+            .map(|(_, volume)| *volume);
+        let group_volume = PACKAGED_VOLUMES
+            .iter()
+            .find(|(group_id, _)| *group_id == self.group_id)
+            .map(|(_, volume)| *volume);
+        override_volume.or(group_volume).or(self.volume)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TypeMaterials {
+    materials: Vec<TypeMaterial>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TypeMaterial {
+    #[serde(rename = "materialTypeID")]
+    material_type_id: TypeId,
+    quantity: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EveCategory {
+    pub name: Translation,
+    #[serde(default)]
+    pub published: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EveGroup {
+    pub name: Translation,
+    #[serde(rename = "categoryID")]
+    pub category_id: i64,
+    #[serde(default)]
+    pub published: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EveMetaGroup {
+    #[serde(rename = "nameID")]
+    pub name: Translation,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EveMarketGroup {
+    #[serde(rename = "nameID")]
+    pub name: Translation,
+    #[serde(rename = "parentGroupID")]
+    pub parent_group_id: Option<i64>,
+    #[serde(rename = "descriptionID")]
+    pub description: Option<Translation>,
+    #[serde(default)]
+    pub has_types: bool,
+    #[serde(rename = "iconID")]
+    pub icon_id: Option<i64>,
+}
+
+/// A localized SDE string, English is always present.
+#[derive(Debug, Deserialize)]
+pub struct Translation {
+    pub en: String,
+    /// The other languages by code, e.g. `de`, `ru` or `zh`.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, String>,
+}
+
+impl Translation {
+    /// All languages including English as (language, text).
+    pub fn languages(&self) -> impl Iterator<Item = (&str, &str)> {
+        std::iter::once(("en", self.en.as_str())).chain(
+            self.other
+                .iter()
+                .map(|(language, text)| (language.as_str(), text.as_str())),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegionStaticData {
+    #[serde(rename = "regionID")]
+    region_id: RegionId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConstellationStaticData {
+    #[serde(rename = "constellationID")]
+    constellation_id: ConstellationId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SolarSystemStaticData {
+    #[serde(rename = "solarSystemID")]
+    solar_system_id: SystemId,
+    security: f64,
+    security_class: Option<String>,
+    center: [f64; 3],
+    #[serde(default)]
+    stargates: HashMap<i64, SolarSystemStaticGateData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SolarSystemStaticGateData {
+    #[serde(rename = "destination")]
+    destination_gate: i64,
+}
+
+pub struct SdeConstellation {
+    pub id: ConstellationId,
+    pub name: String,
+    pub region_id: RegionId,
+}
+
+pub struct SdeSystem {
+    pub id: SystemId,
+    pub name: String,
+    pub region_id: RegionId,
+    pub constellation_id: ConstellationId,
+    /// True security status, see [`SdeSystem::security_rounded`] for the one
+    /// shown in game.
+    pub security: f64,
+    pub security_class: Option<String>,
+    /// x, y, z in meters.
+    pub position: [f64; 3],
+    pub stargates: Vec<(i64, i64)>, // source stargate id, target stargate id
+}
+
+pub struct SdeStation {
+    pub id: StationId,
+    pub name: String,
+    pub system_id: SystemId,
+    pub corporation_id: i64,
+    pub type_id: TypeId,
+    pub reprocessing_efficiency: f64,
+    pub reprocessing_stations_take: f64,
+    pub services: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StaStation {
+    #[serde(rename = "stationID")]
+    station_id: StationId,
+    station_name: String,
+    #[serde(rename = "solarSystemID")]
+    solar_system_id: SystemId,
+    #[serde(rename = "corporationID")]
+    corporation_id: i64,
+    #[serde(rename = "stationTypeID")]
+    station_type_id: TypeId,
+    #[serde(rename = "operationID")]
+    operation_id: i64,
+    reprocessing_efficiency: f64,
+    reprocessing_stations_take: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct StationOperation {
+    #[serde(default)]
+    services: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StationService {
+    #[serde(rename = "serviceNameID")]
+    name: Translation,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{eve_type, TRITANIUM};
+    use crate::ids::TypeId;
+
+    #[test]
+    fn ships_use_their_packaged_volume() {
+        let hulls = [
+            ("Raven", 638, 27, 486_000.0, 50_000.0),
+            ("Naglfar", 19722, 485, 13_950_000.0, 1_000_000.0),
+            ("Avatar", 11567, 30, 2_550_000_000.0, 10_000_000.0),
+            ("Porpoise", 42244, 941, 5_000_000.0, 50_000.0),
+            ("Orca", 28606, 941, 10_250_000.0, 250_000.0),
+        ];
+        for (name, type_id, group_id, volume, packaged) in hulls {
+            assert_eq!(
+                eve_type(name, group_id, None, Some(volume)).packaged_volume(TypeId(type_id)),
+                Some(packaged),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn other_types_fall_back_to_their_volume() {
+        assert_eq!(
+            eve_type("Tritanium", 18, None, Some(0.01)).packaged_volume(TRITANIUM),
+            Some(0.01)
+        );
+        assert_eq!(
+            eve_type("Tritanium", 18, None, None).packaged_volume(TRITANIUM),
+            None
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use super::SdeError;
+use md5::{Digest, Md5};
+use serde::de::DeserializeOwned;
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+use zip::ZipArchive;
+
+/// Where the SDE is read from: an extracted `sde` folder or the `sde.zip`
+/// archive as downloaded from CCP. Files are addressed by their path relative
+/// to the `sde` folder, e.g. `fsd/typeIDs.yaml`.
+pub enum SdeSource {
+    Directory(PathBuf),
+    Zip {
+        path: PathBuf,
+        archive: ZipArchive<BufReader<File>>,
+        prefix: String,
+    },
+}
+
+impl SdeSource {
+    pub fn open(path: &Path) -> Result<Self, SdeError> {
+        if path.is_dir() {
+            return Ok(SdeSource::Directory(path.to_path_buf()));
+        }
+
+        let file = File::open(path).map_err(|e| SdeError::Io(e, path.to_path_buf()))?;
+        let archive = ZipArchive::new(BufReader::new(file))
+            .map_err(|e| SdeError::Zip(e, path.to_path_buf()))?;
+
+        // The archive wraps everything in an `sde/` folder, find it from a
+        // file that is always there instead of relying on the name.
+        let prefix = archive
+            .file_names()
+            .find_map(|name| name.strip_suffix("fsd/typeIDs.yaml"))
+            .map(|prefix| prefix.to_string())
+            .ok_or_else(|| SdeError::MissingFile("fsd/typeIDs.yaml".to_string()))?;
+
+        Ok(SdeSource::Zip {
+            path: path.to_path_buf(),
+            archive,
+            prefix,
+        })
+    }
+
+    /// Relative paths of all files under `folder`, using `/` as separator.
+    pub fn files(&self, folder: &str) -> Result<Vec<String>, SdeError> {
+        match self {
+            SdeSource::Directory(root) => {
+                let mut files = Vec::new();
+                walk(root, &root.join(folder), &mut files)?;
+                files.sort();
+                Ok(files)
+            }
+            SdeSource::Zip {
+                archive, prefix, ..
+            } => {
+                let mut files = archive
+                    .file_names()
+                    .filter_map(|name| name.strip_prefix(prefix.as_str()))
+                    .filter(|name| name.starts_with(folder) && !name.ends_with('/'))
+                    .map(|name| name.to_string())
+                    .collect::<Vec<_>>();
+                files.sort();
+                Ok(files)
+            }
+        }
+    }
+
+    /// Deserializes a YAML file, streaming it straight out of the archive.
+    pub fn load_yaml<T: DeserializeOwned>(&mut self, name: &str) -> Result<T, SdeError> {
+        match self {
+            SdeSource::Directory(root) => {
+                let path = root.join(name);
+                let file = File::open(&path).map_err(|e| SdeError::Io(e, path.clone()))?;
+                serde_yaml::from_reader(BufReader::new(file)).map_err(|e| SdeError::Yaml(e, path))
+            }
+            SdeSource::Zip {
+                path,
+                archive,
+                prefix,
+            } => {
+                let entry = archive
+                    .by_name(&format!("{}{}", prefix, name))
+                    .map_err(|e| SdeError::Zip(e, path.join(name)))?;
+                serde_yaml::from_reader(BufReader::new(entry))
+                    .map_err(|e| SdeError::Yaml(e, path.join(name)))
+            }
+        }
+    }
+}
+
+fn walk(root: &Path, folder: &Path, files: &mut Vec<String>) -> Result<(), SdeError> {
+    let entries = std::fs::read_dir(folder).map_err(|e| SdeError::Io(e, folder.to_path_buf()))?;
+
+    for entry in entries {
+        let path = entry
+            .map_err(|e| SdeError::Io(e, folder.to_path_buf()))?
+            .path();
+
+        if path.is_dir() {
+            walk(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let relative = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push(relative);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks `archive` against the MD5 checksum file CCP publishes next to
+/// `sde.zip`.
+pub fn verify_checksum(archive: &Path, checksum: &Path) -> Result<(), SdeError> {
+    let expected = std::fs::read_to_string(checksum)
+        .map_err(|e| SdeError::Io(e, checksum.to_path_buf()))?
+        .split_whitespace()
+        .next()
+        .map(|checksum| checksum.to_lowercase())
+        .ok_or_else(|| SdeError::MissingFile(checksum.to_string_lossy().to_string()))?;
+
+    let mut file = File::open(archive).map_err(|e| SdeError::Io(e, archive.to_path_buf()))?;
+    let mut hasher = Md5::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| SdeError::Io(e, archive.to_path_buf()))?;
+
+    let actual = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    if actual != expected {
+        return Err(SdeError::ChecksumMismatch(expected, actual));
+    }
+
+    Ok(())
+}